@@ -0,0 +1,62 @@
+//! Compares the socket table backends end to end: `cargo bench --bench backends`.
+//!
+//! Each backend runs `portman check` (one scan of the listening sockets) and
+//! `portman list` (the scan plus connections and annotations) repeatedly
+//! while a few hundred extra listeners are open, as on a busy host.
+
+use std::net::TcpListener;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 20;
+const LISTENERS: usize = 300;
+
+fn time_runs(backend: &str, args: &[&str]) -> Option<(Duration, Duration)> {
+    let mut total = Duration::ZERO;
+    let mut fastest = Duration::MAX;
+    for _ in 0..RUNS {
+        let started = Instant::now();
+        let output = Command::new(env!("CARGO_BIN_EXE_portman"))
+            .args(["--backend", backend])
+            .args(args)
+            .output()
+            .ok()?;
+        let elapsed = started.elapsed();
+        // `check` exits 1 for an occupied port, anything else is a failure
+        if !output.status.success() && output.status.code() != Some(1) {
+            return None;
+        }
+        total += elapsed;
+        fastest = fastest.min(elapsed);
+    }
+    Some((total / RUNS, fastest))
+}
+
+fn main() {
+    let listeners: Vec<TcpListener> = (0..LISTENERS)
+        .filter_map(|_| TcpListener::bind("127.0.0.1:0").ok())
+        .collect();
+    let port = listeners[0].local_addr().unwrap().port().to_string();
+
+    println!("{} runs each, {} extra listeners", RUNS, listeners.len());
+    println!(
+        "{:<10} {:<28} {:>10} {:>10}",
+        "backend", "command", "mean", "fastest"
+    );
+    for backend in ["netlink", "procfs", "netstat"] {
+        for args in [
+            vec!["check", port.as_str()],
+            vec!["list", "--format", "json"],
+        ] {
+            let command = args.join(" ");
+            match time_runs(backend, &args) {
+                Some((mean, fastest)) => println!(
+                    "{backend:<10} {command:<28} {:>8.2}ms {:>8.2}ms",
+                    mean.as_secs_f64() * 1000.0,
+                    fastest.as_secs_f64() * 1000.0
+                ),
+                None => println!("{backend:<10} {command:<28} {:>10}", "unavailable"),
+            }
+        }
+    }
+}
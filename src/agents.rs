@@ -0,0 +1,115 @@
+use crate::config::user_data_dir;
+use crate::scanner::PortInfo;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use sysinfo::{Pid, System};
+
+/// A long-running portman process (`hold`, `forward`) that owns ports.
+///
+/// Each one writes `agents/<pid>.json` to the user data dir while it runs so
+/// that `list` can describe its sockets instead of showing a bare `portman`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Agent {
+    pub pid: u32,
+    pub kind: String,
+    /// What each port is used for, e.g. `TCP 127.0.0.1:3000, HTTP 503`
+    pub ports: BTreeMap<u16, String>,
+}
+
+/// Removes the agent file when the agent stops
+pub struct AgentRegistration {
+    path: PathBuf,
+}
+
+impl Drop for AgentRegistration {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn agents_dir() -> Option<PathBuf> {
+    user_data_dir().map(|dir| dir.join("agents"))
+}
+
+/// Announce the current process as an agent
+pub fn register(kind: &str, ports: BTreeMap<u16, String>) -> Result<AgentRegistration> {
+    let dir = agents_dir().context("Could not determine the user data directory")?;
+    fs::create_dir_all(&dir)?;
+
+    // Drop files left behind by agents that were killed
+    let mut system = System::new();
+    for agent in registered() {
+        if !system.refresh_process(Pid::from_u32(agent.pid)) {
+            let _ = fs::remove_file(dir.join(format!("{pid}.json", pid = agent.pid)));
+        }
+    }
+
+    let agent = Agent {
+        pid: std::process::id(),
+        kind: kind.to_string(),
+        ports,
+    };
+    let path = dir.join(format!("{pid}.json", pid = agent.pid));
+    fs::write(&path, serde_json::to_string_pretty(&agent)?)
+        .with_context(|| format!("Failed to register agent: {}", path.display()))?;
+
+    Ok(AgentRegistration { path })
+}
+
+/// Registered agents; a stale file cannot match anything in `annotate`
+/// because its PID no longer owns the ports
+pub fn registered() -> Vec<Agent> {
+    let Some(entries) = agents_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect()
+}
+
+/// Replace the command of ports held by agents with what the agent does
+pub fn annotate(ports: &mut [PortInfo], agents: &[Agent]) {
+    for port_info in ports {
+        let description = agents
+            .iter()
+            .filter(|agent| Some(agent.pid) == port_info.pid)
+            .find_map(|agent| {
+                let description = agent.ports.get(&port_info.port)?;
+                Some(format!("portman {kind}: {description}", kind = agent.kind))
+            });
+        if let Some(description) = description {
+            port_info.command = description;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_matches_pid_and_port() {
+        let port_info = |port, pid| PortInfo {
+            command: "portman hold 3000".to_string(),
+            ..PortInfo::listener(port, pid, "portman")
+        };
+        let mut ports = vec![port_info(3000, 42), port_info(3000, 7), port_info(3001, 42)];
+        let agents = vec![Agent {
+            pid: 42,
+            kind: "hold".to_string(),
+            ports: BTreeMap::from([(3000, "TCP 127.0.0.1:3000".to_string())]),
+        }];
+
+        annotate(&mut ports, &agents);
+
+        assert_eq!(ports[0].command, "portman hold: TCP 127.0.0.1:3000");
+        assert_eq!(ports[1].command, "portman hold 3000");
+        assert_eq!(ports[2].command, "portman hold 3000");
+    }
+}
@@ -1,4 +1,7 @@
 use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "portman")]
@@ -7,6 +10,14 @@ use clap::{Parser, Subcommand};
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// Show what destructive commands would do without sending any signals
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Where to read listening sockets and connections from (default: netlink, then procfs on Linux)
+    #[arg(long, global = true, value_enum)]
+    pub backend: Option<ScanBackend>,
 }
 
 #[derive(Subcommand)]
@@ -21,13 +32,68 @@ pub enum Commands {
         #[arg(long)]
         filter: Option<String>,
 
-        /// Show only common development ports
+        /// Filter by well-known service name (e.g., postgres, redis, vite)
+        #[arg(long)]
+        service: Option<String>,
+
+        /// Show only common development ports (same as --set common)
         #[arg(long)]
         common: bool,
 
+        /// Show only ports in the named port set(s) (e.g., db or web,debuggers)
+        #[arg(long, value_name = "NAME[,NAME...]", value_delimiter = ',')]
+        set: Vec<String>,
+
+        /// Show only ports opened from the project with this name
+        #[arg(long, value_name = "NAME")]
+        project: Option<String>,
+
+        /// Show only ports opened from inside the project at this path (e.g. . for the current repo)
+        #[arg(long, value_name = "PATH")]
+        project_dir: Option<PathBuf>,
+
+        /// List ports from every network namespace (containers, `ip netns`, ...)
+        #[arg(long, conflicts_with = "netns")]
+        all_namespaces: bool,
+
+        /// List ports from one network namespace, given by PID or namespace file (e.g. /run/netns/blue)
+        #[arg(long, value_name = "PATH|PID")]
+        netns: Option<String>,
+
+        /// Show established client connections to the listed ports instead
+        #[arg(long)]
+        connections: bool,
+
+        /// Show listening Unix domain sockets instead of ports (Linux); only --filter applies to them
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "connections",
+                "range",
+                "service",
+                "common",
+                "set",
+                "project",
+                "project_dir",
+                "all_namespaces",
+                "netns",
+            ]
+        )]
+        unix: bool,
+
+        /// Output format (defaults to `output_format` from config, then table)
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+
+    /// Show which processes are connected to which local listeners
+    Graph {
+        /// Only show clients of these ports (e.g., 5432)
+        ports: Vec<u16>,
+
         /// Output format
-        #[arg(long, value_enum, default_value = "table")]
-        format: OutputFormat,
+        #[arg(long, value_enum)]
+        format: Option<GraphFormat>,
     },
 
     /// Kill process(es) using the specified port(s)
@@ -35,15 +101,93 @@ pub enum Commands {
         /// Port number(s) to kill processes on
         ports: Vec<u16>,
 
+        /// Kill the process listening on a Unix domain socket (Linux)
+        #[arg(long, value_name = "PATH")]
+        socket: Vec<String>,
+
         /// Kill without confirmation prompt
         #[arg(long, short)]
         force: bool,
+
+        /// Also kill all child processes of each port owner
+        #[arg(long)]
+        tree: bool,
+
+        /// Allow signalling processes owned by other users
+        #[arg(long)]
+        allow_other_users: bool,
+
+        /// Output format for the --dry-run plan
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+
+    /// Show details of the process behind a port or PID
+    Inspect {
+        /// Port number (a PID with --pid)
+        target: u32,
+
+        /// Treat the target as a PID
+        #[arg(long)]
+        pid: bool,
+
+        /// Show environment variable values instead of redacting them
+        #[arg(long)]
+        show_env: bool,
+
+        /// Output format
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
     },
 
-    /// Check if port(s) are available
+    /// Check if port(s) are available, or if remote targets are reachable
+    ///
+    /// Local ports pass when they are free; remote targets pass when something
+    /// answers. One call checks either local ports or remote targets.
     Check {
-        /// Port numbers or ranges to check (e.g., 3000, 3000-3010)
+        /// Port numbers or ranges to check (e.g., 3000, 3000-3010), or remote HOST:PORT targets
         ports: Vec<String>,
+
+        /// Check the ports on this host by connecting instead of locally
+        #[arg(long, conflicts_with = "probe")]
+        host: Option<String>,
+
+        /// Complete a TLS handshake with remote targets instead of a bare TCP connect
+        #[arg(long)]
+        tls: bool,
+
+        /// How long to wait for each remote check (default: 3s)
+        #[arg(long, value_name = "DURATION")]
+        timeout: Option<String>,
+
+        /// Count remote UDP targets that send no reply as reachable
+        #[arg(long)]
+        allow_silent: bool,
+
+        /// Also try binding each port and report the result per address
+        #[arg(long)]
+        probe: bool,
+
+        /// Address(es) to probe (default: 127.0.0.1, 0.0.0.0, ::1 and ::)
+        #[arg(
+            long,
+            value_name = "ADDR[,ADDR...]",
+            value_delimiter = ',',
+            requires = "probe"
+        )]
+        bind: Vec<IpAddr>,
+
+        /// Protocol(s) to probe, or to check remote targets with
+        #[arg(long, value_enum, default_value_t = HoldProtocol::Tcp)]
+        protocol: HoldProtocol,
+
+        /// Number of free ports to suggest near each unavailable port (0 to disable)
+        #[arg(long, value_name = "N", default_value_t = 3)]
+        suggest: usize,
+
+        /// Print only a `PORT=N` line with the first port, or its first free alternative (messages go to stderr)
+        #[arg(long)]
+        suggest_env: bool,
     },
 
     /// Watch ports defined in configuration file
@@ -51,17 +195,37 @@ pub enum Commands {
         /// Configuration file path (defaults to .portman.yaml)
         #[arg(short, long)]
         config: Option<String>,
+
+        /// Check interval overriding the config, e.g. 250ms, 2s or 1m
+        #[arg(long, value_name = "DURATION")]
+        interval: Option<String>,
     },
 
-    /// Kill all processes on common development ports
+    /// Kill all processes on common development ports or named port sets
     Free {
-        /// Target common development ports only
+        /// Target common development ports only (same as --set common)
         #[arg(long)]
         common: bool,
 
+        /// Target ports in the named port set(s) (e.g., web,debuggers)
+        #[arg(long, value_name = "NAME[,NAME...]", value_delimiter = ',')]
+        set: Vec<String>,
+
         /// Kill without confirmation prompt
         #[arg(long, short)]
         force: bool,
+
+        /// Also kill all child processes of each port owner
+        #[arg(long)]
+        tree: bool,
+
+        /// Allow signalling processes owned by other users
+        #[arg(long)]
+        allow_other_users: bool,
+
+        /// Output format for the --dry-run plan
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
     },
 
     /// Initialize a .portman.yaml config file in current directory
@@ -71,6 +235,115 @@ pub enum Commands {
         force: bool,
     },
 
+    /// Find ports claimed by more than one project config
+    Conflicts {
+        /// Directories to search recursively for project configs (defaults to current directory)
+        paths: Vec<String>,
+
+        /// Also check projects registered by 'portman init'
+        #[arg(long)]
+        registered: bool,
+
+        /// Output format
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+
+    /// Reserve port(s) for a project so other projects leave them alone
+    ///
+    /// Leases are shared by every portman invocation of the current user.
+    /// `check` reports leased ports as reserved for other projects and
+    /// `watch` warns when a process from another project binds one.
+    Reserve {
+        /// Port number(s) to reserve
+        ports: Vec<u16>,
+
+        /// Name shown for the lease (defaults to the project directory name)
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Release the lease automatically after this long (e.g. 30m, 8h, 2d)
+        #[arg(long)]
+        ttl: Option<String>,
+    },
+
+    /// Release reserved port(s)
+    ///
+    /// Leases belong to the project directory they were taken in, so run
+    /// this from the same project (or pass --force).
+    Release {
+        /// Port number(s) to release
+        ports: Vec<u16>,
+
+        /// Release every lease held by the project
+        #[arg(long)]
+        all: bool,
+
+        /// Release leases held by other projects
+        #[arg(long, short)]
+        force: bool,
+    },
+
+    /// List reserved ports
+    Leases {
+        /// Output format
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+
+    /// Keep port(s) occupied with a placeholder listener
+    ///
+    /// The ports are released on Ctrl+C or when --duration runs out.
+    Hold {
+        /// Port number(s) to hold
+        ports: Vec<u16>,
+
+        /// Address to bind (e.g. 0.0.0.0 or ::1)
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: IpAddr,
+
+        /// Protocol(s) to bind
+        #[arg(long, value_enum, default_value_t = HoldProtocol::Tcp)]
+        protocol: HoldProtocol,
+
+        /// Answer TCP connections with this HTTP status code (100-599)
+        #[arg(long, value_name = "CODE", value_parser = clap::value_parser!(u16).range(100..=599))]
+        http_status: Option<u16>,
+
+        /// Body of the HTTP response (implies --http-status 200)
+        #[arg(long)]
+        http_body: Option<String>,
+
+        /// Release the ports after this long (e.g. 30s, 10m, 1h)
+        #[arg(long)]
+        duration: Option<String>,
+    },
+
+    /// Relay local port(s) to another port or host
+    ///
+    /// `portman forward 3000 3001` accepts connections on 3000 and relays
+    /// them to 127.0.0.1:3001. Several mappings can be given as
+    /// LISTEN:PORT or LISTEN:HOST:PORT.
+    Forward {
+        /// Listen and target port, or LISTEN:[HOST:]PORT mappings
+        #[arg(required = true, value_name = "MAPPING")]
+        mappings: Vec<String>,
+
+        /// Address to listen on (e.g. 0.0.0.0 or ::1)
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: IpAddr,
+
+        /// Do not log every connection
+        #[arg(long, short)]
+        quiet: bool,
+    },
+
+    /// Inspect portman configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
     /// Show history of recent port management actions
     History {
         /// Number of recent actions to show
@@ -79,7 +352,63 @@ pub enum Commands {
     },
 }
 
-#[derive(clap::ValueEnum, Clone)]
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Show the config files in use
+    ///
+    /// Settings are taken from command-line flags first, then the project
+    /// config found from the current directory, then the user config
+    /// ($XDG_CONFIG_HOME/portman/config.toml), then built-in defaults.
+    /// Protection rules from all files are combined.
+    Show {
+        /// Print the effective value of every setting and where it came from
+        #[arg(long)]
+        resolved: bool,
+
+        /// Output format (table or json)
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq)]
+pub enum GraphFormat {
+    Table,
+    Json,
+    Csv,
+    Dot,
+    Mermaid,
+}
+
+impl From<OutputFormat> for GraphFormat {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Table => GraphFormat::Table,
+            OutputFormat::Json => GraphFormat::Json,
+            OutputFormat::Csv => GraphFormat::Csv,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ScanBackend {
+    /// INET_DIAG dump over a netlink socket (Linux)
+    Netlink,
+    /// The /proc/net/{tcp,tcp6,udp,udp6} tables (Linux)
+    Procfs,
+    /// The netstat binary (lsof on macOS)
+    Netstat,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum HoldProtocol {
+    Tcp,
+    Udp,
+    Both,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum OutputFormat {
     Table,
     Json,
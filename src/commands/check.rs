@@ -1,21 +1,130 @@
-use crate::output::{display_error, display_info, display_success};
-use crate::scanner::{parse_port_range, PortScanner};
+use crate::cli::HoldProtocol;
+use crate::config::ResolvedConfig;
+use crate::leases::{current_project, LeaseStore};
+use crate::output::{
+    display_error, display_info, display_success, display_warning, messages_to_stderr,
+};
+use crate::probe::{self, BindOutcome, BindProbe, Transport};
+use crate::process::parse_duration;
+use crate::projects::nearby_free_ports;
+use crate::remote::{self, Outcome, Target};
+use crate::scanner::{parse_port_range, PortScanner, ELEVATED_HINT, UNKNOWN_OWNER};
 use anyhow::Result;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::time::Duration;
 
-pub async fn execute(port_args: Vec<String>) -> Result<bool> {
+/// Where and how `check --probe` tries binding each port
+pub struct ProbeOptions {
+    pub addresses: Vec<IpAddr>,
+    pub transports: Vec<Transport>,
+}
+
+impl ProbeOptions {
+    /// Probe `addresses`, or the default loopback and wildcard addresses
+    pub fn new(addresses: Vec<IpAddr>, protocol: HoldProtocol) -> Self {
+        let addresses = if addresses.is_empty() {
+            probe::DEFAULT_ADDRESSES.to_vec()
+        } else {
+            addresses
+        };
+        Self {
+            addresses,
+            transports: transports(protocol),
+        }
+    }
+}
+
+/// How `check` reaches `host:port` targets and ports given with `--host`
+pub struct RemoteOptions {
+    pub host: Option<String>,
+    pub transports: Vec<Transport>,
+    /// Complete a TLS handshake instead of a bare TCP connect
+    pub tls: bool,
+    /// Per-check timeout, e.g. `3s` or `500ms`, when given
+    pub timeout: Option<String>,
+    /// UDP targets that send no reply pass instead of failing
+    pub allow_silent: bool,
+}
+
+impl RemoteOptions {
+    pub fn new(
+        host: Option<String>,
+        protocol: HoldProtocol,
+        tls: bool,
+        timeout: Option<String>,
+        allow_silent: bool,
+    ) -> Self {
+        Self {
+            host,
+            transports: transports(protocol),
+            tls,
+            timeout,
+            allow_silent,
+        }
+    }
+
+    /// The first flag given that only makes sense for remote targets
+    fn remote_flag(&self) -> Option<&'static str> {
+        [
+            (self.tls, "--tls"),
+            (self.timeout.is_some(), "--timeout"),
+            (self.allow_silent, "--allow-silent"),
+        ]
+        .into_iter()
+        .find_map(|(given, flag)| given.then_some(flag))
+    }
+}
+
+fn transports(protocol: HoldProtocol) -> Vec<Transport> {
+    match protocol {
+        HoldProtocol::Tcp => vec![Transport::Tcp],
+        HoldProtocol::Udp => vec![Transport::Udp],
+        HoldProtocol::Both => vec![Transport::Tcp, Transport::Udp],
+    }
+}
+
+pub async fn execute(
+    port_args: Vec<String>,
+    probe: Option<ProbeOptions>,
+    suggest: usize,
+    suggest_env: bool,
+    remote: RemoteOptions,
+    config: &ResolvedConfig,
+) -> Result<bool> {
     if port_args.is_empty() {
         display_error("No ports specified to check");
         return Ok(false);
     }
+    // Keep stdout to the PORT= line so it can be exported as is
+    if suggest_env {
+        messages_to_stderr();
+    }
+
+    let limit = match remote.timeout.as_deref().map(parse_duration).transpose() {
+        Ok(limit) => limit.unwrap_or(remote::DEFAULT_TIMEOUT),
+        Err(e) => {
+            display_error(&format!("{e:#}"));
+            return Ok(false);
+        }
+    };
+    if remote.tls && !remote.transports.contains(&Transport::Tcp) {
+        display_error("--tls runs over TCP and cannot be used with --protocol udp");
+        return Ok(false);
+    }
+    if remote.allow_silent && !remote.transports.contains(&Transport::Udp) {
+        display_error("--allow-silent only applies to UDP checks (--protocol udp or both)");
+        return Ok(false);
+    }
 
-    let mut scanner = PortScanner::new();
-    let mut all_available = true;
     let mut ports_to_check = Vec::new();
+    let mut targets = Vec::new();
 
-    // Parse port arguments (can be individual ports or ranges)
+    // Parse port arguments (can be individual ports, ranges or host:port)
     for arg in port_args {
-        if arg.contains('-') {
+        if let Some(target) = remote::parse_target(&arg) {
+            targets.push(target);
+        } else if arg.contains('-') {
             match parse_port_range(&arg) {
                 Ok((start, end)) => {
                     for port in start..=end {
@@ -42,25 +151,208 @@ pub async fn execute(port_args: Vec<String>) -> Result<bool> {
     ports_to_check.sort_unstable();
     ports_to_check.dedup();
 
+    // With --host, bare ports belong to that host too
+    if let Some(host) = &remote.host {
+        targets.extend(ports_to_check.drain(..).map(|port| Target {
+            host: host.clone(),
+            port,
+        }));
+    }
+
+    // Local ports pass when free and remote targets when something answers,
+    // so a single exit code cannot cover both
+    if !ports_to_check.is_empty() && !targets.is_empty() {
+        display_error(
+            "Local ports and remote targets cannot be checked together; \
+             local ports pass when free, remote targets when something answers",
+        );
+        return Ok(false);
+    }
+    if targets.is_empty() {
+        if let Some(flag) = remote.remote_flag() {
+            display_error(&format!(
+                "{flag} only applies to remote targets (HOST:PORT or --host)"
+            ));
+            return Ok(false);
+        }
+        return check_local(
+            &ports_to_check,
+            probe.as_ref(),
+            suggest,
+            suggest_env,
+            config,
+        );
+    }
+    Ok(check_remote(&targets, &remote, limit).await)
+}
+
+/// Connect to every target and report each check
+async fn check_remote(targets: &[Target], options: &RemoteOptions, limit: Duration) -> bool {
+    let mut all_reachable = true;
+    for target in targets {
+        for (check, outcome) in remote::check(target, &options.transports, options.tls, limit).await
+        {
+            match outcome {
+                Outcome::Reachable(elapsed) => display_success(&format!(
+                    "{target} is reachable over {check} ({elapsed} ms)",
+                    elapsed = elapsed.as_millis()
+                )),
+                Outcome::Tls(version) => {
+                    display_success(&format!("{target} answered a {version} handshake"))
+                }
+                Outcome::Silent if options.allow_silent => display_warning(&format!(
+                    "{target} sent no {check} reply (open or filtered)"
+                )),
+                Outcome::Silent => {
+                    display_error(&format!(
+                        "{target} sent no {check} reply (open or filtered); \
+                         use --allow-silent to accept that"
+                    ));
+                    all_reachable = false;
+                }
+                Outcome::Unreachable(reason) => {
+                    display_error(&format!("{target} is not reachable over {check}: {reason}"));
+                    all_reachable = false;
+                }
+            }
+        }
+    }
+
+    if all_reachable {
+        if targets.len() == 1 {
+            display_info("Target is reachable");
+        } else {
+            display_info(&format!(
+                "All {len} targets are reachable",
+                len = targets.len()
+            ));
+        }
+    } else {
+        display_info("Some targets are unreachable");
+    }
+    all_reachable
+}
+
+/// Check local ports against the socket scan, leases and optional bind probes
+fn check_local(
+    ports_to_check: &[u16],
+    probe: Option<&ProbeOptions>,
+    suggest: usize,
+    suggest_env: bool,
+    config: &ResolvedConfig,
+) -> Result<bool> {
+    let mut scanner = PortScanner::new();
+    let mut all_available = true;
+
     // Check each port by looking for actual processes using them
-    let occupied_ports = match scanner.scan_all_ports() {
-        Ok(ports) => ports
-            .into_iter()
-            .map(|info| info.port)
-            .collect::<HashSet<_>>(),
+    let listeners = match scanner.scan_all_ports() {
+        Ok(ports) => ports,
         Err(e) => {
             display_error(&format!("Failed to scan ports: {e}"));
             return Ok(false);
         }
     };
 
+    let occupied_ports: HashSet<u16> = listeners.iter().map(|info| info.port).collect();
+    // Sockets of other users are occupied even when their owner is hidden
+    let unowned_ports: HashSet<u16> = listeners
+        .iter()
+        .filter(|info| info.pid.is_none())
+        .map(|info| info.port)
+        .collect();
+
+    // A port leased by another project is not available to this one
+    let (_, root) = current_project(config)?;
+    let leases = match LeaseStore::open().and_then(|store| store.active()) {
+        Ok(leases) => leases
+            .into_iter()
+            .map(|lease| (lease.port, lease))
+            .collect::<HashMap<_, _>>(),
+        Err(e) => {
+            display_warning(&format!("Failed to read leases: {e:#}"));
+            HashMap::new()
+        }
+    };
+
+    // Suggestions skip listeners, leased ports and ports the config names
+    let mut taken = occupied_ports.clone();
+    taken.extend(leases.keys());
+    taken.extend(config.services.keys());
+    taken.extend(&config.project_ports);
+    // First requested port, or the first alternative to it
+    let mut env_port = ports_to_check.first().copied();
+
     // Check each port
-    for port in &ports_to_check {
-        if occupied_ports.contains(port) {
-            display_error(&format!("Port {port} is occupied"));
-            all_available = false;
+    for port in ports_to_check {
+        let lease = leases.get(port);
+        let reserved = lease
+            .map(|lease| format!(" (reserved by {owner})", owner = lease.project))
+            .unwrap_or_default();
+        let leased_to_other = lease.filter(|lease| !lease.is_held_by(&root));
+        let probes: Vec<BindProbe> = probe
+            .map(|probe| probe::probe_port(*port, &probe.addresses, &probe.transports))
+            .unwrap_or_default();
+
+        let available = if unowned_ports.contains(port) {
+            display_error(&format!(
+                "Port {port} is occupied by an {UNKNOWN_OWNER}{reserved}; {ELEVATED_HINT}"
+            ));
+            false
+        } else if occupied_ports.contains(port) {
+            display_error(&format!("Port {port} is occupied{reserved}"));
+            false
+        } else if probes.iter().any(BindProbe::blocks) {
+            // Not listed, e.g. a socket that is bound but not listening
+            display_error(&format!("Port {port} cannot be bound{reserved}"));
+            false
+        } else if let Some(lease) = leased_to_other {
+            display_warning(&format!(
+                "Port {port} is reserved by {owner}",
+                owner = lease.project
+            ));
+            false
         } else {
-            display_success(&format!("Port {port} is available"));
+            display_success(&format!("Port {port} is available{reserved}"));
+            true
+        };
+
+        for probe in &probes {
+            let line = format!("  {}", probe.describe());
+            match probe.outcome {
+                BindOutcome::Free => display_success(&line),
+                BindOutcome::Unavailable => display_info(&line),
+                BindOutcome::InUse | BindOutcome::Failed(_) => display_warning(&line),
+            }
+        }
+
+        if available {
+            continue;
+        }
+        all_available = false;
+        // --suggest-env needs one alternative even with --suggest 0
+        let count = suggest.max(usize::from(suggest_env));
+        let alternatives = nearby_free_ports(*port, count, &taken, |candidate| {
+            probe.is_none_or(|probe| {
+                !probe::probe_port(candidate, &probe.addresses, &probe.transports)
+                    .iter()
+                    .any(BindProbe::blocks)
+            })
+        });
+        if env_port == Some(*port) {
+            env_port = alternatives.first().copied();
+        }
+        if suggest > 0 {
+            match alternatives.as_slice() {
+                [] => display_info("  No free ports nearby"),
+                alternatives => display_info(&format!(
+                    "  Free ports nearby: {}",
+                    alternatives
+                        .iter()
+                        .map(|port| port.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            }
         }
     }
 
@@ -75,7 +367,21 @@ pub async fn execute(port_args: Vec<String>) -> Result<bool> {
             ));
         }
     } else {
-        display_info("Some ports are occupied");
+        display_info("Some ports are occupied or reserved");
+    }
+
+    if suggest_env {
+        // Succeeds when there is a port to use, taken or not
+        return Ok(match env_port {
+            Some(port) => {
+                println!("PORT={port}");
+                true
+            }
+            None => {
+                display_warning("No free port to suggest for PORT");
+                false
+            }
+        });
     }
 
     Ok(all_available)
@@ -0,0 +1,40 @@
+use crate::cli::OutputFormat;
+use crate::config::{user_config_path, ResolvedConfig};
+use crate::output::{display_info, display_resolved_config, display_warning};
+use anyhow::Result;
+use std::fs;
+
+pub async fn show(resolved: bool, format: OutputFormat, config: &ResolvedConfig) -> Result<()> {
+    if resolved {
+        display_resolved_config(config, &format);
+        return Ok(());
+    }
+
+    let user_path = config.user_path.clone().or_else(user_config_path);
+    let files = [
+        ("User config", user_path),
+        ("Project config", config.project_path.clone()),
+    ];
+
+    for (label, path) in files {
+        match path {
+            Some(path) if path.exists() => {
+                display_info(&format!("{label}: {path}", path = path.display()));
+                println!();
+                println!("{}", fs::read_to_string(&path)?.trim_end());
+                println!();
+            }
+            Some(path) => {
+                display_warning(&format!(
+                    "{label}: {path} (not found)",
+                    path = path.display()
+                ));
+            }
+            None => display_warning(&format!("{label}: none found")),
+        }
+    }
+
+    display_info("Run 'portman config show --resolved' to see the effective settings");
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use crate::cli::OutputFormat;
+use crate::leases::LeaseStore;
+use crate::output::{display_conflicts, display_error, display_info, display_warning};
+use crate::projects::{
+    config_in_dir, discover_config_files, find_conflicts, Project, ProjectRegistry,
+};
+use crate::scanner::PortScanner;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+pub async fn execute(paths: Vec<String>, registered: bool, format: OutputFormat) -> Result<bool> {
+    let mut config_paths = Vec::new();
+
+    let roots = if paths.is_empty() && !registered {
+        vec![std::env::current_dir()?]
+    } else {
+        paths.into_iter().map(PathBuf::from).collect()
+    };
+    for root in roots {
+        if !root.exists() {
+            display_error(&format!("Path not found: {path}", path = root.display()));
+            return Ok(false);
+        }
+        config_paths.extend(discover_config_files(&root));
+    }
+
+    if registered {
+        let registry = match ProjectRegistry::load() {
+            Ok(registry) => registry,
+            Err(e) => {
+                display_error(&format!("{e:#}"));
+                return Ok(false);
+            }
+        };
+        for dir in &registry.projects {
+            match config_in_dir(dir) {
+                Some(path) => config_paths.push(path),
+                None => display_warning(&format!(
+                    "Registered project has no config: {path}",
+                    path = dir.display()
+                )),
+            }
+        }
+    }
+
+    // The same config can be reached from a path and from the registry
+    let mut seen = HashSet::new();
+    config_paths.retain(|path| seen.insert(path.canonicalize().unwrap_or_else(|_| path.clone())));
+
+    let mut projects = Vec::new();
+    for path in &config_paths {
+        match Project::load(path) {
+            Ok(project) => projects.push(project),
+            Err(e) => display_warning(&format!("Skipping {path}: {e:#}", path = path.display())),
+        }
+    }
+
+    if projects.is_empty() {
+        display_info("No project configs found");
+        return Ok(true);
+    }
+
+    let mut scanner = PortScanner::new();
+    let mut occupied: HashSet<u16> = match scanner.scan_all_ports() {
+        Ok(ports) => ports.into_iter().map(|info| info.port).collect(),
+        Err(e) => {
+            display_warning(&format!(
+                "Failed to scan ports, suggestions may be in use: {e}"
+            ));
+            HashSet::new()
+        }
+    };
+
+    // Leased ports are spoken for even when nothing listens on them yet
+    match LeaseStore::open().and_then(|store| store.active()) {
+        Ok(leases) => occupied.extend(leases.iter().map(|lease| lease.port)),
+        Err(e) => display_warning(&format!("Failed to read leases: {e:#}")),
+    }
+
+    let conflicts = find_conflicts(&projects, &occupied);
+    display_conflicts(&projects, &conflicts, &format);
+
+    Ok(conflicts.is_empty())
+}
@@ -0,0 +1,119 @@
+use crate::agents;
+use crate::forward::{parse_mappings, serve, ConnectionEvent, Counters, EventHandler};
+use crate::output::{display_error, display_info, display_success, display_warning};
+use crate::process::format_memory;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+pub async fn execute(mappings: Vec<String>, bind: IpAddr, quiet: bool) -> Result<bool> {
+    let mappings = match parse_mappings(&mappings) {
+        Ok(mappings) if !mappings.is_empty() => mappings,
+        Ok(_) => {
+            display_error("No port mappings specified (e.g. portman forward 3000 3001)");
+            return Ok(false);
+        }
+        Err(e) => {
+            display_error(&format!("{e:#}"));
+            return Ok(false);
+        }
+    };
+
+    if let Some(mapping) = mappings
+        .iter()
+        .find(|mapping| mapping.forwards_to_itself(bind))
+    {
+        display_error(&format!(
+            "Mapping {mapping} forwards port {port} to itself",
+            port = mapping.listen
+        ));
+        return Ok(false);
+    }
+
+    let mut tasks = Vec::new();
+    let mut stats = Vec::new();
+    let mut descriptions = BTreeMap::new();
+
+    for mapping in mappings {
+        let address = SocketAddr::new(bind, mapping.listen);
+        let listener = match TcpListener::bind(address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                display_error(&format!("Failed to bind {address}: {e}"));
+                return Ok(false);
+            }
+        };
+
+        let label = format!("{address} -> {target}", target = mapping.target);
+        let counters = Arc::new(Counters::default());
+        tasks.push(tokio::spawn(serve(
+            listener,
+            mapping.target.clone(),
+            counters.clone(),
+            connection_logger(label.clone(), quiet),
+        )));
+
+        display_success(&format!("Forwarding {label}"));
+        descriptions.insert(mapping.listen, label.clone());
+        stats.push((label, counters));
+    }
+
+    let _registration = match agents::register("forward", descriptions) {
+        Ok(registration) => Some(registration),
+        Err(e) => {
+            display_warning(&format!(
+                "Forwarded ports will not be described in list: {e:#}"
+            ));
+            None
+        }
+    };
+
+    display_info("Press Ctrl+C to stop forwarding");
+    tokio::signal::ctrl_c().await?;
+
+    for task in tasks {
+        task.abort();
+    }
+
+    println!();
+    for (label, counters) in stats {
+        display_info(&format!(
+            "{label}: {connections} connection(s), {sent} sent, {received} received",
+            connections = counters.connections.load(Ordering::Relaxed),
+            sent = format_memory(counters.sent.load(Ordering::Relaxed)),
+            received = format_memory(counters.received.load(Ordering::Relaxed))
+        ));
+    }
+
+    Ok(true)
+}
+
+/// Print one line when a connection opens and one when it ends
+fn connection_logger(label: String, quiet: bool) -> EventHandler {
+    Arc::new(move |event| match event {
+        ConnectionEvent::Opened { id, peer } if !quiet => {
+            display_info(&format!("[{label}] #{id} {peer} connected"));
+        }
+        ConnectionEvent::Closed {
+            id,
+            peer,
+            sent,
+            received,
+            elapsed,
+        } if !quiet => {
+            display_info(&format!(
+                "[{label}] #{id} {peer} closed after {seconds:.1}s: {sent} sent, {received} received",
+                seconds = elapsed.as_secs_f64(),
+                sent = format_memory(sent),
+                received = format_memory(received)
+            ));
+        }
+        ConnectionEvent::Failed { id, peer, error } => {
+            display_warning(&format!("[{label}] #{id} {peer}: {error}"));
+        }
+        _ => {}
+    })
+}
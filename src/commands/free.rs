@@ -1,45 +1,82 @@
-use crate::commands::kill;
-use crate::output::{display_error, display_info};
-use crate::scanner::{PortScanner, COMMON_DEV_PORTS};
+use crate::cli::OutputFormat;
+use crate::commands::kill::{self, KillOptions};
+use crate::config::ResolvedConfig;
+use crate::output::{display_error, display_info, display_kill_plan, messages_to_stderr};
+use crate::plan::KillPlan;
+use crate::port_sets::{ports_in_sets, COMMON_SET};
+use crate::scanner::PortScanner;
 use anyhow::Result;
 
-pub async fn execute(common: bool, force: bool) -> Result<()> {
-    if !common {
-        display_error("The 'free' command currently only supports --common flag");
-        display_info("Usage: portman free --common [--force]");
-        return Ok(());
+pub async fn execute(
+    sets: Vec<String>,
+    options: KillOptions,
+    config: &ResolvedConfig,
+) -> Result<bool> {
+    // A JSON or CSV plan is all that goes to stdout, so scripts can parse it
+    let machine_plan = options.dry_run && !matches!(options.format, OutputFormat::Table);
+    if machine_plan {
+        messages_to_stderr();
     }
 
+    if sets.is_empty() {
+        display_error("The 'free' command needs the ports to target");
+        display_info("Usage: portman free --common | --set NAME[,NAME...] [--force]");
+        return Ok(false);
+    }
+
+    let target_ports = match ports_in_sets(&sets, &config.port_sets) {
+        Ok(ports) => ports,
+        Err(e) => {
+            display_error(&e.to_string());
+            return Ok(false);
+        }
+    };
+
+    let description = if sets == [COMMON_SET] {
+        "common development ports".to_string()
+    } else {
+        format!("ports in set(s) {names}", names = sets.join(", "))
+    };
+
     let mut scanner = PortScanner::new();
 
-    // Find which common dev ports are occupied
-    let occupied_ports: Vec<u16> = match scanner.scan_all_ports() {
+    // Find which target ports are occupied
+    let mut occupied_ports: Vec<u16> = match scanner.scan_all_ports() {
         Ok(all_ports) => all_ports
             .into_iter()
-            .filter(|port_info| COMMON_DEV_PORTS.contains(&port_info.port))
+            .filter(|port_info| target_ports.contains(&port_info.port))
             .map(|port_info| port_info.port)
             .collect(),
         Err(e) => {
             display_error(&format!("Failed to scan ports: {e}"));
-            return Ok(());
+            return Ok(false);
         }
     };
+    occupied_ports.sort_unstable();
+    occupied_ports.dedup();
 
     if occupied_ports.is_empty() {
-        display_info("No processes found on common development ports");
-        return Ok(());
+        display_info(&format!("No processes found on {description}"));
+        if machine_plan {
+            display_kill_plan(&KillPlan::default(), &options.format);
+        }
+        return Ok(true);
+    }
+
+    // Keep machine-readable dry-run output clean
+    if matches!(options.format, OutputFormat::Table) {
+        let ports_list = occupied_ports
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        display_info(&format!(
+            "Found processes on {len} {description}: {ports_list}",
+            len = occupied_ports.len()
+        ));
     }
 
-    let ports_list = occupied_ports
-        .iter()
-        .map(|p| p.to_string())
-        .collect::<Vec<_>>()
-        .join(", ");
-    display_info(&format!(
-        "Found processes on {len} common development ports: {ports_list}",
-        len = occupied_ports.len()
-    ));
-
-    // Use the kill command to handle the actual killing
-    kill::execute(occupied_ports, force).await
+    // Use the kill command to handle the actual killing, reusing this scan
+    kill::kill_ports(&mut scanner, occupied_ports, options, config).await?;
+    Ok(true)
 }
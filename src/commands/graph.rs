@@ -0,0 +1,45 @@
+use crate::agents;
+use crate::cli::{GraphFormat, OutputFormat};
+use crate::config::ResolvedConfig;
+use crate::graph::{build_edges, to_dot, to_mermaid};
+use crate::output::{display_edges, display_error};
+use crate::scanner::PortScanner;
+use crate::services::ServiceRegistry;
+use anyhow::Result;
+
+pub async fn execute(ports: Vec<u16>, format: GraphFormat, config: &ResolvedConfig) -> Result<()> {
+    let mut scanner = PortScanner::new();
+
+    let mut listeners = match scanner.scan_all_ports() {
+        Ok(listeners) => listeners,
+        Err(e) => {
+            display_error(&format!("Failed to scan ports: {e}"));
+            return Ok(());
+        }
+    };
+    ServiceRegistry::load(&config.services).annotate(&mut listeners);
+    agents::annotate(&mut listeners, &agents::registered());
+
+    let connections = match scanner.scan_connections() {
+        Ok(connections) => connections,
+        Err(e) => {
+            display_error(&format!("Failed to scan connections: {e}"));
+            return Ok(());
+        }
+    };
+
+    let mut edges = build_edges(&listeners, &connections);
+    if !ports.is_empty() {
+        edges.retain(|edge| ports.contains(&edge.port));
+    }
+
+    match format {
+        GraphFormat::Table => display_edges(&edges, &OutputFormat::Table),
+        GraphFormat::Json => display_edges(&edges, &OutputFormat::Json),
+        GraphFormat::Csv => display_edges(&edges, &OutputFormat::Csv),
+        GraphFormat::Dot => print!("{}", to_dot(&edges)),
+        GraphFormat::Mermaid => print!("{}", to_mermaid(&edges)),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use crate::agents;
+use crate::cli::HoldProtocol;
+use crate::output::{display_error, display_info, display_success, display_warning};
+use crate::process::{format_duration, parse_duration};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+
+/// Body sent when `--http-status` is given without `--http-body`
+const DEFAULT_HTTP_BODY: &str = "Port held by portman\n";
+
+/// How long a client gets to send its request before the reply is sent anyway
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub async fn execute(
+    ports: Vec<u16>,
+    bind: IpAddr,
+    protocol: HoldProtocol,
+    http_status: Option<u16>,
+    http_body: Option<String>,
+    duration: Option<String>,
+) -> Result<bool> {
+    if ports.is_empty() {
+        display_error("No ports specified to hold");
+        return Ok(false);
+    }
+
+    let duration = match duration.as_deref().map(parse_duration).transpose() {
+        Ok(duration) => duration,
+        Err(e) => {
+            display_error(&format!("{e:#}"));
+            return Ok(false);
+        }
+    };
+
+    // Any HTTP option turns the TCP placeholder into a tiny HTTP server
+    let http_status = http_status.or(http_body.as_ref().map(|_| 200));
+    let response = http_status.map(|status| {
+        Arc::new(http_response(
+            status,
+            http_body.as_deref().unwrap_or(DEFAULT_HTTP_BODY),
+        ))
+    });
+
+    let mut tasks = Vec::new();
+    let mut descriptions = BTreeMap::new();
+
+    for port in ports {
+        let address = SocketAddr::new(bind, port);
+        let mut held = Vec::new();
+
+        if matches!(protocol, HoldProtocol::Tcp | HoldProtocol::Both) {
+            let listener = match TcpListener::bind(address).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    display_error(&format!("Failed to bind TCP {address}: {e}"));
+                    return Ok(false);
+                }
+            };
+            tasks.push(tokio::spawn(serve_tcp(listener, response.clone())));
+            held.push(format!("TCP {address}"));
+        }
+
+        if matches!(protocol, HoldProtocol::Udp | HoldProtocol::Both) {
+            let socket = match UdpSocket::bind(address).await {
+                Ok(socket) => socket,
+                Err(e) => {
+                    display_error(&format!("Failed to bind UDP {address}: {e}"));
+                    return Ok(false);
+                }
+            };
+            tasks.push(tokio::spawn(drain_udp(socket)));
+            held.push(format!("UDP {address}"));
+        }
+
+        let mut description = held.join(" + ");
+        if let Some(status) = http_status.filter(|_| protocol != HoldProtocol::Udp) {
+            description.push_str(&format!(", HTTP {status}"));
+        }
+        display_success(&format!("Holding {description}"));
+        descriptions.insert(port, description);
+    }
+
+    let held_ports = descriptions
+        .keys()
+        .map(|port| port.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let _registration = match agents::register("hold", descriptions) {
+        Ok(registration) => Some(registration),
+        Err(e) => {
+            display_warning(&format!("Held ports will not be described in list: {e:#}"));
+            None
+        }
+    };
+
+    match duration {
+        Some(duration) => display_info(&format!(
+            "Releasing in {time} (Ctrl+C to release now)",
+            time = format_duration(duration.as_secs())
+        )),
+        None => display_info("Press Ctrl+C to release"),
+    }
+
+    let expired = async {
+        match duration {
+            Some(duration) => tokio::time::sleep(duration).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = expired => {}
+    }
+
+    for task in tasks {
+        task.abort();
+    }
+    display_info(&format!("Released ports: {held_ports}"));
+
+    Ok(true)
+}
+
+/// Accept connections and either reply over HTTP or close them right away
+async fn serve_tcp(listener: TcpListener, response: Option<Arc<Vec<u8>>>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        if let Some(response) = response.clone() {
+            tokio::spawn(reply(stream, response));
+        }
+    }
+}
+
+async fn reply(mut stream: TcpStream, response: Arc<Vec<u8>>) {
+    // Read the request head so clients do not see a reset before the reply
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    let _ = tokio::time::timeout(REQUEST_TIMEOUT, async {
+        while let Ok(read) = stream.read(&mut buffer).await {
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+            if request.windows(4).any(|end| end == b"\r\n\r\n") || request.len() > 16 * 1024 {
+                break;
+            }
+        }
+    })
+    .await;
+
+    let _ = stream.write_all(&response).await;
+    let _ = stream.shutdown().await;
+}
+
+/// Keep the UDP port bound; datagrams are discarded
+async fn drain_udp(socket: UdpSocket) {
+    let mut buffer = [0u8; 2048];
+    while socket.recv_from(&mut buffer).await.is_ok() {}
+}
+
+fn http_response(status: u16, body: &str) -> Vec<u8> {
+    format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {length}\r\nConnection: close\r\n\r\n{body}",
+        reason = reason_phrase(status),
+        length = body.len()
+    )
+    .into_bytes()
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "",
+    }
+}
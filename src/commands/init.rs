@@ -1,6 +1,8 @@
 use crate::config::{get_default_config_path, PortmanConfig};
 use crate::output::{confirm_action, display_error, display_success, display_warning};
+use crate::projects::ProjectRegistry;
 use anyhow::Result;
+use std::path::{Path, PathBuf};
 
 pub async fn execute(force: bool) -> Result<()> {
     let config_path = get_default_config_path();
@@ -18,8 +20,14 @@ pub async fn execute(force: bool) -> Result<()> {
         }
     }
 
-    match PortmanConfig::create_default_config(&config_path) {
-        Ok(config) => {
+    let project_dir = config_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let (config, detected) = PortmanConfig::generate(&project_dir);
+
+    match config.save_with_provenance(&config_path, &detected) {
+        Ok(()) => {
             display_success(&format!(
                 "Created config file: {path}",
                 path = config_path.display()
@@ -29,7 +37,19 @@ pub async fn execute(force: bool) -> Result<()> {
             if let Some(project) = &config.project {
                 println!("  Project: {project}");
             }
-            println!("  Ports: {:?}", config.ports);
+            if detected.is_empty() {
+                println!("  Ports: {:?}", config.ports);
+            } else {
+                println!("  Ports:");
+                for found in &detected {
+                    let service = found.service.as_deref().unwrap_or("-");
+                    println!(
+                        "    {port:<5}  {service:<16} from {source}",
+                        port = found.port,
+                        source = found.source
+                    );
+                }
+            }
             if let Some(interval) = config.watch_interval {
                 println!("  Watch interval: {interval}s");
             }
@@ -38,6 +58,14 @@ pub async fn execute(force: bool) -> Result<()> {
             println!("  • Edit the config file to customize your ports");
             println!("  • Run 'portman watch' to monitor these ports");
             println!("  • Use 'portman list' to see current port usage");
+
+            // Remember the project so 'portman conflicts --registered' can find it
+            let mut registry = ProjectRegistry::load().unwrap_or_default();
+            if registry.register(&project_dir) {
+                if let Err(e) = registry.save() {
+                    display_warning(&format!("Failed to register project: {e:#}"));
+                }
+            }
         }
         Err(e) => {
             display_error(&format!("Failed to create config file: {e}"));
@@ -0,0 +1,77 @@
+use crate::agents;
+use crate::cli::OutputFormat;
+use crate::config::ResolvedConfig;
+use crate::containers::{self, ContainerRuntime};
+use crate::graph::annotate_connections;
+use crate::inspect::Inspection;
+use crate::output::{display_error, display_inspection};
+use crate::process::ProcessManager;
+use crate::scanner::{PortInfo, PortScanner, ELEVATED_HINT};
+use crate::services::ServiceRegistry;
+use crate::systemd;
+use anyhow::Result;
+
+pub async fn execute(
+    target: u32,
+    as_pid: bool,
+    show_env: bool,
+    format: OutputFormat,
+    config: &ResolvedConfig,
+) -> Result<bool> {
+    let mut scanner = PortScanner::new();
+
+    let mut ports = match scanner.scan_all_ports() {
+        Ok(ports) => ports,
+        Err(e) => {
+            display_error(&format!("Failed to scan ports: {e}"));
+            return Ok(false);
+        }
+    };
+    ServiceRegistry::load(&config.services).annotate(&mut ports);
+    agents::annotate(&mut ports, &agents::registered());
+    // Sockets are best effort; the process details are still worth showing
+    let connections = scanner.scan_connections().unwrap_or_default();
+    annotate_connections(&mut ports, &connections);
+    containers::annotate(&mut ports, ContainerRuntime::detect().as_ref());
+    systemd::annotate(&mut ports);
+
+    // Without --pid the number is a port and something has to listen on it
+    let listener = ports
+        .iter()
+        .find(|port_info| !as_pid && u32::from(port_info.port) == target);
+    let pid = match listener {
+        Some(PortInfo { pid: Some(pid), .. }) => *pid,
+        Some(port_info) => {
+            display_error(&format!(
+                "Port {port} is in use, but its owner cannot be read; {hint}",
+                port = port_info.port,
+                hint = ELEVATED_HINT
+            ));
+            return Ok(false);
+        }
+        None if as_pid => target,
+        None => {
+            display_error(&format!(
+                "Nothing is listening on port {target}; use --pid to inspect PID {target}"
+            ));
+            return Ok(false);
+        }
+    };
+
+    let mut process_manager = ProcessManager::new();
+    let Some(process) = process_manager.get_process_info(pid) else {
+        match listener {
+            Some(port_info) => display_error(&format!(
+                "Process {pid} listening on port {port} exited or cannot be read",
+                port = port_info.port
+            )),
+            None => display_error(&format!("No process with PID {target}")),
+        }
+        return Ok(false);
+    };
+
+    let mut inspection = Inspection::new(process, &ports, &connections);
+    inspection.systemd_unit = systemd::unit_for_pid(pid);
+    display_inspection(&inspection, show_env, &format);
+    Ok(true)
+}
@@ -1,128 +1,699 @@
+use crate::cli::OutputFormat;
+use crate::config::ResolvedConfig;
+use crate::containers::{self, ContainerRuntime};
+use crate::graph::annotate_connections;
 use crate::output::{
-    confirm_action, display_error, display_info, display_success, display_warning,
+    choose_action, confirm_action, display_error, display_info, display_kill_plan, display_success,
+    display_warning, messages_to_stderr,
 };
+use crate::plan::{ContainerTarget, KillPlan, KillTarget, SkippedTarget, UnitTarget};
 use crate::process::ProcessManager;
-use crate::scanner::PortScanner;
+use crate::protection::{ProtectionGuard, ProtectionRules};
+use crate::scanner::{PortInfo, PortScanner, ELEVATED_HINT};
+use crate::systemd;
 use anyhow::Result;
+use std::collections::BTreeMap;
+use std::time::Duration;
 
-pub async fn execute(ports: Vec<u16>, force: bool) -> Result<()> {
+/// Flags shared by every command that ends up signalling processes
+pub struct KillOptions {
+    pub force: bool,
+    pub tree: bool,
+    pub allow_other_users: bool,
+    pub dry_run: bool,
+    pub format: OutputFormat,
+}
+
+pub async fn execute(ports: Vec<u16>, options: KillOptions, config: &ResolvedConfig) -> Result<()> {
+    kill_ports(&mut PortScanner::new(), ports, options, config).await
+}
+
+/// Kill the processes on `ports` with a scanner that may already hold the
+/// socket table, so callers that scanned first do not scan again
+pub async fn kill_ports(
+    scanner: &mut PortScanner,
+    ports: Vec<u16>,
+    options: KillOptions,
+    config: &ResolvedConfig,
+) -> Result<()> {
+    // Keep a JSON or CSV plan the only thing on stdout
+    if options.dry_run && !matches!(options.format, OutputFormat::Table) {
+        messages_to_stderr();
+    }
     if ports.is_empty() {
         display_error("No ports specified");
         return Ok(());
     }
 
+    let rules = config.protection_rules();
+    let grace_period = config.kill_timeout.value;
+    let process_manager = ProcessManager::new();
+
+    let mut scanned = match scanner.scan_all_ports() {
+        Ok(scanned) => scanned,
+        Err(e) => {
+            display_error(&format!("Failed to scan ports: {e}"));
+            return Ok(());
+        }
+    };
+    // Other listeners of the same processes stay in the scan for the
+    // protection check; only the requested ports are annotated and shown
+    scanned.sort_by_key(|port_info| !ports.contains(&port_info.port));
+    let requested = scanned
+        .iter()
+        .take_while(|port_info| ports.contains(&port_info.port))
+        .count();
+
+    // Best effort: the kill goes ahead, but without the warning about clients
+    match scanner.scan_connections() {
+        Ok(established) => annotate_connections(&mut scanned[..requested], &established),
+        Err(e) => display_warning(&format!(
+            "Failed to scan connections, so connected clients are not shown: {e:#}"
+        )),
+    }
+    let runtime = ContainerRuntime::detect();
+    containers::annotate(&mut scanned[..requested], runtime.as_ref());
+
+    // Killing a PID that systemd owns just gets it restarted, so those ports
+    // are offered `systemctl stop/restart` instead (unless --force is given)
+    systemd::annotate(&mut scanned[..requested]);
+
+    let guard = match protection_guard(&rules, &process_manager, options.allow_other_users) {
+        Ok(guard) => guard,
+        Err(e) => {
+            display_error(&format!("{e:#}"));
+            return Ok(());
+        }
+    };
+    // Containers and units are checked against the protection rules here,
+    // before anything is stopped
+    let mut plan = KillPlan::build(
+        &ports,
+        &scanned,
+        &process_manager,
+        &guard,
+        options.tree,
+        grace_period,
+        !options.force,
+    );
+
+    if options.dry_run {
+        for target in plan.containers.drain(..) {
+            plan.skipped.push(SkippedTarget {
+                ports: target.ports,
+                pid: target.proxy.pid,
+                process_name: Some(target.proxy.process_name),
+                reason: format!(
+                    "Published by container {}; would offer to stop the container",
+                    target.container.describe()
+                ),
+            });
+        }
+        for target in plan.units.drain(..) {
+            plan.skipped.push(SkippedTarget {
+                ports: target.ports,
+                pid: target.owner.pid,
+                process_name: Some(target.owner.process_name),
+                reason: format!(
+                    "Managed by systemd unit {unit}; would offer systemctl stop/restart",
+                    unit = target.unit
+                ),
+            });
+        }
+        display_kill_plan(&plan, &options.format);
+        return Ok(());
+    }
+
+    let port_list = |_: Option<u32>, ports: &[u16]| ports.iter().map(u16::to_string).collect();
+    let mut run = KillRun {
+        noun: "port",
+        subjects: &port_list,
+        unused: ports
+            .iter()
+            .filter(|&&port| !scanned.iter().any(|port_info| port_info.port == port))
+            .map(u16::to_string)
+            .collect(),
+        scanned: &scanned,
+        force: options.force,
+        process_manager,
+        grace_period,
+        killed: Vec::new(),
+        failed: Vec::new(),
+        failed_children: Vec::new(),
+    };
+    run.execute(&plan, runtime.as_ref());
+    run.display_summary();
+
+    Ok(())
+}
+
+/// Kill the processes listening on Unix domain sockets, given by path
+/// (`@name` for abstract sockets)
+pub async fn kill_sockets(
+    paths: &[String],
+    options: &KillOptions,
+    config: &ResolvedConfig,
+) -> Result<()> {
+    let rules = config.protection_rules();
+    let grace_period = config.kill_timeout.value;
+
     let mut scanner = PortScanner::new();
-    let mut process_manager = ProcessManager::new();
-    let mut successful_kills = Vec::new();
-    let mut failed_kills = Vec::new();
-
-    for port in ports {
-        match scanner.get_port_info(port) {
-            Ok(Some(port_info)) => {
-                // Check if this is a system-critical process
-                if process_manager.is_system_critical(&port_info.process_name) {
+    let process_manager = ProcessManager::new();
+
+    let sockets = match scanner.scan_unix_sockets() {
+        Ok(sockets) => sockets,
+        Err(e) => {
+            display_error(&format!("Failed to scan Unix sockets: {e:#}"));
+            return Ok(());
+        }
+    };
+
+    let mut owners: Vec<(u32, String, String)> = Vec::new();
+    let mut sockets_of: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+    let mut missing = Vec::new();
+    let mut unreadable = Vec::new();
+    for path in paths {
+        // The kernel records the path the socket was bound with, which may
+        // differ from the one given by a symlink or relative path
+        let canonical = std::fs::canonicalize(path)
+            .map(|canonical| canonical.to_string_lossy().into_owned())
+            .ok();
+        let matching: Vec<_> = sockets
+            .iter()
+            .filter(|socket| &socket.path == path || Some(&socket.path) == canonical.as_ref())
+            .collect();
+        if matching.is_empty() {
+            missing.push(path.clone());
+        }
+        for socket in matching {
+            let Some(pid) = socket.pid else {
+                unreadable.push(path.clone());
+                continue;
+            };
+            if !owners.iter().any(|(owner, _, _)| *owner == pid) {
+                owners.push((pid, socket.process_name.clone(), socket.command.clone()));
+            }
+            let owned = sockets_of.entry(pid).or_default();
+            if !owned.contains(path) {
+                owned.push(path.clone());
+            }
+        }
+    }
+
+    let guard = match protection_guard(&rules, &process_manager, options.allow_other_users) {
+        Ok(guard) => guard,
+        Err(e) => {
+            display_error(&format!("{e:#}"));
+            return Ok(());
+        }
+    };
+    // The owners' TCP/UDP ports are needed for the protected-ports rule
+    let listeners = match scanner.scan_all_ports() {
+        Ok(listeners) => listeners,
+        Err(e) => {
+            display_error(&format!("Failed to scan ports: {e}"));
+            return Ok(());
+        }
+    };
+    let mut plan = KillPlan::for_processes(
+        &owners,
+        &listeners,
+        &process_manager,
+        &guard,
+        options.tree,
+        grace_period,
+    );
+
+    if options.dry_run {
+        for path in &missing {
+            plan.skipped.push(SkippedTarget {
+                ports: Vec::new(),
+                pid: None,
+                process_name: None,
+                reason: format!("No process listens on socket {path}"),
+            });
+        }
+        for path in &unreadable {
+            plan.skipped.push(SkippedTarget {
+                ports: Vec::new(),
+                pid: None,
+                process_name: None,
+                reason: format!("Owner of socket {path} cannot be read; {ELEVATED_HINT}"),
+            });
+        }
+        display_kill_plan(&plan, &options.format);
+        return Ok(());
+    }
+
+    let socket_list = |pid: Option<u32>, _: &[u16]| {
+        pid.and_then(|pid| sockets_of.get(&pid))
+            .cloned()
+            .unwrap_or_default()
+    };
+    let mut run = KillRun {
+        noun: "socket",
+        subjects: &socket_list,
+        unused: Vec::new(),
+        scanned: &listeners,
+        force: options.force,
+        process_manager,
+        grace_period,
+        killed: Vec::new(),
+        failed: Vec::new(),
+        failed_children: Vec::new(),
+    };
+    for path in missing {
+        display_warning(&format!("No process found on socket {path}"));
+        run.failed.push((path, "No process found".to_string()));
+    }
+    for path in unreadable {
+        let reason = format!("Owner cannot be read; {ELEVATED_HINT}");
+        display_warning(&format!("Skipping socket {path}: {reason}"));
+        run.failed.push((path, reason));
+    }
+    run.execute(&plan, None);
+    run.display_summary();
+
+    Ok(())
+}
+
+/// Maps a PID and its requested ports to the requested ports or socket paths
+/// it serves
+type Subjects<'a> = dyn Fn(Option<u32>, &[u16]) -> Vec<String> + 'a;
+
+/// Carries out a kill plan for ports or Unix sockets alike, asking first
+/// unless `--force` is given, and records the outcome per requested port or
+/// socket path for the summary
+struct KillRun<'a> {
+    /// What was asked for: `port` or `socket`
+    noun: &'static str,
+    subjects: &'a Subjects<'a>,
+    /// Requested subjects nothing listens on
+    unused: Vec<String>,
+    /// Listeners, for the clients and systemd unit of each target
+    scanned: &'a [PortInfo],
+    force: bool,
+    process_manager: ProcessManager,
+    grace_period: Duration,
+    killed: Vec<String>,
+    failed: Vec<(String, String)>,
+    failed_children: Vec<(String, String)>,
+}
+
+impl KillRun<'_> {
+    fn execute(&mut self, plan: &KillPlan, runtime: Option<&ContainerRuntime>) {
+        self.report_skipped(&plan.skipped);
+
+        for target in &plan.containers {
+            self.stop_container(target, runtime);
+        }
+
+        // Owners the user chose to signal despite their unit
+        let confirmed: Vec<&KillTarget> = plan
+            .units
+            .iter()
+            .filter_map(|target| self.offer_unit(target))
+            .collect();
+
+        let roots = plan
+            .targets
+            .iter()
+            .filter(|target| target.tree_root.is_none());
+        for target in roots {
+            let children: Vec<_> = plan.tree_of(target.pid).collect();
+            self.kill_target(target, &children, false);
+        }
+        for target in confirmed {
+            self.kill_target(target, &[], true);
+        }
+    }
+
+    fn title(&self) -> String {
+        let mut title = self.noun.to_string();
+        title[..1].make_ascii_uppercase();
+        title
+    }
+
+    fn fail(&mut self, subjects: &[String], reason: &str) {
+        self.failed.extend(
+            subjects
+                .iter()
+                .map(|subject| (subject.clone(), reason.to_string())),
+        );
+    }
+
+    fn report_skipped(&mut self, skipped: &[SkippedTarget]) {
+        for skipped in skipped {
+            let subjects = (self.subjects)(skipped.pid, &skipped.ports);
+            match (skipped.pid, &skipped.process_name) {
+                (Some(pid), Some(name)) if subjects.is_empty() => {
                     display_warning(&format!(
-                        "Skipping system-critical process on port {port}: {name} (PID: {pid})",
-                        name = port_info.process_name,
-                        pid = port_info.pid
-                    ));
-                    failed_kills.push((
-                        port,
-                        format!(
-                            "System-critical process: {name}",
-                            name = port_info.process_name
-                        ),
+                        "Skipping child process {name} (PID: {pid}): {reason}",
+                        reason = skipped.reason
                     ));
-                    continue;
                 }
-
-                // Show what will be killed
-                let truncated_cmd = truncate_command(&port_info.command, 50);
-                display_info(&format!(
-                    "Process to kill:\n  Port: {port}\n  PID: {pid}\n  Process: {name} ({truncated_cmd})",
-                    pid = port_info.pid,
-                    name = port_info.process_name
-                ));
-
-                // Ask for confirmation unless force flag is used
-                let should_kill = if force {
-                    true
-                } else {
-                    confirm_action(&format!(
-                        "Kill process {name} on port {port}?",
-                        name = port_info.process_name
-                    ))
-                };
-
-                if should_kill {
-                    match process_manager.kill_process(port_info.pid) {
-                        Ok(true) => {
-                            display_success(&format!(
-                                "Successfully killed process on port {port} (PID: {pid})",
-                                pid = port_info.pid
-                            ));
-                            successful_kills.push(port);
-                        }
-                        Ok(false) => {
-                            display_error(&format!(
-                                "Failed to kill process on port {port} (PID: {pid})",
-                                pid = port_info.pid
+                (Some(pid), Some(name)) => {
+                    for subject in &subjects {
+                        display_warning(&format!(
+                            "Skipping process on {noun} {subject}: {name} (PID: {pid}): {reason}",
+                            noun = self.noun,
+                            reason = skipped.reason
+                        ));
+                    }
+                    self.fail(&subjects, &skipped.reason);
+                }
+                _ => {
+                    for subject in &subjects {
+                        if self.unused.contains(subject) {
+                            display_warning(&format!(
+                                "No process found on {noun} {subject}",
+                                noun = self.noun
                             ));
-                            failed_kills.push((port, "Kill command failed".to_string()));
-                        }
-                        Err(e) => {
-                            display_error(&format!(
-                                "Error killing process on port {port} (PID: {pid}): {e}",
-                                pid = port_info.pid
+                        } else {
+                            display_warning(&format!(
+                                "Skipping {noun} {subject}: {reason}",
+                                noun = self.noun,
+                                reason = skipped.reason
                             ));
-                            failed_kills.push((port, e.to_string()));
                         }
                     }
-                } else {
-                    display_info("Skipped");
-                    failed_kills.push((port, "User cancelled".to_string()));
+                    self.fail(&subjects, &skipped.reason);
                 }
             }
-            Ok(None) => {
-                display_warning(&format!("No process found on port {port}"));
-                failed_kills.push((port, "Port not in use".to_string()));
+        }
+    }
+
+    fn stop_container(&mut self, target: &ContainerTarget, runtime: Option<&ContainerRuntime>) {
+        let ContainerTarget {
+            container,
+            proxy,
+            ports,
+        } = target;
+        let subjects = (self.subjects)(proxy.pid, ports);
+        let list = subjects.join(", ");
+        display_info(&format!(
+            "{title} {list} is published by container {container} through {proxy}",
+            title = self.title(),
+            container = container.describe(),
+            proxy = proxy.describe_owner()
+        ));
+
+        let should_stop = self.force
+            || confirm_action(&format!(
+                "Stop container {name} instead?",
+                name = container.name
+            ));
+        if !should_stop {
+            display_info("Skipped");
+            self.fail(&subjects, "User cancelled");
+            return;
+        }
+
+        let stopped = match runtime {
+            Some(runtime) => runtime.stop(&container.id),
+            None => Err(anyhow::anyhow!("Container runtime is not available")),
+        };
+        match stopped {
+            Ok(()) => {
+                display_success(&format!(
+                    "Stopped container {name} on {noun} {list}",
+                    name = container.name,
+                    noun = self.noun
+                ));
+                self.killed.extend(subjects);
+            }
+            Err(e) => {
+                display_error(&format!(
+                    "Failed to stop container {name} on {noun} {list}: {e:#}",
+                    name = container.name,
+                    noun = self.noun
+                ));
+                self.fail(&subjects, &format!("{e:#}"));
+            }
+        }
+    }
+
+    /// Offer `systemctl stop/restart` for a unit; returns its process when
+    /// the user chooses to kill it instead
+    fn offer_unit<'p>(&mut self, target: &'p UnitTarget) -> Option<&'p KillTarget> {
+        let UnitTarget {
+            unit,
+            owner,
+            ports,
+            kill,
+        } = target;
+        let subjects = (self.subjects)(owner.pid, ports);
+        let list = subjects.join(", ");
+        display_info(&format!(
+            "{title} {list} is served by {owner} in systemd unit {unit}",
+            title = self.title(),
+            owner = owner.describe_owner()
+        ));
+        display_warning("Killing the process only gets it restarted by systemd");
+
+        let choices: &[&str] = if kill.is_some() {
+            &["stop", "restart", "kill"]
+        } else {
+            &["stop", "restart"]
+        };
+        let action = choose_action(
+            &format!("Stop or restart {name} instead?", name = unit.name),
+            choices,
+        );
+        match (action, kill) {
+            (Some("kill"), Some(kill)) => return Some(kill),
+            (Some(action), _) => match unit.systemctl(action) {
+                Ok(()) => {
+                    let done = if action == "stop" {
+                        "Stopped"
+                    } else {
+                        "Restarted"
+                    };
+                    display_success(&format!(
+                        "{done} {targets} on {noun} {list}",
+                        targets = unit.control_targets().join(" and "),
+                        noun = self.noun
+                    ));
+                    self.killed.extend(subjects);
+                }
+                Err(e) => {
+                    display_error(&format!("{e:#}"));
+                    self.fail(&subjects, &format!("{e:#}"));
+                }
+            },
+            (None, _) => {
+                display_info("Skipped");
+                self.fail(&subjects, "User cancelled");
+            }
+        }
+        None
+    }
+
+    /// Signal a planned process and then its `--tree` children; `confirmed`
+    /// skips the prompt for a process the user already chose to kill
+    fn kill_target(&mut self, target: &KillTarget, children: &[&KillTarget], confirmed: bool) {
+        let subjects = (self.subjects)(Some(target.pid), &target.ports);
+        let list = subjects.join(", ");
+
+        // Show what will be killed
+        let truncated_cmd = truncate_command(&target.command, 50);
+        display_info(&format!(
+            "Process to kill:\n  {title}: {list}\n  PID: {pid}\n  Process: {name} ({truncated_cmd})",
+            title = self.title(),
+            pid = target.pid,
+            name = target.process_name
+        ));
+        if !children.is_empty() {
+            display_info(&format!(
+                "  Child processes: {pids}",
+                pids = children
+                    .iter()
+                    .map(|child| format!("{} ({})", child.pid, child.process_name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        let served: Vec<_> = self
+            .scanned
+            .iter()
+            .filter(|port_info| {
+                port_info.pid == Some(target.pid) && target.ports.contains(&port_info.port)
+            })
+            .collect();
+        let active_clients: usize = served.iter().map(|port_info| port_info.connections).sum();
+        if active_clients > 0 {
+            let mut peers: Vec<&str> = served
+                .iter()
+                .flat_map(|port_info| port_info.peers.iter().map(String::as_str))
+                .collect();
+            peers.sort_unstable();
+            peers.dedup();
+            display_warning(&format!(
+                "{active_clients} active client(s) connected: {peers}",
+                peers = peers.join(", ")
+            ));
+        }
+
+        if let Some(unit) = served
+            .iter()
+            .find_map(|port_info| port_info.systemd_unit.as_ref())
+        {
+            display_warning(&format!(
+                "{name} belongs to systemd unit {unit} and may be restarted",
+                name = target.process_name
+            ));
+        }
+
+        // Ask for confirmation unless force flag is used
+        let should_kill = if self.force || confirmed {
+            true
+        } else if active_clients > 0 {
+            confirm_action(&format!(
+                "{active_clients} active client(s) connected to {name} on {noun} {list}, continue?",
+                name = target.process_name,
+                noun = self.noun
+            ))
+        } else {
+            confirm_action(&format!(
+                "Kill process {name} on {noun} {list}?",
+                name = target.process_name,
+                noun = self.noun
+            ))
+        };
+
+        if !should_kill {
+            display_info("Skipped");
+            self.fail(&subjects, "User cancelled");
+            return;
+        }
+
+        let killed = match self
+            .process_manager
+            .kill_process(target.pid, self.grace_period)
+        {
+            Ok(true) => {
+                display_success(&format!(
+                    "Successfully killed process on {noun} {list} (PID: {pid})",
+                    noun = self.noun,
+                    pid = target.pid
+                ));
+                self.killed.extend(subjects);
+                true
+            }
+            Ok(false) => {
+                display_error(&format!(
+                    "Failed to kill process on {noun} {list} (PID: {pid})",
+                    noun = self.noun,
+                    pid = target.pid
+                ));
+                self.fail(&subjects, "Kill command failed");
+                false
             }
             Err(e) => {
-                display_error(&format!("Error checking port {port}: {e}"));
-                failed_kills.push((port, e.to_string()));
+                display_error(&format!(
+                    "Error killing process on {noun} {list} (PID: {pid}): {e}",
+                    noun = self.noun,
+                    pid = target.pid
+                ));
+                self.fail(&subjects, &e.to_string());
+                false
+            }
+        };
+
+        self.kill_children(children, killed);
+    }
+
+    /// Signal the `--tree` descendants of a target once the target itself is
+    /// dead, recording the children that could not be killed with the reason
+    fn kill_children(&mut self, children: &[&KillTarget], parent_killed: bool) {
+        if children.is_empty() {
+            return;
+        }
+        if !parent_killed {
+            display_warning(&format!(
+                "Not killing {count} child process(es) because their parent is still running",
+                count = children.len()
+            ));
+            return;
+        }
+
+        for child in children {
+            let label = format!(
+                "{name} (PID: {pid})",
+                name = child.process_name,
+                pid = child.pid
+            );
+            match self
+                .process_manager
+                .kill_process(child.pid, self.grace_period)
+            {
+                Ok(true) => display_success(&format!("Killed child process {label}")),
+                Ok(false) => {
+                    display_error(&format!("Failed to kill child process {label}"));
+                    self.failed_children
+                        .push((label, "Kill command failed".to_string()));
+                }
+                // Children often exit on their own once the parent is gone
+                Err(_) if !self.process_manager.is_running(child.pid) => {
+                    display_info(&format!("Child process {label} already exited"))
+                }
+                Err(e) => {
+                    display_error(&format!("Error killing child process {label}: {e}"));
+                    self.failed_children.push((label, e.to_string()));
+                }
             }
         }
     }
 
-    // Summary
-    if !successful_kills.is_empty() || !failed_kills.is_empty() {
+    fn display_summary(&self) {
+        if self.killed.is_empty() && self.failed.is_empty() && self.failed_children.is_empty() {
+            return;
+        }
         println!();
         display_info("Summary:");
 
-        if !successful_kills.is_empty() {
-            let ports_list = successful_kills
-                .iter()
-                .map(|p| p.to_string())
-                .collect::<Vec<_>>()
-                .join(", ");
+        if !self.killed.is_empty() {
             display_success(&format!(
-                "Successfully killed processes on {count} port(s): {ports_list}",
-                count = successful_kills.len()
+                "Successfully killed processes on {count} {noun}(s): {list}",
+                count = self.killed.len(),
+                noun = self.noun,
+                list = self.killed.join(", ")
             ));
         }
 
-        if !failed_kills.is_empty() {
+        if !self.failed.is_empty() {
             display_error(&format!(
-                "Failed to kill processes on {count} port(s):",
-                count = failed_kills.len()
+                "Failed to kill processes on {count} {noun}(s):",
+                count = self.failed.len(),
+                noun = self.noun
             ));
-            for (port, reason) in failed_kills {
-                display_error(&format!("  Port {port}: {reason}"));
+            for (subject, reason) in &self.failed {
+                display_error(&format!(
+                    "  {title} {subject}: {reason}",
+                    title = self.title()
+                ));
+            }
+        }
+
+        if !self.failed_children.is_empty() {
+            display_error(&format!(
+                "Failed to kill {count} child process(es):",
+                count = self.failed_children.len()
+            ));
+            for (child, reason) in &self.failed_children {
+                display_error(&format!("  {child}: {reason}"));
             }
         }
     }
+}
 
-    Ok(())
+fn protection_guard(
+    rules: &ProtectionRules,
+    process_manager: &ProcessManager,
+    allow_other_users: bool,
+) -> Result<ProtectionGuard> {
+    ProtectionGuard::new(rules, process_manager.current_user(), allow_other_users)
 }
 
 fn truncate_command(command: &str, max_length: usize) -> String {
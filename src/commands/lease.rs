@@ -0,0 +1,96 @@
+use crate::cli::OutputFormat;
+use crate::config::ResolvedConfig;
+use crate::leases::{current_project, format_remaining, LeaseStore};
+use crate::output::{display_error, display_info, display_leases, display_success};
+use crate::process::parse_duration;
+use anyhow::Result;
+
+pub async fn reserve(
+    ports: Vec<u16>,
+    project: Option<String>,
+    ttl: Option<String>,
+    config: &ResolvedConfig,
+) -> Result<bool> {
+    if ports.is_empty() {
+        display_error("No ports specified to reserve");
+        return Ok(false);
+    }
+
+    let ttl = match ttl.as_deref().map(parse_duration).transpose() {
+        Ok(ttl) => ttl,
+        Err(e) => {
+            display_error(&format!("{e:#}"));
+            return Ok(false);
+        }
+    };
+
+    let (default_project, root) = current_project(config)?;
+    let project = project.unwrap_or(default_project);
+    let store = LeaseStore::open()?;
+
+    let mut all_reserved = true;
+    for port in ports {
+        match store.reserve(port, &project, &root, ttl) {
+            Ok(lease) => {
+                let until = match lease.expires_at {
+                    Some(expires_at) => format!(" (expires in {})", format_remaining(expires_at)),
+                    None => String::new(),
+                };
+                display_success(&format!("Reserved port {port} for {project}{until}"));
+            }
+            Err(e) => {
+                display_error(&format!("{e:#}"));
+                all_reserved = false;
+            }
+        }
+    }
+
+    Ok(all_reserved)
+}
+
+pub async fn release(
+    ports: Vec<u16>,
+    all: bool,
+    force: bool,
+    config: &ResolvedConfig,
+) -> Result<bool> {
+    if ports.is_empty() && !all {
+        display_error("No ports specified to release (use --all for every lease of the project)");
+        return Ok(false);
+    }
+
+    let (_, root) = current_project(config)?;
+    let store = LeaseStore::open()?;
+
+    let released = if all {
+        store.release_project(&root)
+    } else {
+        store.release(&ports, &root, force)
+    };
+    let released = match released {
+        Ok(released) => released,
+        Err(e) => {
+            display_error(&format!("{e:#}"));
+            return Ok(false);
+        }
+    };
+
+    if released.is_empty() {
+        display_info("No matching leases");
+    }
+    for lease in released {
+        display_success(&format!(
+            "Released port {port} ({project})",
+            port = lease.port,
+            project = lease.project
+        ));
+    }
+
+    Ok(true)
+}
+
+pub async fn list(format: OutputFormat) -> Result<()> {
+    let leases = LeaseStore::open()?.active()?;
+    display_leases(&leases, &format);
+    Ok(())
+}
@@ -1,21 +1,78 @@
+use crate::agents;
 use crate::cli::OutputFormat;
-use crate::output::{display_error, display_ports};
-use crate::scanner::{parse_port_range, PortScanner, COMMON_DEV_PORTS};
+use crate::config::ResolvedConfig;
+use crate::containers::{self, ContainerRuntime};
+use crate::graph::{annotate_connections, build_edges};
+use crate::netns;
+use crate::output::{display_edges, display_error, display_ports, display_unix_sockets};
+use crate::port_sets::ports_in_sets;
+use crate::projects::{self, project_root};
+use crate::scanner::{parse_port_range, PortInfo, PortScanner};
+use crate::services::ServiceRegistry;
+use crate::systemd;
 use anyhow::Result;
+use std::path::PathBuf;
+
+/// Filters that narrow down the listed ports
+pub struct ListFilters {
+    pub range: Option<String>,
+    pub filter: Option<String>,
+    pub service: Option<String>,
+    pub sets: Vec<String>,
+    pub project: Option<String>,
+    pub project_dir: Option<PathBuf>,
+}
+
+/// Which network namespaces to list ports from
+pub enum NamespaceScope {
+    /// The caller's namespace, as seen by the system tools
+    Current,
+    All,
+    /// A namespace given by PID or namespace file
+    One(String),
+}
 
 pub async fn execute(
-    range: Option<String>,
-    filter: Option<String>,
-    common: bool,
+    filters: ListFilters,
+    namespaces: NamespaceScope,
+    connections: bool,
     format: OutputFormat,
+    config: &ResolvedConfig,
 ) -> Result<()> {
+    let ListFilters {
+        range,
+        filter,
+        service,
+        sets,
+        project,
+        project_dir,
+    } = filters;
     let mut scanner = PortScanner::new();
 
-    match scanner.scan_all_ports() {
+    let scanned = match &namespaces {
+        NamespaceScope::Current => scanner.scan_all_ports(),
+        NamespaceScope::All | NamespaceScope::One(_) => scan_namespaces(&mut scanner, &namespaces),
+    };
+
+    match scanned {
         Ok(mut ports) => {
+            ServiceRegistry::load(&config.services).annotate(&mut ports);
+            agents::annotate(&mut ports, &agents::registered());
+            projects::annotate(&mut ports);
+            containers::annotate(&mut ports, ContainerRuntime::detect().as_ref());
+            systemd::annotate(&mut ports);
+
             // Apply filters
-            if common {
-                ports.retain(|port_info| COMMON_DEV_PORTS.contains(&port_info.port));
+            if !sets.is_empty() {
+                match ports_in_sets(&sets, &config.port_sets) {
+                    Ok(set_ports) => {
+                        ports.retain(|port_info| set_ports.contains(&port_info.port));
+                    }
+                    Err(e) => {
+                        display_error(&e.to_string());
+                        return Ok(());
+                    }
+                }
             }
 
             if let Some(range_str) = range {
@@ -41,15 +98,115 @@ pub async fn execute(
                 });
             }
 
+            if let Some(service_str) = service {
+                let service_lower = service_str.to_lowercase();
+                ports.retain(|port_info| {
+                    port_info
+                        .service
+                        .as_ref()
+                        .is_some_and(|name| name.to_lowercase().contains(&service_lower))
+                });
+            }
+
+            if let Some(project_str) = project {
+                let project_lower = project_str.to_lowercase();
+                ports.retain(|port_info| {
+                    port_info
+                        .project
+                        .as_ref()
+                        .is_some_and(|name| name.to_lowercase() == project_lower)
+                });
+            }
+
+            if let Some(path) = project_dir {
+                // Everything opened from inside the project holding the path
+                let path = match path.canonicalize() {
+                    Ok(path) => path,
+                    Err(e) => {
+                        display_error(&format!(
+                            "Cannot read project directory {path}: {e}",
+                            path = path.display()
+                        ));
+                        return Ok(());
+                    }
+                };
+                let root = project_root(&path).unwrap_or(path);
+                ports.retain(|port_info| {
+                    port_info
+                        .project_root
+                        .as_ref()
+                        .is_some_and(|project_root| project_root.starts_with(&root))
+                });
+            }
+
             // Sort by port number
             ports.sort_by_key(|port_info| port_info.port);
 
+            // Scanned after filtering: only the listed ports need their clients.
+            // Connection counts are best effort; a failed scan leaves them at zero
+            let scanned_connections = if ports.is_empty() {
+                Ok(Vec::new())
+            } else {
+                scanner.scan_connections()
+            };
+            let established = match scanned_connections {
+                Ok(established) => established,
+                Err(e) if connections => {
+                    display_error(&format!("Failed to scan connections: {e:#}"));
+                    return Ok(());
+                }
+                Err(_) => Vec::new(),
+            };
+            annotate_connections(&mut ports, &established);
+
+            if connections {
+                display_edges(&build_edges(&ports, &established), &format);
+                return Ok(());
+            }
+
             display_ports(&ports, &format);
         }
         Err(e) => {
-            display_error(&format!("Failed to scan ports: {e}"));
+            display_error(&format!("Failed to scan ports: {e:#}"));
         }
     }
 
     Ok(())
 }
+
+/// List listening Unix domain sockets, optionally narrowed to processes or
+/// paths matching `filter`
+pub async fn list_unix_sockets(filter: Option<String>, format: OutputFormat) -> Result<()> {
+    let mut scanner = PortScanner::new();
+    let mut sockets = match scanner.scan_unix_sockets() {
+        Ok(sockets) => sockets,
+        Err(e) => {
+            display_error(&format!("Failed to scan Unix sockets: {e:#}"));
+            return Ok(());
+        }
+    };
+
+    if let Some(filter_str) = filter {
+        let filter_lower = filter_str.to_lowercase();
+        sockets.retain(|socket| {
+            socket.path.to_lowercase().contains(&filter_lower)
+                || socket.process_name.to_lowercase().contains(&filter_lower)
+                || socket.command.to_lowercase().contains(&filter_lower)
+        });
+    }
+
+    display_unix_sockets(&sockets, &format);
+    Ok(())
+}
+
+fn scan_namespaces(scanner: &mut PortScanner, scope: &NamespaceScope) -> Result<Vec<PortInfo>> {
+    // Container names label the namespaces they own
+    let known = ContainerRuntime::detect()
+        .and_then(|runtime| runtime.containers().ok())
+        .unwrap_or_default();
+    let namespaces = match scope {
+        NamespaceScope::One(spec) => vec![netns::resolve(spec, &known)?],
+        _ => netns::enumerate(&known)?,
+    };
+    scanner.scan_namespaces(&namespaces)
+}
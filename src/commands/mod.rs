@@ -1,7 +1,14 @@
 pub mod check;
+pub mod config;
+pub mod conflicts;
+pub mod forward;
 pub mod free;
+pub mod graph;
 pub mod history;
+pub mod hold;
 pub mod init;
+pub mod inspect;
 pub mod kill;
+pub mod lease;
 pub mod list;
 pub mod watch;
@@ -1,11 +1,14 @@
 use crate::config::load_or_create_config;
+use crate::leases::LeaseStore;
 use crate::output::{display_error, display_info, display_success, display_warning};
+use crate::process::parse_duration;
 use crate::scanner::PortScanner;
+use crate::snapshot::SocketSnapshot;
 use anyhow::Result;
 use std::collections::HashSet;
-use tokio::time::{interval, Duration};
+use tokio::time::{interval, Duration, MissedTickBehavior};
 
-pub async fn execute(config_path: Option<String>) -> Result<()> {
+pub async fn execute(config_path: Option<String>, interval_arg: Option<String>) -> Result<()> {
     let (config, config_file_path) = match load_or_create_config(config_path) {
         Ok(result) => result,
         Err(e) => {
@@ -29,24 +32,37 @@ pub async fn execute(config_path: Option<String>) -> Result<()> {
         ports = config.ports
     ));
 
-    let watch_interval = config.watch_interval.unwrap_or(5);
-    display_info(&format!("Check interval: {watch_interval}s"));
+    // `--interval` accepts sub-second values such as 250ms
+    let watch_interval = match interval_arg.as_deref().map(parse_duration).transpose() {
+        Ok(Some(watch_interval)) => watch_interval,
+        Ok(None) => Duration::from_secs(config.watch_interval.unwrap_or(5)),
+        Err(e) => {
+            display_error(&e.to_string());
+            return Ok(());
+        }
+    };
+    display_info(&format!("Check interval: {watch_interval:?}"));
     display_info("Press Ctrl+C to stop watching");
 
     println!();
 
     let mut scanner = PortScanner::new();
-    let mut interval_timer = interval(Duration::from_secs(watch_interval));
+    let mut snapshot = SocketSnapshot::new();
+    let mut interval_timer = interval(watch_interval);
+    interval_timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
     let mut previously_occupied: HashSet<u16> = HashSet::new();
     let mut first_check = true;
+    let lease_store = LeaseStore::open().ok();
+    // (port, pid) pairs already reported as binding someone else's lease
+    let mut flagged_binds: HashSet<(u16, Option<u32>)> = HashSet::new();
 
     loop {
         interval_timer.tick().await;
 
-        match scanner.scan_all_ports() {
-            Ok(all_ports) => {
-                let currently_occupied: HashSet<u16> = all_ports
-                    .iter()
+        match snapshot.update(&mut scanner) {
+            Ok(changes) => {
+                let currently_occupied: HashSet<u16> = snapshot
+                    .ports()
                     .filter(|port_info| config.ports.contains(&port_info.port))
                     .map(|port_info| port_info.port)
                     .collect();
@@ -60,13 +76,12 @@ pub async fn execute(config_path: Option<String>) -> Result<()> {
                         display_warning(&format!("Currently occupied ports: {port_list}"));
 
                         // Show details for occupied ports
-                        for port_info in &all_ports {
+                        for port_info in snapshot.ports() {
                             if config.ports.contains(&port_info.port) {
                                 display_info(&format!(
-                                    "  Port {port}: {name} (PID: {pid})",
+                                    "  Port {port}: {owner}",
                                     port = port_info.port,
-                                    name = port_info.process_name,
-                                    pid = port_info.pid
+                                    owner = port_info.describe_owner()
                                 ));
                             }
                         }
@@ -83,11 +98,10 @@ pub async fn execute(config_path: Option<String>) -> Result<()> {
                         .collect();
 
                     for &&port in &newly_occupied {
-                        if let Some(port_info) = all_ports.iter().find(|p| p.port == port) {
+                        if let Some(port_info) = changes.opened.iter().find(|p| p.port == port) {
                             display_warning(&format!(
-                                "Port {port} became occupied: {name} (PID: {pid})",
-                                name = port_info.process_name,
-                                pid = port_info.pid
+                                "Port {port} became occupied: {owner}",
+                                owner = port_info.describe_owner()
                             ));
                         }
                     }
@@ -95,7 +109,62 @@ pub async fn execute(config_path: Option<String>) -> Result<()> {
                     for &&port in &newly_freed {
                         display_success(&format!("Port {port} became available"));
                     }
+
+                    // A port that stayed occupied by a different socket was
+                    // released and bound again, e.g. by a restarted server
+                    let mut rebound = HashSet::new();
+                    for port_info in &changes.opened {
+                        let port = port_info.port;
+                        if config.ports.contains(&port)
+                            && previously_occupied.contains(&port)
+                            && changes.closed.iter().any(|closed| closed.port == port)
+                            && rebound.insert(port)
+                        {
+                            display_info(&format!(
+                                "Port {port} was bound again: {owner}",
+                                owner = port_info.describe_owner()
+                            ));
+                        }
+                    }
+                }
+
+                // Leased ports are watched whether or not the config lists them
+                let leases = lease_store
+                    .as_ref()
+                    .and_then(|store| store.active().ok())
+                    .unwrap_or_default();
+                let mut current_binds = HashSet::new();
+                for port_info in snapshot.ports() {
+                    let Some(lease) = leases.iter().find(|lease| lease.port == port_info.port)
+                    else {
+                        continue;
+                    };
+                    // Another user's process hides its directory, so it
+                    // cannot be shown to belong to the lease holder
+                    if port_info
+                        .cwd
+                        .as_ref()
+                        .is_some_and(|cwd| lease.is_owned_by(cwd))
+                    {
+                        continue;
+                    }
+
+                    let bind = (port_info.port, port_info.pid);
+                    current_binds.insert(bind);
+                    if !flagged_binds.contains(&bind) {
+                        let from = match &port_info.cwd {
+                            Some(cwd) => cwd.display().to_string(),
+                            None => "an unknown directory".to_string(),
+                        };
+                        display_warning(&format!(
+                            "Port {port} is reserved by {owner} but was bound by {bound_by} from {from}",
+                            port = port_info.port,
+                            owner = lease.project,
+                            bound_by = port_info.describe_owner(),
+                        ));
+                    }
                 }
+                flagged_binds = current_binds;
 
                 previously_occupied = currently_occupied;
             }
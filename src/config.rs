@@ -1,14 +1,47 @@
+use crate::cli::OutputFormat;
+use crate::detect::{detect_ports, DetectedPort};
+use crate::port_sets::BUILTIN_PORT_SETS;
+use crate::process::TERM_GRACE_PERIOD;
+use crate::protection::ProtectionRules;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// File names recognised as project configs, in lookup order
+pub const CONFIG_FILE_NAMES: &[&str] = &[
+    ".portman.yaml",
+    ".portman.yml",
+    ".portman.toml",
+    "portman.yaml",
+    "portman.yml",
+    "portman.toml",
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortmanConfig {
     pub project: Option<String>,
+    #[serde(default)]
     pub ports: Vec<u16>,
     pub description: Option<String>,
     pub watch_interval: Option<u64>, // seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<OutputFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kill_timeout_ms: Option<u64>,
+    /// Named port sets usable with `--set`; these replace built-in sets of the same name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub port_sets: BTreeMap<String, Vec<u16>>,
+    /// Service names shown for ports, keyed by port number
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub services: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "ProtectionRules::is_empty")]
+    pub protected: ProtectionRules,
 }
 
 impl Default for PortmanConfig {
@@ -18,6 +51,12 @@ impl Default for PortmanConfig {
             ports: vec![3000, 3001, 5432, 6379], // Common defaults
             description: None,
             watch_interval: Some(5), // 5 seconds
+            output_format: None,
+            color: None,
+            kill_timeout_ms: None,
+            port_sets: BTreeMap::new(),
+            services: BTreeMap::new(),
+            protected: ProtectionRules::default(),
         }
     }
 }
@@ -76,19 +115,10 @@ impl PortmanConfig {
 
     /// Find config file in current directory or parent directories
     pub fn find_config_file() -> Option<PathBuf> {
-        let possible_names = [
-            ".portman.yaml",
-            ".portman.yml",
-            ".portman.toml",
-            "portman.yaml",
-            "portman.yml",
-            "portman.toml",
-        ];
-
         let mut current_dir = std::env::current_dir().ok()?;
 
         loop {
-            for name in &possible_names {
+            for name in CONFIG_FILE_NAMES {
                 let config_path = current_dir.join(name);
                 if config_path.exists() {
                     return Some(config_path);
@@ -105,34 +135,109 @@ impl PortmanConfig {
 
     /// Create a default config file with smart defaults based on current directory
     pub fn create_default_config<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let (config, detected) = Self::generate(&current_dir);
+        config.save_with_provenance(&path, &detected)?;
+        Ok(config)
+    }
+
+    /// Build a config for `dir` from the ports its project files declare,
+    /// falling back to guesses based on the project type
+    pub fn generate(dir: &Path) -> (Self, Vec<DetectedPort>) {
         let mut config = Self::default();
 
-        // Try to detect project type and set smart defaults
-        if let Ok(current_dir) = std::env::current_dir() {
-            if let Some(project_name) = current_dir.file_name().and_then(|n| n.to_str()) {
-                config.project = Some(project_name.to_string());
+        if let Some(project_name) = dir.file_name().and_then(|n| n.to_str()) {
+            config.project = Some(project_name.to_string());
+        }
+
+        let detected = detect_ports(dir);
+        if !detected.is_empty() {
+            config.ports = detected.iter().map(|found| found.port).collect();
+            config.services = detected
+                .iter()
+                .filter_map(|found| Some((found.port.to_string(), found.service.clone()?)))
+                .collect();
+            return (config, detected);
+        }
+
+        // Detect common project files and adjust port defaults
+        let common_files = [
+            ("package.json", vec![3000, 3001, 8080]), // Node.js
+            ("Cargo.toml", vec![8000, 8080, 3000]),   // Rust
+            ("requirements.txt", vec![8000, 5000]),   // Python
+            ("pom.xml", vec![8080, 8081, 9090]),      // Java Maven
+            ("build.gradle", vec![8080, 8081, 9090]), // Java Gradle
+            ("docker-compose.yml", vec![3000, 5432, 6379, 8080]), // Docker
+        ];
+
+        for (file, ports) in &common_files {
+            if dir.join(file).exists() {
+                config.ports.clone_from(ports);
+                break;
             }
+        }
+
+        (config, detected)
+    }
+
+    /// Save the config, annotating each YAML port entry with where it was found
+    pub fn save_with_provenance<P: AsRef<Path>>(
+        &self,
+        path: P,
+        detected: &[DetectedPort],
+    ) -> Result<()> {
+        let is_yaml = matches!(
+            path.as_ref().extension().and_then(|ext| ext.to_str()),
+            Some("yaml" | "yml") | None
+        );
+        if detected.is_empty() || !is_yaml {
+            return self.save(path);
+        }
+
+        let yaml =
+            serde_yaml::to_string(self).with_context(|| "Failed to serialize config to YAML")?;
 
-            // Detect common project files and adjust port defaults
-            let common_files = [
-                ("package.json", vec![3000, 3001, 8080]), // Node.js
-                ("Cargo.toml", vec![8000, 8080, 3000]),   // Rust
-                ("requirements.txt", vec![8000, 5000]),   // Python
-                ("pom.xml", vec![8080, 8081, 9090]),      // Java Maven
-                ("build.gradle", vec![8080, 8081, 9090]), // Java Gradle
-                ("docker-compose.yml", vec![3000, 5432, 6379, 8080]), // Docker
-            ];
-
-            for (file, ports) in &common_files {
-                if current_dir.join(file).exists() {
-                    config.ports.clone_from(ports);
-                    break;
+        let mut content = String::from("# Generated by portman init from project files\n");
+        let mut in_ports = false;
+        for line in yaml.lines() {
+            content.push_str(line);
+            if line == "ports:" {
+                in_ports = true;
+            } else if in_ports && line.starts_with("- ") {
+                let note = line[2..]
+                    .parse::<u16>()
+                    .ok()
+                    .and_then(|port| detected.iter().find(|found| found.port == port));
+                if let Some(found) = note {
+                    match &found.service {
+                        Some(service) => {
+                            content.push_str(&format!("  # {service} ({})", found.source))
+                        }
+                        None => content.push_str(&format!("  # {}", found.source)),
+                    }
                 }
+            } else {
+                in_ports = false;
             }
+            content.push('\n');
         }
 
-        config.save(&path)?;
-        Ok(config)
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write config file: {}", path.as_ref().display()))?;
+
+        Ok(())
+    }
+
+    /// Service names keyed by port; keys are strings so that TOML can hold them
+    pub fn service_names(&self) -> Result<BTreeMap<u16, String>> {
+        self.services
+            .iter()
+            .map(|(port, name)| {
+                port.parse::<u16>()
+                    .map(|port| (port, name.clone()))
+                    .with_context(|| format!("Invalid port in services: {port}"))
+            })
+            .collect()
     }
 
     /// Validate configuration
@@ -169,6 +274,231 @@ pub fn get_default_config_path() -> PathBuf {
         .join(".portman.yaml")
 }
 
+/// Path of the user-level config: `$XDG_CONFIG_HOME/portman/config.toml`,
+/// falling back to `~/.config` (or `%APPDATA%` on Windows)
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            if cfg!(windows) {
+                std::env::var_os("APPDATA").map(PathBuf::from)
+            } else {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+            }
+        })?;
+
+    Some(base.join("portman").join("config.toml"))
+}
+
+/// Directory for portman's own state: `$XDG_DATA_HOME/portman`, falling back
+/// to `~/.local/share` (or `%LOCALAPPDATA%` on Windows)
+pub fn user_data_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            if cfg!(windows) {
+                std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+            } else {
+                std::env::var_os("HOME")
+                    .map(|home| PathBuf::from(home).join(".local").join("share"))
+            }
+        })?;
+
+    Some(base.join("portman"))
+}
+
+/// Where an effective setting came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    Default,
+    User(PathBuf),
+    Project(PathBuf),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::User(path) => write!(f, "user ({})", path.display()),
+            ConfigSource::Project(path) => write!(f, "project ({})", path.display()),
+        }
+    }
+}
+
+/// A setting value together with the config layer that provided it
+#[derive(Debug, Clone)]
+pub struct Resolved<T> {
+    pub value: T,
+    pub source: ConfigSource,
+}
+
+/// Effective settings for one invocation.
+///
+/// Precedence, highest first: command-line flags, the project config found
+/// from the current directory, the user config, built-in defaults.
+/// Protection rules are not overridden but combined from both files.
+#[derive(Debug, Clone)]
+pub struct ResolvedConfig {
+    pub user_path: Option<PathBuf>,
+    pub project_path: Option<PathBuf>,
+    /// Ports the project config claims (`ports`)
+    pub project_ports: Vec<u16>,
+    pub port_sets: BTreeMap<String, Resolved<Vec<u16>>>,
+    pub services: BTreeMap<u16, Resolved<String>>,
+    pub output_format: Resolved<OutputFormat>,
+    pub color: Resolved<bool>,
+    pub kill_timeout: Resolved<Duration>,
+    pub protected: Vec<Resolved<ProtectionRules>>,
+}
+
+impl ResolvedConfig {
+    /// Load the user config and the project config and merge them
+    pub fn load() -> Result<Self> {
+        let user = match user_config_path() {
+            Some(path) if path.exists() => Some((PortmanConfig::load(&path)?, path)),
+            _ => None,
+        };
+
+        let project = match PortmanConfig::find_config_file() {
+            Some(path) => Some((PortmanConfig::load(&path)?, path)),
+            None => None,
+        };
+
+        for (config, path) in user.iter().chain(project.iter()) {
+            config
+                .service_names()
+                .with_context(|| format!("Invalid config file: {}", path.display()))?;
+        }
+
+        Ok(Self::merge(user, project))
+    }
+
+    /// Built-in defaults that still point at the config files found, for
+    /// commands that must work while one of those files cannot be loaded
+    pub fn defaults() -> Self {
+        Self {
+            user_path: user_config_path().filter(|path| path.exists()),
+            project_path: PortmanConfig::find_config_file(),
+            ..Self::merge(None, None)
+        }
+    }
+
+    pub fn merge(
+        user: Option<(PortmanConfig, PathBuf)>,
+        project: Option<(PortmanConfig, PathBuf)>,
+    ) -> Self {
+        let layers: Vec<(&PortmanConfig, ConfigSource)> = user
+            .iter()
+            .map(|(config, path)| (config, ConfigSource::User(path.clone())))
+            .chain(
+                project
+                    .iter()
+                    .map(|(config, path)| (config, ConfigSource::Project(path.clone()))),
+            )
+            .collect();
+
+        let protected = layers
+            .iter()
+            .filter(|(config, _)| !config.protected.is_empty())
+            .map(|(config, source)| Resolved {
+                value: config.protected.clone(),
+                source: source.clone(),
+            })
+            .collect();
+
+        let mut port_sets: BTreeMap<String, Resolved<Vec<u16>>> = BUILTIN_PORT_SETS
+            .iter()
+            .map(|(name, ports)| {
+                (
+                    name.to_string(),
+                    Resolved {
+                        value: ports.to_vec(),
+                        source: ConfigSource::Default,
+                    },
+                )
+            })
+            .collect();
+        for (config, source) in &layers {
+            for (name, ports) in &config.port_sets {
+                port_sets.insert(
+                    name.clone(),
+                    Resolved {
+                        value: ports.clone(),
+                        source: source.clone(),
+                    },
+                );
+            }
+        }
+
+        let mut services = BTreeMap::new();
+        for (config, source) in &layers {
+            for (port, name) in config.service_names().unwrap_or_default() {
+                services.insert(
+                    port,
+                    Resolved {
+                        value: name,
+                        source: source.clone(),
+                    },
+                );
+            }
+        }
+
+        Self {
+            user_path: user.as_ref().map(|(_, path)| path.clone()),
+            project_path: project.as_ref().map(|(_, path)| path.clone()),
+            project_ports: project
+                .as_ref()
+                .map(|(config, _)| config.ports.clone())
+                .unwrap_or_default(),
+            port_sets,
+            services,
+            output_format: resolve(&layers, OutputFormat::Table, |config| {
+                config.output_format.clone()
+            }),
+            color: resolve(&layers, true, |config| config.color),
+            kill_timeout: resolve(&layers, TERM_GRACE_PERIOD, |config| {
+                config.kill_timeout_ms.map(Duration::from_millis)
+            }),
+            protected,
+        }
+    }
+
+    /// Protection rules from every config layer combined
+    pub fn protection_rules(&self) -> ProtectionRules {
+        let mut rules = ProtectionRules::default();
+        for layer in &self.protected {
+            rules.names.extend(layer.value.names.iter().cloned());
+            rules.paths.extend(layer.value.paths.iter().cloned());
+            rules.uids.extend(layer.value.uids.iter().copied());
+            rules.ports.extend(layer.value.ports.iter().copied());
+        }
+        rules
+    }
+}
+
+/// Take the value from the last layer that sets it, or the default
+fn resolve<T>(
+    layers: &[(&PortmanConfig, ConfigSource)],
+    default: T,
+    get: impl Fn(&PortmanConfig) -> Option<T>,
+) -> Resolved<T> {
+    layers
+        .iter()
+        .rev()
+        .find_map(|(config, source)| {
+            get(config).map(|value| Resolved {
+                value,
+                source: source.clone(),
+            })
+        })
+        .unwrap_or(Resolved {
+            value: default,
+            source: ConfigSource::Default,
+        })
+}
+
 /// Load config from default locations or create if not exists
 pub fn load_or_create_config(config_path: Option<String>) -> Result<(PortmanConfig, PathBuf)> {
     let path = if let Some(custom_path) = config_path {
@@ -188,3 +518,114 @@ pub fn load_or_create_config(config_path: Option<String>) -> Result<(PortmanConf
         Ok((config, path))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(source: &str, config: PortmanConfig) -> Option<(PortmanConfig, PathBuf)> {
+        Some((config, PathBuf::from(source)))
+    }
+
+    #[test]
+    fn test_defaults_without_config_files() {
+        let resolved = ResolvedConfig::merge(None, None);
+
+        assert_eq!(resolved.port_sets.len(), BUILTIN_PORT_SETS.len());
+        assert_eq!(resolved.port_sets["db"].source, ConfigSource::Default);
+        assert!(resolved.color.value);
+        assert_eq!(resolved.kill_timeout.value, TERM_GRACE_PERIOD);
+        assert!(resolved.protection_rules().is_empty());
+    }
+
+    #[test]
+    fn test_project_overrides_user() {
+        let user: PortmanConfig = toml::from_str(
+            "color = false\nkill_timeout_ms = 2000\n[port_sets]\nweb3 = [8545]\nweb = [8080]\n",
+        )
+        .unwrap();
+        let project: PortmanConfig = serde_yaml::from_str(
+            "ports: [3000]\nkill_timeout_ms: 500\nport_sets:\n  web: [5173, 6006]\n",
+        )
+        .unwrap();
+
+        let resolved = ResolvedConfig::merge(
+            layer("/home/dev/.config/portman/config.toml", user),
+            layer("/work/app/.portman.yaml", project),
+        );
+
+        assert!(!resolved.color.value);
+        assert!(matches!(resolved.color.source, ConfigSource::User(_)));
+        assert_eq!(resolved.kill_timeout.value, Duration::from_millis(500));
+        assert!(matches!(
+            resolved.kill_timeout.source,
+            ConfigSource::Project(_)
+        ));
+        assert_eq!(resolved.port_sets["web3"].value, vec![8545]);
+        assert_eq!(resolved.port_sets["web"].value, vec![5173, 6006]);
+        assert!(matches!(
+            resolved.port_sets["web"].source,
+            ConfigSource::Project(_)
+        ));
+    }
+
+    #[test]
+    fn test_generate_from_project_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("docker-compose.yml"),
+            "services:\n  db:\n    ports:\n      - \"5432:5432\"\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join(".env"), "PORT=3000\n").unwrap();
+
+        let (config, detected) = PortmanConfig::generate(dir.path());
+        assert_eq!(config.ports, vec![5432, 3000]);
+        assert_eq!(config.services.get("5432").map(String::as_str), Some("db"));
+
+        let path = dir.path().join(".portman.yaml");
+        config.save_with_provenance(&path, &detected).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("- 5432  # db (docker-compose.yml services.db.ports)"));
+        assert!(content.contains("- 3000  # .env PORT"));
+
+        let reloaded = PortmanConfig::load(&path).unwrap();
+        assert_eq!(reloaded.ports, vec![5432, 3000]);
+    }
+
+    #[test]
+    fn test_service_names() {
+        let user: PortmanConfig =
+            toml::from_str("[services]\n5432 = \"pg-main\"\n8545 = \"anvil\"\n").unwrap();
+        let project: PortmanConfig =
+            serde_yaml::from_str("ports: [3000]\nservices:\n  5432: pg-replica\n").unwrap();
+
+        let resolved = ResolvedConfig::merge(
+            layer("/home/dev/.config/portman/config.toml", user),
+            layer("/work/app/.portman.yaml", project),
+        );
+
+        assert_eq!(resolved.services[&5432].value, "pg-replica");
+        assert_eq!(resolved.services[&8545].value, "anvil");
+
+        let invalid: PortmanConfig = toml::from_str("[services]\nhttp = \"web\"\n").unwrap();
+        assert!(invalid.service_names().is_err());
+    }
+
+    #[test]
+    fn test_protection_rules_are_combined() {
+        let user: PortmanConfig = toml::from_str("[protected]\nports = [5432]\n").unwrap();
+        let project: PortmanConfig =
+            serde_yaml::from_str("ports: [3000]\nprotected:\n  names: [\"^redis\"]\n").unwrap();
+
+        let resolved = ResolvedConfig::merge(
+            layer("/home/dev/.config/portman/config.toml", user),
+            layer("/work/app/.portman.yaml", project),
+        );
+
+        let rules = resolved.protection_rules();
+        assert_eq!(rules.ports, vec![5432]);
+        assert_eq!(rules.names, vec!["^redis".to_string()]);
+        assert_eq!(resolved.protected.len(), 2);
+    }
+}
@@ -0,0 +1,469 @@
+use crate::scanner::PortInfo;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Processes that publish container ports on the host; killing them only
+/// unpublishes the port while the container keeps running
+const PROXY_PROCESSES: &[&str] = &[
+    "docker-proxy",
+    "rootlessport",
+    "rootlesskit",
+    "slirp4netns",
+    "pasta",
+];
+
+/// How long to wait for the container runtime before giving up
+const API_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Seconds the runtime waits for a container to exit before killing it
+const STOP_TIMEOUT_SECS: u32 = 10;
+
+/// The container behind a published port
+#[derive(Debug, Clone, PartialEq)]
+pub struct Container {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    /// `com.docker.compose.service` label
+    pub compose_service: Option<String>,
+    /// Host ports the container publishes
+    pub published: Vec<u16>,
+}
+
+impl Container {
+    /// `name (image, compose service web)`
+    pub fn describe(&self) -> String {
+        match &self.compose_service {
+            Some(service) => format!(
+                "{name} ({image}, compose service {service})",
+                name = self.name,
+                image = self.image
+            ),
+            None => format!("{name} ({image})", name = self.name, image = self.image),
+        }
+    }
+}
+
+/// Whether `process_name` forwards ports for containers
+pub fn is_proxy(process_name: &str) -> bool {
+    PROXY_PROCESSES
+        .iter()
+        .any(|proxy| process_name.starts_with(proxy))
+}
+
+/// A container from the Docker-compatible `/containers/json` API
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiContainer {
+    pub id: String,
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(default)]
+    pub image: String,
+    #[serde(default)]
+    pub labels: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub ports: Vec<ApiPort>,
+    #[serde(default)]
+    pub network_settings: Option<ApiNetworkSettings>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiPort {
+    #[serde(rename = "PrivatePort")]
+    pub private_port: u16,
+    #[serde(rename = "PublicPort")]
+    pub public_port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiNetworkSettings {
+    #[serde(rename = "Networks", default)]
+    pub networks: HashMap<String, ApiNetwork>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiNetwork {
+    #[serde(rename = "IPAddress", default)]
+    pub ip_address: String,
+}
+
+impl ApiContainer {
+    fn to_container(&self) -> Container {
+        let name = self
+            .names
+            .first()
+            .map(|name| name.trim_start_matches('/').to_string())
+            .unwrap_or_else(|| self.id.chars().take(12).collect());
+        let mut published: Vec<u16> = self
+            .ports
+            .iter()
+            .filter_map(|api_port| api_port.public_port)
+            .collect();
+        published.sort_unstable();
+        published.dedup();
+        Container {
+            id: self.id.clone(),
+            name,
+            image: self.image.clone(),
+            compose_service: self
+                .labels
+                .as_ref()
+                .and_then(|labels| labels.get("com.docker.compose.service").cloned()),
+            published,
+        }
+    }
+
+    fn has_ip(&self, ip: &str) -> bool {
+        self.network_settings.as_ref().is_some_and(|settings| {
+            settings
+                .networks
+                .values()
+                .any(|network| network.ip_address == ip)
+        })
+    }
+}
+
+/// Where a `docker-proxy` forwards to, from its `-container-ip` and `-container-port` args
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProxyTarget {
+    pub container_ip: Option<String>,
+    pub container_port: Option<u16>,
+}
+
+pub fn parse_proxy_args(command: &str) -> ProxyTarget {
+    let mut target = ProxyTarget::default();
+    let mut args = command.split_whitespace();
+    while let Some(arg) = args.next() {
+        match arg.trim_start_matches('-') {
+            "container-ip" => target.container_ip = args.next().map(str::to_string),
+            "container-port" => {
+                target.container_port = args.next().and_then(|port| port.parse().ok())
+            }
+            _ => {}
+        }
+    }
+    target
+}
+
+/// Container ID from a `/proc/<pid>/cgroup` file of a process running inside a container
+pub fn cgroup_container_id(cgroup: &str) -> Option<String> {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN
+        .get_or_init(|| {
+            Regex::new(r"(?:docker|libpod|crio|cri-containerd)[-/]([0-9a-f]{64})").unwrap()
+        })
+        .captures(cgroup)
+        .map(|captures| captures[1].to_string())
+}
+
+/// Find the container behind a listener: by ID for processes inside a
+/// container, by the proxy's target address, or by published port
+pub fn match_container(
+    port_info: &PortInfo,
+    container_id: Option<&str>,
+    containers: &[ApiContainer],
+) -> Option<Container> {
+    if let Some(id) = container_id {
+        return containers
+            .iter()
+            .find(|container| container.id.starts_with(id) || id.starts_with(&container.id))
+            .map(ApiContainer::to_container);
+    }
+
+    let target = parse_proxy_args(&port_info.command);
+    let by_target = target.container_ip.as_deref().and_then(|ip| {
+        containers.iter().find(|container| {
+            container.has_ip(ip)
+                && target.container_port.is_none_or(|port| {
+                    container
+                        .ports
+                        .iter()
+                        .any(|api_port| api_port.private_port == port)
+                })
+        })
+    });
+
+    by_target
+        .or_else(|| {
+            containers.iter().find(|container| {
+                container
+                    .ports
+                    .iter()
+                    .any(|api_port| api_port.public_port == Some(port_info.port))
+            })
+        })
+        .map(ApiContainer::to_container)
+}
+
+/// Fill in the container of ports opened by container proxies or by
+/// processes inside containers. The runtime is only asked when one is found.
+/// Proxies stay without a container when no runtime answers (e.g. rootless
+/// Podman without its API socket); `is_proxy` still identifies them.
+pub fn annotate(ports: &mut [PortInfo], runtime: Option<&ContainerRuntime>) {
+    let candidates: Vec<(usize, Option<String>)> = ports
+        .iter()
+        .enumerate()
+        .filter_map(|(index, port_info)| {
+            let container_id = port_info
+                .pid
+                .and_then(read_cgroup)
+                .as_deref()
+                .and_then(cgroup_container_id);
+            (is_proxy(&port_info.process_name) || container_id.is_some())
+                .then_some((index, container_id))
+        })
+        .collect();
+    if candidates.is_empty() {
+        return;
+    }
+
+    let Some(containers) = runtime.and_then(|runtime| runtime.containers().ok()) else {
+        return;
+    };
+    for (index, container_id) in candidates {
+        ports[index].container =
+            match_container(&ports[index], container_id.as_deref(), &containers);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_cgroup(pid: u32) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_cgroup(_pid: u32) -> Option<String> {
+    None
+}
+
+/// Client for the Docker-compatible API of Docker or Podman on a Unix socket
+#[derive(Debug, Clone)]
+pub struct ContainerRuntime {
+    socket: PathBuf,
+}
+
+impl ContainerRuntime {
+    /// The first runtime socket that exists: `DOCKER_HOST` (unix:// only),
+    /// Docker's default socket, then Podman's user and system sockets
+    pub fn detect() -> Option<Self> {
+        let mut candidates = Vec::new();
+        if let Ok(host) = std::env::var("DOCKER_HOST") {
+            if let Some(path) = host.strip_prefix("unix://") {
+                candidates.push(PathBuf::from(path));
+            }
+        }
+        candidates.push(PathBuf::from("/var/run/docker.sock"));
+        if let Ok(runtime_dir) = std::env::var("XDG_RUNTIME_DIR") {
+            candidates.push(
+                PathBuf::from(runtime_dir)
+                    .join("podman")
+                    .join("podman.sock"),
+            );
+        }
+        candidates.push(PathBuf::from("/run/podman/podman.sock"));
+
+        candidates
+            .into_iter()
+            .find(|path| path.exists())
+            .map(Self::at)
+    }
+
+    pub fn at(socket: PathBuf) -> Self {
+        Self { socket }
+    }
+
+    /// Running containers
+    pub fn containers(&self) -> Result<Vec<ApiContainer>> {
+        let (status, body) = self.request("GET", "/containers/json")?;
+        if status != 200 {
+            return Err(anyhow::anyhow!(
+                "Container runtime returned HTTP {status} for /containers/json"
+            ));
+        }
+        serde_json::from_str(&body).context("Invalid container list from the container runtime")
+    }
+
+    /// Stop a container, giving it `STOP_TIMEOUT_SECS` to exit
+    pub fn stop(&self, id: &str) -> Result<()> {
+        let (status, body) = self.request(
+            "POST",
+            &format!("/containers/{id}/stop?t={STOP_TIMEOUT_SECS}"),
+        )?;
+        match status {
+            // 304: already stopped
+            204 | 304 => Ok(()),
+            _ => Err(anyhow::anyhow!(
+                "Container runtime returned HTTP {status}: {message}",
+                message = body.trim()
+            )),
+        }
+    }
+
+    /// One HTTP/1.0 request, so the response is never chunked and ends when
+    /// the connection closes
+    #[cfg(unix)]
+    fn request(&self, method: &str, path: &str) -> Result<(u16, String)> {
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixStream;
+
+        let mut stream = UnixStream::connect(&self.socket).with_context(|| {
+            format!(
+                "Failed to connect to container runtime at {}",
+                self.socket.display()
+            )
+        })?;
+        stream.set_read_timeout(Some(API_TIMEOUT))?;
+        stream.set_write_timeout(Some(API_TIMEOUT))?;
+        let request =
+            format!("{method} {path} HTTP/1.0\r\nHost: localhost\r\nContent-Length: 0\r\n\r\n");
+        stream.write_all(request.as_bytes())?;
+
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .context("Failed to read from the container runtime")?;
+        parse_response(&response)
+    }
+
+    #[cfg(not(unix))]
+    fn request(&self, _method: &str, _path: &str) -> Result<(u16, String)> {
+        Err(anyhow::anyhow!(
+            "Container runtime sockets are not supported on this platform"
+        ))
+    }
+}
+
+fn parse_response(response: &str) -> Result<(u16, String)> {
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .context("Invalid HTTP response from the container runtime")?;
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .context("Invalid HTTP status from the container runtime")?;
+    Ok((status, body.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port_info(port: u16, process_name: &str, command: &str) -> PortInfo {
+        PortInfo {
+            command: command.to_string(),
+            ..PortInfo::listener(port, 0, process_name)
+        }
+    }
+
+    const CONTAINERS_JSON: &str = r#"[
+        {
+            "Id": "4f1c2a9d8e7b6c5a4f1c2a9d8e7b6c5a4f1c2a9d8e7b6c5a4f1c2a9d8e7b6c5a",
+            "Names": ["/shop-web-1"],
+            "Image": "nginx:1.25",
+            "Labels": {"com.docker.compose.service": "web"},
+            "Ports": [{"IP": "0.0.0.0", "PrivatePort": 80, "PublicPort": 8080, "Type": "tcp"}],
+            "NetworkSettings": {"Networks": {"shop_default": {"IPAddress": "172.18.0.2"}}}
+        },
+        {
+            "Id": "9a8b7c6d5e4f9a8b7c6d5e4f9a8b7c6d5e4f9a8b7c6d5e4f9a8b7c6d5e4f9a8b",
+            "Names": ["/pg"],
+            "Image": "postgres:16",
+            "Labels": null,
+            "Ports": [{"PrivatePort": 5432, "PublicPort": 5433, "Type": "tcp"}],
+            "NetworkSettings": {"Networks": {"bridge": {"IPAddress": "172.17.0.3"}}}
+        }
+    ]"#;
+
+    #[test]
+    fn test_match_container() {
+        let containers: Vec<ApiContainer> = serde_json::from_str(CONTAINERS_JSON).unwrap();
+
+        let proxy = port_info(
+            8080,
+            "docker-proxy",
+            "/usr/bin/docker-proxy -proto tcp -host-ip 0.0.0.0 -host-port 8080 -container-ip 172.18.0.2 -container-port 80",
+        );
+        let web = match_container(&proxy, None, &containers).unwrap();
+        assert_eq!(web.name, "shop-web-1");
+        assert_eq!(
+            web.describe(),
+            "shop-web-1 (nginx:1.25, compose service web)"
+        );
+
+        // rootlessport has no target args; fall back to the published port
+        let rootless = port_info(5433, "rootlessport", "rootlessport");
+        let pg = match_container(&rootless, None, &containers).unwrap();
+        assert_eq!(pg.name, "pg");
+        assert_eq!(pg.published, vec![5433]);
+
+        // A host-network process inside a container is matched by its cgroup
+        let cgroup = "0::/system.slice/docker-9a8b7c6d5e4f9a8b7c6d5e4f9a8b7c6d5e4f9a8b7c6d5e4f9a8b7c6d5e4f9a8b.scope\n";
+        let id = cgroup_container_id(cgroup);
+        let inside = port_info(9000, "node", "node server.js");
+        assert_eq!(
+            match_container(&inside, id.as_deref(), &containers)
+                .unwrap()
+                .image,
+            "postgres:16"
+        );
+
+        assert!(is_proxy("rootlessport-ch"));
+        assert!(!is_proxy("node"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_runtime_api_over_socket() {
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixListener;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let socket = dir.path().join("docker.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+
+        // A fake runtime answering the two requests portman makes
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = String::new();
+                let mut buffer = [0u8; 1024];
+                while !request.ends_with("\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.push_str(&String::from_utf8_lossy(&buffer[..read]));
+                }
+                let response = if request.starts_with("GET /containers/json ") {
+                    format!(
+                        "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{CONTAINERS_JSON}"
+                    )
+                } else {
+                    "HTTP/1.0 204 No Content\r\n\r\n".to_string()
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+                requests.push(request.lines().next().unwrap().to_string());
+            }
+            requests
+        });
+
+        let runtime = ContainerRuntime::at(socket);
+        let containers = runtime.containers().unwrap();
+        assert_eq!(containers.len(), 2);
+        runtime.stop(&containers[1].id).unwrap();
+
+        let requests = server.join().unwrap();
+        assert_eq!(
+            requests[1],
+            format!(
+                "POST /containers/{id}/stop?t=10 HTTP/1.0",
+                id = containers[1].id
+            )
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use serde_json::Value as JsonValue;
+use serde_yaml::Value as YamlValue;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const COMPOSE_FILES: &[&str] = &[
+    "compose.yaml",
+    "compose.yml",
+    "docker-compose.yaml",
+    "docker-compose.yml",
+];
+
+const ENV_FILES: &[&str] = &[".env", ".env.local", ".env.development"];
+
+/// A port found in a project file, with where it came from
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedPort {
+    pub port: u16,
+    /// Compose service, Procfile process type, script name or env key prefix
+    pub service: Option<String>,
+    /// Human-readable provenance, e.g. `docker-compose.yml services.db.ports`
+    pub source: String,
+}
+
+/// Collect ports declared in compose files, .env files, the Procfile and
+/// package.json scripts, keeping the first source for each port
+pub fn detect_ports(dir: &Path) -> Vec<DetectedPort> {
+    let env = ENV_FILES
+        .iter()
+        .filter_map(|name| fs::read_to_string(dir.join(name)).ok())
+        .flat_map(|content| parse_env_file(&content))
+        .collect::<HashMap<_, _>>();
+
+    let mut detected = Vec::new();
+
+    for name in COMPOSE_FILES {
+        if let Ok(content) = fs::read_to_string(dir.join(name)) {
+            detected.extend(ports_from_compose(&content, name, &env));
+        }
+    }
+
+    for name in ENV_FILES {
+        if let Ok(content) = fs::read_to_string(dir.join(name)) {
+            detected.extend(ports_from_env(&content, name));
+        }
+    }
+
+    if let Ok(content) = fs::read_to_string(dir.join("Procfile")) {
+        detected.extend(ports_from_procfile(&content));
+    }
+
+    if let Ok(content) = fs::read_to_string(dir.join("package.json")) {
+        detected.extend(ports_from_package_json(&content));
+    }
+
+    let mut seen = Vec::new();
+    detected.retain(|found| {
+        if seen.contains(&found.port) {
+            false
+        } else {
+            seen.push(found.port);
+            true
+        }
+    });
+    detected
+}
+
+/// Host ports published by `services.*.ports` in a compose file
+pub fn ports_from_compose(
+    content: &str,
+    file_name: &str,
+    env: &HashMap<String, String>,
+) -> Vec<DetectedPort> {
+    let Ok(document) = serde_yaml::from_str::<YamlValue>(content) else {
+        return Vec::new();
+    };
+    let Some(services) = document.get("services").and_then(YamlValue::as_mapping) else {
+        return Vec::new();
+    };
+
+    let mut detected = Vec::new();
+    for (name, service) in services {
+        let Some(name) = name.as_str() else {
+            continue;
+        };
+        let Some(ports) = service.get("ports").and_then(YamlValue::as_sequence) else {
+            continue;
+        };
+
+        for entry in ports {
+            let published = match entry {
+                YamlValue::String(mapping) => host_ports_from_mapping(&interpolate(mapping, env)),
+                // A bare container port is published on a random host port
+                YamlValue::Number(_) => Vec::new(),
+                YamlValue::Mapping(_) => entry
+                    .get("published")
+                    .and_then(|published| match published {
+                        YamlValue::Number(number) => number.as_u64().map(|n| n.to_string()),
+                        YamlValue::String(text) => Some(interpolate(text, env)),
+                        _ => None,
+                    })
+                    .map(|published| parse_port_or_range(&published))
+                    .unwrap_or_default(),
+                _ => Vec::new(),
+            };
+
+            for port in published {
+                detected.push(DetectedPort {
+                    port,
+                    service: Some(name.to_string()),
+                    source: format!("{file_name} services.{name}.ports"),
+                });
+            }
+        }
+    }
+
+    detected
+}
+
+/// Host side of a short-syntax mapping: `8080:80`, `127.0.0.1:8080:80/tcp`,
+/// `8000-8002:8000-8002`. A lone container port has no fixed host port.
+fn host_ports_from_mapping(mapping: &str) -> Vec<u16> {
+    let mapping = mapping.split('/').next().unwrap_or(mapping);
+    let mut parts = mapping.rsplitn(3, ':');
+    let _container = parts.next();
+    match parts.next() {
+        Some(host) => parse_port_or_range(host),
+        None => Vec::new(),
+    }
+}
+
+fn parse_port_or_range(value: &str) -> Vec<u16> {
+    let value = value.trim();
+    if let Some((start, end)) = value.split_once('-') {
+        match (start.parse::<u16>(), end.parse::<u16>()) {
+            (Ok(start), Ok(end)) if start <= end && end - start < 100 => (start..=end).collect(),
+            _ => Vec::new(),
+        }
+    } else {
+        value
+            .parse::<u16>()
+            .ok()
+            .filter(|&port| port != 0)
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Expand `${VAR}`, `${VAR:-default}` and `${VAR-default}` from the .env values
+fn interpolate(text: &str, env: &HashMap<String, String>) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            result.push_str(&rest[start..]);
+            return result;
+        };
+
+        let expression = &rest[start + 2..start + end];
+        let (name, default) = match expression
+            .split_once(":-")
+            .or_else(|| expression.split_once('-'))
+        {
+            Some((name, default)) => (name, Some(default)),
+            None => (expression, None),
+        };
+        if let Some(value) = env.get(name).map(String::as_str).or(default) {
+            result.push_str(value);
+        }
+        rest = &rest[start + end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// `KEY=value` pairs from a dotenv file
+fn parse_env_file(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.split(" #").next().unwrap_or(value).trim();
+            let value = value.trim_matches(|c| c == '"' || c == '\'');
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// `PORT=3000` and `*_PORT=5432` style keys; the key prefix becomes the service name
+pub fn ports_from_env(content: &str, file_name: &str) -> Vec<DetectedPort> {
+    parse_env_file(content)
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let service = if key == "PORT" {
+                None
+            } else {
+                let prefix = key.strip_suffix("_PORT")?;
+                Some(prefix.to_lowercase().replace('_', "-"))
+            };
+            let port = value.parse::<u16>().ok().filter(|&port| port != 0)?;
+
+            Some(DetectedPort {
+                port,
+                service,
+                source: format!("{file_name} {key}"),
+            })
+        })
+        .collect()
+}
+
+/// `-p 3000`, `-p3000`, `--port 3000` and `--port=3000` in a command line
+fn port_flags(command: &str) -> Vec<u16> {
+    let mut ports = Vec::new();
+    let mut words = command.split_whitespace();
+
+    while let Some(word) = words.next() {
+        let value = if word == "-p" || word == "--port" {
+            words.next()
+        } else if let Some(value) = word.strip_prefix("--port=") {
+            Some(value)
+        } else {
+            word.strip_prefix("-p").filter(|value| !value.is_empty())
+        };
+
+        if let Some(port) = value
+            .map(|value| value.trim_matches(|c| c == '"' || c == '\''))
+            .and_then(|value| value.parse::<u16>().ok())
+            .filter(|&port| port != 0)
+        {
+            ports.push(port);
+        }
+    }
+
+    ports
+}
+
+/// Ports passed via `-p`/`--port` to Procfile process types
+pub fn ports_from_procfile(content: &str) -> Vec<DetectedPort> {
+    content
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .flat_map(|(process_type, command)| {
+            let process_type = process_type.trim().to_string();
+            port_flags(command)
+                .into_iter()
+                .map(move |port| DetectedPort {
+                    port,
+                    service: Some(process_type.clone()),
+                    source: format!("Procfile {process_type}"),
+                })
+        })
+        .collect()
+}
+
+/// Ports passed via `-p`/`--port` in package.json scripts
+pub fn ports_from_package_json(content: &str) -> Vec<DetectedPort> {
+    let Ok(package) = serde_json::from_str::<JsonValue>(content) else {
+        return Vec::new();
+    };
+    let Some(scripts) = package.get("scripts").and_then(JsonValue::as_object) else {
+        return Vec::new();
+    };
+
+    scripts
+        .iter()
+        .filter_map(|(name, script)| Some((name, script.as_str()?)))
+        .flat_map(|(name, script)| {
+            port_flags(script)
+                .into_iter()
+                .map(move |port| DetectedPort {
+                    port,
+                    service: Some(name.clone()),
+                    source: format!("package.json scripts.{name}"),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compose_ports() {
+        let compose = r#"
+services:
+  db:
+    image: postgres:16
+    ports:
+      - "5432:5432"
+  web:
+    ports:
+      - "127.0.0.1:8080:80/tcp"
+      - "${WEB_DEBUG_PORT:-9229}:9229"
+      - "3000"
+  cache:
+    ports:
+      - target: 6379
+        published: 6380
+  workers:
+    ports:
+      - "7000-7002:7000-7002"
+"#;
+        let env = HashMap::new();
+        let ports: Vec<u16> = ports_from_compose(compose, "compose.yaml", &env)
+            .iter()
+            .map(|found| found.port)
+            .collect();
+
+        assert_eq!(ports, vec![5432, 8080, 9229, 6380, 7000, 7001, 7002]);
+
+        let detected = ports_from_compose(compose, "compose.yaml", &env);
+        assert_eq!(detected[0].service.as_deref(), Some("db"));
+        assert_eq!(detected[0].source, "compose.yaml services.db.ports");
+    }
+
+    #[test]
+    fn test_compose_interpolates_env() {
+        let compose = "services:\n  api:\n    ports:\n      - \"${API_PORT}:8000\"\n";
+        let env = HashMap::from([("API_PORT".to_string(), "4100".to_string())]);
+
+        let detected = ports_from_compose(compose, "compose.yaml", &env);
+        assert_eq!(detected.len(), 1);
+        assert_eq!(detected[0].port, 4100);
+    }
+
+    #[test]
+    fn test_env_ports() {
+        let content = "PORT=3000\nexport DB_PORT=\"5432\"\nREDIS_URL=redis://localhost:6379\n# API_PORT=9999\nVITE_DEV_PORT=5173 # dev server\n";
+        let detected = ports_from_env(content, ".env");
+
+        assert_eq!(detected.len(), 3);
+        assert_eq!(detected[0].port, 3000);
+        assert_eq!(detected[0].service, None);
+        assert_eq!(detected[1].service.as_deref(), Some("db"));
+        assert_eq!(detected[2].port, 5173);
+        assert_eq!(detected[2].service.as_deref(), Some("vite-dev"));
+    }
+
+    #[test]
+    fn test_procfile_ports() {
+        let content = "web: bundle exec rails server -p 3000\nworker: bundle exec sidekiq\napi: uvicorn app:main --port=8000\nadmin: ./bin/admin -p$PORT\n";
+        let detected = ports_from_procfile(content);
+
+        assert_eq!(detected.len(), 2);
+        assert_eq!(detected[0].port, 3000);
+        assert_eq!(detected[0].source, "Procfile web");
+        assert_eq!(detected[1].port, 8000);
+    }
+
+    #[test]
+    fn test_package_json_ports() {
+        let content = r#"{
+  "name": "app",
+  "scripts": {
+    "dev": "vite --port 5173",
+    "storybook": "storybook dev -p 6006",
+    "build": "vite build"
+  }
+}"#;
+        let detected = ports_from_package_json(content);
+        let ports: Vec<u16> = detected.iter().map(|found| found.port).collect();
+
+        assert_eq!(ports, vec![5173, 6006]);
+        assert_eq!(detected[1].source, "package.json scripts.storybook");
+    }
+}
@@ -0,0 +1,281 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Host used for targets given as a bare port
+const DEFAULT_TARGET_HOST: &str = "127.0.0.1";
+
+/// A local port relayed to a target address
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mapping {
+    pub listen: u16,
+    /// `host:port`
+    pub target: String,
+}
+
+impl Mapping {
+    /// Whether the target is the listening socket itself, which would relay
+    /// every connection back into the forwarder
+    pub fn forwards_to_itself(&self, bind: IpAddr) -> bool {
+        let Some((host, port)) = self.target.rsplit_once(':') else {
+            return false;
+        };
+        if port.parse() != Ok(self.listen) {
+            return false;
+        }
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.eq_ignore_ascii_case("localhost") {
+            return bind.is_loopback() || bind.is_unspecified();
+        }
+        // `::` also accepts IPv4 connections on dual-stack hosts
+        let covers = |ip: IpAddr| bind.is_ipv6() || ip.is_ipv4();
+        host.parse::<IpAddr>().is_ok_and(|ip| {
+            ip == bind
+                || (bind.is_unspecified()
+                    && (ip.is_loopback() || ip.is_unspecified())
+                    && covers(ip))
+        })
+    }
+}
+
+impl fmt::Display for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.listen, self.target)
+    }
+}
+
+/// Parse `LISTEN TARGET` (two bare ports) or any number of
+/// `LISTEN:PORT` / `LISTEN:HOST:PORT` mappings
+pub fn parse_mappings(args: &[String]) -> Result<Vec<Mapping>> {
+    if let [listen, target] = args {
+        if let (Ok(listen), Ok(target)) = (listen.parse::<u16>(), target.parse::<u16>()) {
+            return Ok(vec![Mapping {
+                listen,
+                target: format!("{DEFAULT_TARGET_HOST}:{target}"),
+            }]);
+        }
+    }
+
+    args.iter().map(|arg| parse_mapping(arg)).collect()
+}
+
+fn parse_mapping(arg: &str) -> Result<Mapping> {
+    let invalid = || {
+        anyhow::anyhow!(
+            "Invalid mapping '{arg}'. Use LISTEN:PORT or LISTEN:HOST:PORT (e.g. 3000:3001)"
+        )
+    };
+
+    let (listen, target) = arg.split_once(':').ok_or_else(invalid)?;
+    let listen = listen.parse::<u16>().map_err(|_| invalid())?;
+
+    let target = match target.parse::<u16>() {
+        Ok(port) => format!("{DEFAULT_TARGET_HOST}:{port}"),
+        Err(_) => {
+            let (host, port) = target.rsplit_once(':').ok_or_else(invalid)?;
+            if host.is_empty() || port.parse::<u16>().is_err() {
+                return Err(invalid());
+            }
+            target.to_string()
+        }
+    };
+
+    Ok(Mapping { listen, target })
+}
+
+/// Traffic through one mapping since the forwarder started
+#[derive(Debug, Default)]
+pub struct Counters {
+    pub connections: AtomicU64,
+    /// Bytes from clients to the target
+    pub sent: AtomicU64,
+    /// Bytes from the target back to clients
+    pub received: AtomicU64,
+}
+
+/// What happened to a relayed connection
+#[derive(Debug)]
+pub enum ConnectionEvent {
+    Opened {
+        id: u64,
+        peer: SocketAddr,
+    },
+    Closed {
+        id: u64,
+        peer: SocketAddr,
+        sent: u64,
+        received: u64,
+        elapsed: Duration,
+    },
+    Failed {
+        id: u64,
+        peer: SocketAddr,
+        error: String,
+    },
+}
+
+pub type EventHandler = Arc<dyn Fn(ConnectionEvent) + Send + Sync>;
+
+/// Accept connections on `listener` and relay each one to `target`
+pub async fn serve(
+    listener: TcpListener,
+    target: String,
+    counters: Arc<Counters>,
+    on_event: EventHandler,
+) {
+    while let Ok((client, peer)) = listener.accept().await {
+        let id = counters.connections.fetch_add(1, Ordering::Relaxed) + 1;
+        let target = target.clone();
+        let counters = counters.clone();
+        let on_event = on_event.clone();
+        on_event(ConnectionEvent::Opened { id, peer });
+
+        tokio::spawn(async move {
+            let started = Instant::now();
+            match relay(client, &target, &counters).await {
+                Ok((sent, received)) => {
+                    on_event(ConnectionEvent::Closed {
+                        id,
+                        peer,
+                        sent,
+                        received,
+                        elapsed: started.elapsed(),
+                    });
+                }
+                Err(e) => on_event(ConnectionEvent::Failed {
+                    id,
+                    peer,
+                    error: format!("{e:#}"),
+                }),
+            }
+        });
+    }
+}
+
+/// Relay one connection in both directions. Bytes are added to `counters`
+/// as they are copied, so connections that fail or are still open when the
+/// forwarder stops are counted too.
+async fn relay(mut client: TcpStream, target: &str, counters: &Counters) -> Result<(u64, u64)> {
+    let mut upstream = TcpStream::connect(target)
+        .await
+        .with_context(|| format!("Failed to connect to {target}"))?;
+
+    let (client_read, client_write) = client.split();
+    let (upstream_read, upstream_write) = upstream.split();
+    tokio::try_join!(
+        pipe(client_read, upstream_write, &counters.sent),
+        pipe(upstream_read, client_write, &counters.received)
+    )
+    .context("Connection reset")
+}
+
+/// Copy until end of stream, then close the writing side
+async fn pipe(
+    mut reader: impl AsyncRead + Unpin,
+    mut writer: impl AsyncWrite + Unpin,
+    counter: &AtomicU64,
+) -> std::io::Result<u64> {
+    let mut buffer = vec![0u8; 16 * 1024];
+    let mut copied = 0;
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            writer.shutdown().await?;
+            return Ok(copied);
+        }
+        writer.write_all(&buffer[..read]).await?;
+        copied += read as u64;
+        counter.fetch_add(read as u64, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mappings() {
+        let args = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            parse_mappings(&args(&["3000", "3001"])).unwrap(),
+            vec![Mapping {
+                listen: 3000,
+                target: "127.0.0.1:3001".to_string()
+            }]
+        );
+
+        let mappings = parse_mappings(&args(&["3000:3001", "5433:db.internal:5432"])).unwrap();
+        assert_eq!(mappings[1].listen, 5433);
+        assert_eq!(mappings[1].target, "db.internal:5432");
+        assert_eq!(mappings[0].to_string(), "3000 -> 127.0.0.1:3001");
+
+        assert!(parse_mappings(&args(&["3000"])).is_err());
+        assert!(parse_mappings(&args(&["3000:host"])).is_err());
+        assert!(parse_mappings(&args(&["web:3001"])).is_err());
+    }
+
+    #[test]
+    fn test_forwards_to_itself() {
+        let mapping = |listen, target: &str| Mapping {
+            listen,
+            target: target.to_string(),
+        };
+        let loopback: IpAddr = "127.0.0.1".parse().unwrap();
+        let any: IpAddr = "0.0.0.0".parse().unwrap();
+
+        assert!(mapping(3000, "127.0.0.1:3000").forwards_to_itself(loopback));
+        assert!(mapping(3000, "localhost:3000").forwards_to_itself(any));
+        assert!(mapping(3000, "[::1]:3000").forwards_to_itself("::".parse().unwrap()));
+        assert!(!mapping(3000, "[::1]:3000").forwards_to_itself(any));
+        assert!(!mapping(3000, "127.0.0.1:3001").forwards_to_itself(loopback));
+        assert!(!mapping(3000, "db.internal:3000").forwards_to_itself(any));
+        assert!(!mapping(3000, "[::1]:3000").forwards_to_itself(loopback));
+    }
+
+    #[tokio::test]
+    async fn test_relay_counts_bytes() {
+        let echo = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let echo_addr = echo.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = echo.accept().await.unwrap();
+            let mut buffer = [0u8; 64];
+            let read = stream.read(&mut buffer).await.unwrap();
+            stream.write_all(&buffer[..read]).await.unwrap();
+            stream.write_all(b"!").await.unwrap();
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listen_addr = listener.local_addr().unwrap();
+        let counters = Arc::new(Counters::default());
+        let (closed_tx, mut closed_rx) = tokio::sync::mpsc::unbounded_channel();
+        let on_event: EventHandler = Arc::new(move |event| {
+            if let ConnectionEvent::Closed { .. } = event {
+                let _ = closed_tx.send(());
+            }
+        });
+        tokio::spawn(serve(
+            listener,
+            echo_addr.to_string(),
+            counters.clone(),
+            on_event,
+        ));
+
+        let mut client = TcpStream::connect(listen_addr).await.unwrap();
+        client.write_all(b"ping").await.unwrap();
+        let mut reply = Vec::new();
+        client.read_to_end(&mut reply).await.unwrap();
+        drop(client);
+        closed_rx.recv().await.unwrap();
+
+        assert_eq!(reply, b"ping!");
+        assert_eq!(counters.connections.load(Ordering::Relaxed), 1);
+        assert_eq!(counters.sent.load(Ordering::Relaxed), 4);
+        assert_eq!(counters.received.load(Ordering::Relaxed), 5);
+    }
+}
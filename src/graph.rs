@@ -0,0 +1,278 @@
+use crate::scanner::{Connection, PortInfo};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// The client end of a connection to a local listener
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Client {
+    Process {
+        pid: u32,
+        name: String,
+    },
+    /// A local socket whose owner could not be read (usually another user's)
+    Unknown,
+    /// A peer on another host
+    Remote(String),
+}
+
+impl fmt::Display for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Client::Process { pid, name } => write!(f, "{name} ({pid})"),
+            Client::Unknown => write!(f, "unknown process"),
+            Client::Remote(address) => write!(f, "{address}"),
+        }
+    }
+}
+
+/// Clients of one local listening port
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub client: Client,
+    pub port: u16,
+    pub service: Option<String>,
+    /// Unknown when the listener belongs to another user
+    pub server_pid: Option<u32>,
+    pub server_name: String,
+    pub connections: usize,
+}
+
+impl Edge {
+    pub fn server_label(&self) -> String {
+        let owner = match self.server_pid {
+            Some(pid) => format!("{name} {pid}", name = self.server_name),
+            None => self.server_name.clone(),
+        };
+        match &self.service {
+            Some(service) => format!("{port} {service} ({owner})", port = self.port),
+            None => format!("{port} ({owner})", port = self.port),
+        }
+    }
+}
+
+/// Map established connections to the local listeners they reach.
+///
+/// A connection between two local processes appears twice in the socket
+/// table; the client side (whose remote port is a listener) names the
+/// client process, so the server side of local pairs is skipped. Server-side
+/// sockets with a peer on another host become `Client::Remote`.
+pub fn build_edges(listeners: &[PortInfo], connections: &[Connection]) -> Vec<Edge> {
+    let mut servers: HashMap<u16, &PortInfo> = HashMap::new();
+    for listener in listeners {
+        servers.entry(listener.port).or_insert(listener);
+    }
+
+    let local_addresses: HashSet<&str> = connections
+        .iter()
+        .map(|connection| connection.local_addr.as_str())
+        .collect();
+    let is_local = |address: &str| {
+        address
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+            || address.starts_with("::ffff:127.")
+            || local_addresses.contains(address)
+    };
+
+    let mut counts: BTreeMap<(u16, Client), usize> = BTreeMap::new();
+    for connection in connections {
+        let key = if is_local(&connection.remote_addr)
+            && servers.contains_key(&connection.remote_port)
+        {
+            let client = match (connection.pid, &connection.process_name) {
+                (Some(pid), Some(name)) => Client::Process {
+                    pid,
+                    name: name.clone(),
+                },
+                (Some(pid), None) => Client::Process {
+                    pid,
+                    name: "?".to_string(),
+                },
+                (None, _) => Client::Unknown,
+            };
+            (connection.remote_port, client)
+        } else if servers.contains_key(&connection.local_port) && !is_local(&connection.remote_addr)
+        {
+            (
+                connection.local_port,
+                Client::Remote(connection.remote_addr.clone()),
+            )
+        } else {
+            continue;
+        };
+        *counts.entry(key).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .map(|((port, client), connections)| {
+            let server = servers[&port];
+            Edge {
+                client,
+                port,
+                service: server.service.clone(),
+                server_pid: server.pid,
+                server_name: server.owner_name().to_string(),
+                connections,
+            }
+        })
+        .collect()
+}
+
+/// Fill in the connection count and peers of every listening port.
+///
+/// Connections are matched by port only, so when several sockets listen on
+/// one port (IPv4 and IPv6, or `SO_REUSEPORT`) they are counted once, on the
+/// first of them like in `build_edges`, instead of once per socket.
+pub fn annotate_connections(ports: &mut [PortInfo], connections: &[Connection]) {
+    let edges = build_edges(ports, connections);
+    let mut annotated = HashSet::new();
+    for port_info in ports {
+        if !annotated.insert(port_info.port) {
+            continue;
+        }
+        let clients = edges.iter().filter(|edge| edge.port == port_info.port);
+        port_info.connections = clients.clone().map(|edge| edge.connections).sum();
+        port_info.peers = clients.map(|edge| edge.client.to_string()).collect();
+    }
+}
+
+/// Graphviz DOT with one node per client and per listener
+pub fn to_dot(edges: &[Edge]) -> String {
+    let quote = |label: &str| format!("\"{}\"", label.replace('\\', "\\\\").replace('"', "\\\""));
+
+    let mut dot = String::from("digraph portman {\n  rankdir=LR;\n  node [shape=box];\n");
+    for edge in edges {
+        dot.push_str(&format!(
+            "  {client} -> {server} [label=\"{count}\"];\n",
+            client = quote(&edge.client.to_string()),
+            server = quote(&edge.server_label()),
+            count = edge.connections
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Mermaid flowchart; node ids are generated because labels contain
+/// characters Mermaid does not accept in ids
+pub fn to_mermaid(edges: &[Edge]) -> String {
+    let escape = |label: &str| label.replace('"', "#quot;");
+    let mut client_ids: BTreeMap<String, usize> = BTreeMap::new();
+
+    let mut mermaid = String::from("graph LR\n");
+    for edge in edges {
+        let client = edge.client.to_string();
+        let next_id = client_ids.len();
+        let client_id = *client_ids.entry(client.clone()).or_insert(next_id);
+        mermaid.push_str(&format!(
+            "  c{client_id}[\"{client}\"] -->|{count}| p{port}[\"{server}\"]\n",
+            client = escape(&client),
+            count = edge.connections,
+            port = edge.port,
+            server = escape(&edge.server_label())
+        ));
+    }
+    mermaid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listener(port: u16, pid: u32, name: &str) -> PortInfo {
+        PortInfo {
+            service: Some("postgresql".to_string()),
+            ..PortInfo::listener(port, pid, name)
+        }
+    }
+
+    fn connection(local: (&str, u16), remote: (&str, u16), pid: Option<u32>) -> Connection {
+        Connection {
+            local_addr: local.0.to_string(),
+            local_port: local.1,
+            remote_addr: remote.0.to_string(),
+            remote_port: remote.1,
+            pid,
+            process_name: pid.map(|_| "node".to_string()),
+            recv_q: 0,
+            send_q: 0,
+        }
+    }
+
+    #[test]
+    fn test_build_edges() {
+        let listeners = vec![listener(5432, 100, "postgres")];
+        let connections = vec![
+            // Two pooled connections from node, each seen from both ends
+            connection(("127.0.0.1", 40000), ("127.0.0.1", 5432), Some(4242)),
+            connection(("127.0.0.1", 5432), ("127.0.0.1", 40000), Some(100)),
+            connection(("127.0.0.1", 40001), ("127.0.0.1", 5432), Some(4242)),
+            connection(("127.0.0.1", 5432), ("127.0.0.1", 40001), Some(100)),
+            // A client on another host
+            connection(("10.0.0.5", 5432), ("10.0.0.9", 51000), Some(100)),
+            // Outgoing traffic unrelated to local listeners
+            connection(("10.0.0.5", 52000), ("140.82.112.3", 443), Some(4242)),
+        ];
+
+        let edges = build_edges(&listeners, &connections);
+
+        assert_eq!(edges.len(), 2);
+        assert_eq!(
+            edges[0].client,
+            Client::Process {
+                pid: 4242,
+                name: "node".to_string()
+            }
+        );
+        assert_eq!(edges[0].connections, 2);
+        assert_eq!(edges[0].server_pid, Some(100));
+        assert_eq!(edges[1].client, Client::Remote("10.0.0.9".to_string()));
+    }
+
+    #[test]
+    fn test_annotate_connections() {
+        let mut ports = vec![
+            listener(5432, 100, "postgres"),
+            listener(6379, 200, "redis"),
+            // The IPv6 socket of the same postgres
+            listener(5432, 100, "postgres"),
+        ];
+        let connections = vec![
+            connection(("127.0.0.1", 40000), ("127.0.0.1", 5432), Some(4242)),
+            connection(("127.0.0.1", 40001), ("127.0.0.1", 5432), None),
+            connection(("10.0.0.5", 5432), ("10.0.0.9", 51000), Some(100)),
+        ];
+
+        annotate_connections(&mut ports, &connections);
+
+        assert_eq!(ports[0].connections, 3);
+        assert_eq!(
+            ports[0].peers,
+            vec!["node (4242)", "unknown process", "10.0.0.9"]
+        );
+        assert_eq!(ports[1].connections, 0);
+        assert!(ports[1].peers.is_empty());
+        assert_eq!(ports[2].connections, 0);
+    }
+
+    #[test]
+    fn test_dot_and_mermaid() {
+        let edges = vec![Edge {
+            client: Client::Process {
+                pid: 4242,
+                name: "node".to_string(),
+            },
+            port: 5432,
+            service: Some("postgresql".to_string()),
+            server_pid: Some(100),
+            server_name: "postgres".to_string(),
+            connections: 2,
+        }];
+
+        assert!(to_dot(&edges)
+            .contains("\"node (4242)\" -> \"5432 postgresql (postgres 100)\" [label=\"2\"];"));
+        assert!(to_mermaid(&edges)
+            .contains("c0[\"node (4242)\"] -->|2| p5432[\"5432 postgresql (postgres 100)\"]"));
+    }
+}
@@ -0,0 +1,156 @@
+use crate::process::ProcessInfo;
+use crate::projects::{config_in_ancestors, Project};
+use crate::scanner::{Connection, PortInfo};
+use crate::systemd::Unit;
+
+/// Placeholder shown instead of environment values unless they are requested
+pub const REDACTED: &str = "<redacted>";
+
+/// Everything portman knows about one process
+#[derive(Debug, Clone)]
+pub struct Inspection {
+    pub process: ProcessInfo,
+    /// Ports the process listens on
+    pub listening: Vec<PortInfo>,
+    /// Established connections owned by the process
+    pub connections: Vec<Connection>,
+    /// Project config found in the process working directory or its parents
+    pub project: Option<Project>,
+    /// systemd service the process runs in
+    pub systemd_unit: Option<Unit>,
+}
+
+impl Inspection {
+    /// Gather the sockets and project of `process` from a port and connection scan
+    pub fn new(process: ProcessInfo, ports: &[PortInfo], connections: &[Connection]) -> Self {
+        let listening = ports
+            .iter()
+            .filter(|port_info| port_info.pid == Some(process.pid))
+            .cloned()
+            .collect();
+        let connections = connections
+            .iter()
+            .filter(|connection| connection.pid == Some(process.pid))
+            .cloned()
+            .collect();
+        let project = process
+            .cwd
+            .as_deref()
+            .and_then(config_in_ancestors)
+            .and_then(|config_path| Project::load(&config_path).ok());
+
+        Self {
+            process,
+            listening,
+            connections,
+            project,
+            systemd_unit: None,
+        }
+    }
+
+    /// Environment variables sorted by name, with values replaced by
+    /// `REDACTED` unless `reveal` is set
+    pub fn environment(&self, reveal: bool) -> Vec<(String, String)> {
+        let mut environment: Vec<(String, String)> = self
+            .process
+            .environment
+            .iter()
+            .map(|(key, value)| {
+                let value = if reveal {
+                    value.clone()
+                } else {
+                    REDACTED.to_string()
+                };
+                (key.clone(), value)
+            })
+            .collect();
+        environment.sort();
+        environment
+    }
+
+    /// Service name of a listening port, preferring the project's own name for it
+    pub fn service_name(&self, port_info: &PortInfo) -> Option<String> {
+        self.project
+            .as_ref()
+            .and_then(|project| project.services.get(&port_info.port).cloned())
+            .or_else(|| port_info.service.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    fn process(cwd: Option<std::path::PathBuf>) -> ProcessInfo {
+        ProcessInfo {
+            pid: 4242,
+            name: "node".to_string(),
+            argv: vec!["node".to_string(), "server.js".to_string()],
+            exe: None,
+            cwd,
+            uid: Some(1000),
+            user: Some("dev".to_string()),
+            parent_chain: Vec::new(),
+            children: Vec::new(),
+            memory_usage: 0,
+            cpu_usage: 0.0,
+            start_time: 0,
+            runtime_duration: "0s".to_string(),
+            environment: vec![
+                ("PORT".to_string(), "3000".to_string()),
+                ("API_TOKEN".to_string(), "secret".to_string()),
+            ],
+        }
+    }
+
+    fn listener(port: u16, pid: u32) -> PortInfo {
+        PortInfo {
+            command: "node server.js".to_string(),
+            service: Some("http-alt".to_string()),
+            ..PortInfo::listener(port, pid, "node")
+        }
+    }
+
+    #[test]
+    fn test_environment_is_redacted_by_default() {
+        let inspection = Inspection::new(process(None), &[], &[]);
+
+        assert_eq!(
+            inspection.environment(false),
+            vec![
+                ("API_TOKEN".to_string(), REDACTED.to_string()),
+                ("PORT".to_string(), REDACTED.to_string()),
+            ]
+        );
+        assert_eq!(inspection.environment(true)[0].1, "secret");
+    }
+
+    #[test]
+    fn test_sockets_and_project() {
+        let root = tempfile::TempDir::new().unwrap();
+        let src = root.path().join("web").join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(
+            root.path().join("web").join(".portman.yaml"),
+            "project: web\nports: [8080]\nservices:\n  8080: frontend\n",
+        )
+        .unwrap();
+
+        let ports = vec![listener(8080, 4242), listener(5432, 100)];
+        let inspection = Inspection::new(process(Some(src)), &ports, &[]);
+
+        assert_eq!(inspection.listening.len(), 1);
+        let project = inspection.project.as_ref().unwrap();
+        assert_eq!(project.name, "web");
+        assert_eq!(
+            project.services,
+            BTreeMap::from([(8080, "frontend".to_string())])
+        );
+        assert_eq!(
+            inspection.service_name(&inspection.listening[0]).as_deref(),
+            Some("frontend")
+        );
+    }
+}
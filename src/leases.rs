@@ -0,0 +1,314 @@
+use crate::config::{user_data_dir, ResolvedConfig};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long to wait for another portman process to release the lock
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A port reserved for a project until it is released or expires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lease {
+    pub port: u16,
+    pub project: String,
+    /// Directory of the project that took the lease; processes started
+    /// outside of it are not the owner
+    pub path: PathBuf,
+    /// Unix timestamps in seconds
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+}
+
+impl Lease {
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Whether a process working in `cwd` belongs to the lease holder
+    pub fn is_owned_by(&self, cwd: &Path) -> bool {
+        cwd.starts_with(&self.path)
+    }
+
+    /// Whether the project rooted at `root` holds this lease. Only the
+    /// canonical root counts: two checkouts may share a directory name.
+    pub fn is_held_by(&self, root: &Path) -> bool {
+        self.path == root
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LeaseFile {
+    leases: Vec<Lease>,
+}
+
+/// Lease registry stored as JSON in the user data dir and guarded by a lock file
+/// so that parallel portman invocations do not race
+pub struct LeaseStore {
+    path: PathBuf,
+}
+
+impl LeaseStore {
+    pub fn open() -> Result<Self> {
+        let dir = user_data_dir().context("Could not determine the user data directory")?;
+        Ok(Self::at(dir.join("leases.json")))
+    }
+
+    pub fn at(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Leases that have not expired yet
+    pub fn active(&self) -> Result<Vec<Lease>> {
+        let now = unix_now();
+        Ok(self
+            .read()?
+            .leases
+            .into_iter()
+            .filter(|lease| !lease.is_expired(now))
+            .collect())
+    }
+
+    /// Reserve a port for the project rooted at `path`, or renew it when
+    /// that project already holds it
+    pub fn reserve(
+        &self,
+        port: u16,
+        project: &str,
+        path: &Path,
+        ttl: Option<Duration>,
+    ) -> Result<Lease> {
+        self.update(|leases, now| {
+            if let Some(existing) = leases.iter().find(|lease| lease.port == port) {
+                if !existing.is_held_by(path) {
+                    return Err(anyhow::anyhow!(
+                        "Port {port} is already reserved by {owner}",
+                        owner = existing.project
+                    ));
+                }
+            }
+            leases.retain(|lease| lease.port != port);
+
+            let lease = Lease {
+                port,
+                project: project.to_string(),
+                path: path.to_path_buf(),
+                created_at: now,
+                expires_at: ttl.map(|ttl| now.saturating_add(ttl.as_secs())),
+            };
+            leases.push(lease.clone());
+            leases.sort_by_key(|lease| lease.port);
+            Ok(lease)
+        })
+    }
+
+    /// Release leases on `ports` held by the project rooted at `root` (any
+    /// project when `force`)
+    pub fn release(&self, ports: &[u16], root: &Path, force: bool) -> Result<Vec<Lease>> {
+        self.update(|leases, _| {
+            for lease in leases.iter().filter(|lease| ports.contains(&lease.port)) {
+                if !lease.is_held_by(root) && !force {
+                    return Err(anyhow::anyhow!(
+                        "Port {port} is reserved by {owner}; use --force to release it",
+                        port = lease.port,
+                        owner = lease.project
+                    ));
+                }
+            }
+
+            let (released, kept) = leases
+                .drain(..)
+                .partition(|lease| ports.contains(&lease.port));
+            *leases = kept;
+            Ok(released)
+        })
+    }
+
+    /// Release every lease held by the project rooted at `root`
+    pub fn release_project(&self, root: &Path) -> Result<Vec<Lease>> {
+        self.update(|leases, _| {
+            let (released, kept) = leases.drain(..).partition(|lease| lease.is_held_by(root));
+            *leases = kept;
+            Ok(released)
+        })
+    }
+
+    fn read(&self) -> Result<LeaseFile> {
+        if !self.path.exists() {
+            return Ok(LeaseFile::default());
+        }
+
+        let content = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read leases: {}", self.path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse leases: {}", self.path.display()))
+    }
+
+    /// Read-modify-write under the lock; expired leases are dropped on the way
+    fn update<T>(&self, change: impl FnOnce(&mut Vec<Lease>, u64) -> Result<T>) -> Result<T> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let _lock = LockGuard::acquire(&self.path.with_extension("lock"))?;
+
+        let now = unix_now();
+        let mut file = self.read()?;
+        file.leases.retain(|lease| !lease.is_expired(now));
+
+        let result = change(&mut file.leases, now)?;
+
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(&file)?)?;
+        fs::rename(&temp_path, &self.path)
+            .with_context(|| format!("Failed to write leases: {}", self.path.display()))?;
+
+        Ok(result)
+    }
+}
+
+/// Exclusive advisory lock on a lock file. The OS drops it when the file is
+/// closed, including when a process crashes, so a lock is never left stale.
+struct LockGuard {
+    _file: File,
+}
+
+impl LockGuard {
+    fn acquire(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to create lease lock: {}", path.display()))?;
+        let started = SystemTime::now();
+
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Self { _file: file }),
+                Err(TryLockError::WouldBlock) => {
+                    if started.elapsed().unwrap_or_default() > LOCK_TIMEOUT {
+                        return Err(anyhow::anyhow!(
+                            "Timed out waiting for lease lock: {}",
+                            path.display()
+                        ));
+                    }
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to lock leases: {}", path.display()))
+                }
+            }
+        }
+    }
+}
+
+/// Name and root directory of the project the current directory belongs to:
+/// the directory holding the project config, or the current directory.
+/// The directory name is used rather than the config's `project` so that
+/// worktrees of the same repository count as separate projects.
+pub fn current_project(config: &ResolvedConfig) -> Result<(String, PathBuf)> {
+    let root = match config.project_path.as_ref().and_then(|path| path.parent()) {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => std::env::current_dir()?,
+    };
+    let root = root.canonicalize().unwrap_or(root);
+    let name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| root.display().to_string());
+    Ok((name, root))
+}
+
+/// Time left until a lease expires, e.g. `7h 59m`
+pub fn format_remaining(expires_at: u64) -> String {
+    crate::process::format_duration(expires_at.saturating_sub(unix_now()))
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_and_release() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = LeaseStore::at(dir.path().join("leases.json"));
+        let project_dir = dir.path().join("app");
+        // Another checkout whose directory has the same name
+        let other_dir = dir.path().join("worktrees").join("app");
+
+        store.reserve(3000, "app", &project_dir, None).unwrap();
+        assert!(store.reserve(3000, "app", &other_dir, None).is_err());
+        // Renewing your own lease is fine
+        store
+            .reserve(3000, "app", &project_dir, Some(Duration::from_secs(60)))
+            .unwrap();
+
+        let lease = store.active().unwrap().remove(0);
+        assert_eq!(lease.project, "app");
+        assert!(lease.expires_at.is_some());
+        assert!(lease.is_owned_by(&project_dir.join("frontend")));
+        assert!(!lease.is_owned_by(dir.path()));
+
+        assert!(store.release(&[3000], &other_dir, false).is_err());
+        assert_eq!(store.release(&[3000], &other_dir, true).unwrap().len(), 1);
+        assert!(store.active().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_expired_leases_are_ignored() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = LeaseStore::at(dir.path().join("leases.json"));
+
+        store.reserve(4000, "app", dir.path(), None).unwrap();
+        let mut file = store.read().unwrap();
+        file.leases[0].expires_at = Some(unix_now() - 1);
+        fs::write(
+            dir.path().join("leases.json"),
+            serde_json::to_string(&file).unwrap(),
+        )
+        .unwrap();
+
+        assert!(store.active().unwrap().is_empty());
+        store
+            .reserve(4000, "other", &dir.path().join("other"), None)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_release_project() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = LeaseStore::at(dir.path().join("leases.json"));
+
+        let app_dir = dir.path().join("app");
+        let db_dir = dir.path().join("db");
+
+        store.reserve(3000, "app", &app_dir, None).unwrap();
+        store.reserve(3001, "app", &app_dir, None).unwrap();
+        store.reserve(5432, "db", &db_dir, None).unwrap();
+
+        assert_eq!(store.release_project(&app_dir).unwrap().len(), 2);
+        assert_eq!(store.active().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_lock_is_exclusive_until_dropped() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("leases.lock");
+
+        let lock = LockGuard::acquire(&path).unwrap();
+        let other = File::open(&path).unwrap();
+        assert!(matches!(other.try_lock(), Err(TryLockError::WouldBlock)));
+
+        drop(lock);
+        assert!(other.try_lock().is_ok());
+    }
+}
@@ -1,45 +1,242 @@
+mod agents;
 mod cli;
 mod commands;
 mod config;
+mod containers;
+mod detect;
+mod forward;
+mod graph;
+mod inspect;
+mod leases;
+mod netns;
 mod output;
+mod plan;
+mod port_sets;
+mod probe;
 mod process;
+mod projects;
+mod protection;
+mod remote;
 mod scanner;
+mod services;
+mod snapshot;
+#[cfg(target_os = "linux")]
+mod sock_diag;
+mod systemd;
+mod unix_sockets;
 
 use anyhow::Result;
 use clap::Parser;
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, ConfigAction};
+use commands::check::{ProbeOptions, RemoteOptions};
+use commands::kill::KillOptions;
+use commands::list::{ListFilters, NamespaceScope};
+use config::ResolvedConfig;
+use output::display_error;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    if let Some(backend) = cli.backend {
+        scanner::select_backend(backend);
+    }
+
+    let config = match ResolvedConfig::load() {
+        Ok(config) => config,
+        // `init --force` and `config show` are how a broken file gets fixed
+        Err(e) if matches!(cli.command, Commands::Init { .. } | Commands::Config { .. }) => {
+            display_error(&format!("Failed to load config: {e:#}; using defaults"));
+            ResolvedConfig::defaults()
+        }
+        Err(e) => {
+            display_error(&format!("Failed to load config: {e:#}"));
+            std::process::exit(1);
+        }
+    };
+
+    if !config.color.value {
+        colored::control::set_override(false);
+    }
+
+    let default_format = config.output_format.value.clone();
 
     match cli.command {
         Commands::List {
             range,
             filter,
+            service,
             common,
+            set,
+            project,
+            project_dir,
+            all_namespaces,
+            netns,
+            connections,
+            unix,
             format,
         } => {
-            commands::list::execute(range, filter, common, format).await?;
+            let format = format.unwrap_or(default_format);
+            if unix {
+                commands::list::list_unix_sockets(filter, format).await?;
+            } else {
+                let sets = selected_sets(common, set);
+                let filters = ListFilters {
+                    range,
+                    filter,
+                    service,
+                    sets,
+                    project,
+                    project_dir,
+                };
+                let namespaces = match (all_namespaces, netns) {
+                    (true, _) => NamespaceScope::All,
+                    (false, Some(spec)) => NamespaceScope::One(spec),
+                    (false, None) => NamespaceScope::Current,
+                };
+                commands::list::execute(filters, namespaces, connections, format, &config).await?;
+            }
+        }
+        Commands::Graph { ports, format } => {
+            let format = format.unwrap_or_else(|| default_format.clone().into());
+            commands::graph::execute(ports, format, &config).await?;
         }
-        Commands::Kill { ports, force } => {
-            commands::kill::execute(ports, force).await?;
+        Commands::Kill {
+            ports,
+            socket,
+            force,
+            tree,
+            allow_other_users,
+            format,
+        } => {
+            let options = KillOptions {
+                force,
+                tree,
+                allow_other_users,
+                dry_run: cli.dry_run,
+                format: format.unwrap_or(default_format),
+            };
+            if !socket.is_empty() {
+                commands::kill::kill_sockets(&socket, &options, &config).await?;
+            }
+            if !ports.is_empty() || socket.is_empty() {
+                commands::kill::execute(ports, options, &config).await?;
+            }
         }
-        Commands::Check { ports } => {
-            let all_available = commands::check::execute(ports).await?;
+        Commands::Inspect {
+            target,
+            pid,
+            show_env,
+            format,
+        } => {
+            let format = format.unwrap_or(default_format);
+            if !commands::inspect::execute(target, pid, show_env, format, &config).await? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Check {
+            ports,
+            host,
+            tls,
+            timeout,
+            allow_silent,
+            probe,
+            bind,
+            protocol,
+            suggest,
+            suggest_env,
+        } => {
+            let remote = RemoteOptions::new(host, protocol, tls, timeout, allow_silent);
+            let probe = probe.then(|| ProbeOptions::new(bind, protocol));
+            let all_available =
+                commands::check::execute(ports, probe, suggest, suggest_env, remote, &config)
+                    .await?;
             if !all_available {
                 std::process::exit(1);
             }
         }
-        Commands::Watch { config } => {
-            commands::watch::execute(config).await?;
+        Commands::Watch { config, interval } => {
+            commands::watch::execute(config, interval).await?;
         }
-        Commands::Free { common, force } => {
-            commands::free::execute(common, force).await?;
+        Commands::Free {
+            common,
+            set,
+            force,
+            tree,
+            allow_other_users,
+            format,
+        } => {
+            let options = KillOptions {
+                force,
+                tree,
+                allow_other_users,
+                dry_run: cli.dry_run,
+                format: format.unwrap_or(default_format),
+            };
+            if !commands::free::execute(selected_sets(common, set), options, &config).await? {
+                std::process::exit(1);
+            }
         }
         Commands::Init { force } => {
             commands::init::execute(force).await?;
         }
+        Commands::Conflicts {
+            paths,
+            registered,
+            format,
+        } => {
+            let format = format.unwrap_or(default_format);
+            let no_conflicts = commands::conflicts::execute(paths, registered, format).await?;
+            if !no_conflicts {
+                std::process::exit(1);
+            }
+        }
+        Commands::Reserve {
+            ports,
+            project,
+            ttl,
+        } => {
+            if !commands::lease::reserve(ports, project, ttl, &config).await? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Release { ports, all, force } => {
+            if !commands::lease::release(ports, all, force, &config).await? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Leases { format } => {
+            commands::lease::list(format.unwrap_or(default_format)).await?;
+        }
+        Commands::Hold {
+            ports,
+            bind,
+            protocol,
+            http_status,
+            http_body,
+            duration,
+        } => {
+            let held =
+                commands::hold::execute(ports, bind, protocol, http_status, http_body, duration)
+                    .await?;
+            if !held {
+                std::process::exit(1);
+            }
+        }
+        Commands::Forward {
+            mappings,
+            bind,
+            quiet,
+        } => {
+            if !commands::forward::execute(mappings, bind, quiet).await? {
+                std::process::exit(1);
+            }
+        }
+        Commands::Config { action } => match action {
+            ConfigAction::Show { resolved, format } => {
+                let format = format.unwrap_or(default_format);
+                commands::config::show(resolved, format, &config).await?;
+            }
+        },
         Commands::History { limit } => {
             commands::history::execute(limit).await?;
         }
@@ -47,3 +244,11 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// `--common` is shorthand for `--set common`
+fn selected_sets(common: bool, mut sets: Vec<String>) -> Vec<String> {
+    if common {
+        sets.insert(0, port_sets::COMMON_SET.to_string());
+    }
+    sets
+}
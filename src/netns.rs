@@ -0,0 +1,446 @@
+use crate::containers::{cgroup_container_id, ApiContainer};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// A network namespace and a process inside it through which its
+/// `/proc/<pid>/net` socket tables can be read
+#[derive(Debug, Clone, PartialEq)]
+pub struct Namespace {
+    /// Inode of the namespace, as in `net:[4026531840]`
+    pub id: u64,
+    /// Lowest PID in the namespace
+    pub pid: u32,
+    /// Container or process that owns the namespace, or `host`
+    pub owner: String,
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "net:[{id}] {owner}", id = self.id, owner = self.owner)
+    }
+}
+
+/// Namespace inode from a `/proc/<pid>/ns/net` link target
+pub fn parse_namespace_link(link: &str) -> Option<u64> {
+    link.strip_prefix("net:[")?.strip_suffix(']')?.parse().ok()
+}
+
+/// A listening socket from `/proc/<pid>/net/{tcp,tcp6,udp,udp6}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcSocket {
+    pub address: IpAddr,
+    pub port: u16,
+    pub udp: bool,
+    pub inode: u64,
+    /// Connections waiting to be accepted
+    pub recv_q: u64,
+    /// Outgoing queue; the backlog limit when read over netlink
+    pub send_q: u64,
+    /// User that created the socket
+    pub uid: u32,
+}
+
+/// An established TCP socket from `/proc/<pid>/net/{tcp,tcp6}` or sock_diag
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcConnection {
+    pub local: SocketAddr,
+    pub remote: SocketAddr,
+    pub inode: u64,
+    /// Bytes received but not yet read by the process
+    pub recv_q: u64,
+    /// Bytes sent but not yet acknowledged by the peer
+    pub send_q: u64,
+}
+
+/// An address of a `/proc/net` row: the kernel prints the address as
+/// 32-bit words in host byte order
+fn parse_hex_address(hex: &str) -> Option<IpAddr> {
+    let word = |index: usize| -> Option<[u8; 4]> {
+        let value = u32::from_str_radix(hex.get(index * 8..index * 8 + 8)?, 16).ok()?;
+        Some(value.to_ne_bytes())
+    };
+    match hex.len() {
+        8 => Some(IpAddr::V4(Ipv4Addr::from(word(0)?))),
+        32 => {
+            let mut octets = [0u8; 16];
+            for index in 0..4 {
+                octets[index * 4..index * 4 + 4].copy_from_slice(&word(index)?);
+            }
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+/// Parse one row of a `/proc/net` TCP or UDP table, keeping listening TCP
+/// sockets (state 0A) and unconnected UDP sockets (state 07)
+pub fn parse_proc_net_line(line: &str, udp: bool) -> Option<ProcSocket> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 10 {
+        return None;
+    }
+
+    let listening_state = if udp { "07" } else { "0A" };
+    if fields[3] != listening_state {
+        return None;
+    }
+
+    let (address, port) = fields[1].rsplit_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let (send_q, recv_q) = fields[4].split_once(':')?;
+    let inode = fields[9].parse().ok()?;
+    Some(ProcSocket {
+        address: parse_hex_address(address)?,
+        port,
+        udp,
+        inode,
+        recv_q: u64::from_str_radix(recv_q, 16).ok()?,
+        send_q: u64::from_str_radix(send_q, 16).ok()?,
+        uid: fields[7].parse().ok()?,
+    })
+}
+
+/// `ADDRESS:PORT` of a `/proc/net` row, both in hex
+fn parse_hex_socket_address(field: &str) -> Option<SocketAddr> {
+    let (address, port) = field.rsplit_once(':')?;
+    Some(SocketAddr::new(
+        parse_hex_address(address)?,
+        u16::from_str_radix(port, 16).ok()?,
+    ))
+}
+
+/// Parse one row of a `/proc/net` TCP table, keeping established sockets
+/// (state 01)
+pub fn parse_proc_net_connection(line: &str) -> Option<ProcConnection> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 10 || fields[3] != "01" {
+        return None;
+    }
+
+    let (send_q, recv_q) = fields[4].split_once(':')?;
+    Some(ProcConnection {
+        local: parse_hex_socket_address(fields[1])?,
+        remote: parse_hex_socket_address(fields[2])?,
+        inode: fields[9].parse().ok()?,
+        recv_q: u64::from_str_radix(recv_q, 16).ok()?,
+        send_q: u64::from_str_radix(send_q, 16).ok()?,
+    })
+}
+
+/// Inode from a `/proc/<pid>/fd/<n>` link target such as `socket:[123456]`
+pub fn parse_socket_link(link: &str) -> Option<u64> {
+    link.strip_prefix("socket:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+/// Describe who owns a namespace: a container when its first process runs
+/// in one, otherwise that process
+fn describe_owner(pid: u32, name: &str, containers: &[ApiContainer]) -> String {
+    let container_id = platform::read_cgroup(pid)
+        .as_deref()
+        .and_then(cgroup_container_id);
+    match container_id {
+        Some(id) => containers
+            .iter()
+            .find(|container| container.id == id)
+            .and_then(|container| container.names.first())
+            .map(|name| format!("container {}", name.trim_start_matches('/')))
+            .unwrap_or_else(|| format!("container {}", &id[..12])),
+        None => format!("{name} ({pid})"),
+    }
+}
+
+/// All network namespaces with at least one visible process, the caller's first
+pub fn enumerate(containers: &[ApiContainer]) -> Result<Vec<Namespace>> {
+    let current = platform::namespace_of(std::process::id());
+    let mut lowest: BTreeMap<u64, u32> = BTreeMap::new();
+    for pid in platform::pids()? {
+        if let Some(id) = platform::namespace_of(pid) {
+            lowest
+                .entry(id)
+                .and_modify(|lowest_pid| *lowest_pid = (*lowest_pid).min(pid))
+                .or_insert(pid);
+        }
+    }
+
+    let mut namespaces: Vec<Namespace> = lowest
+        .into_iter()
+        .map(|(id, pid)| Namespace {
+            id,
+            pid,
+            owner: if Some(id) == current {
+                "host".to_string()
+            } else {
+                describe_owner(pid, &platform::process_name(pid), containers)
+            },
+        })
+        .collect();
+    namespaces.sort_by_key(|namespace| Some(namespace.id) != current);
+    Ok(namespaces)
+}
+
+/// The namespace named by a PID or by a namespace file such as
+/// `/run/netns/blue` or `/proc/<pid>/ns/net`
+pub fn resolve(spec: &str, containers: &[ApiContainer]) -> Result<Namespace> {
+    let id = match spec.parse::<u32>() {
+        Ok(pid) => platform::namespace_of(pid)
+            .ok_or_else(|| anyhow::anyhow!("Cannot read the network namespace of PID {pid}"))?,
+        Err(_) => platform::namespace_file_id(spec)?,
+    };
+
+    enumerate(containers)?
+        .into_iter()
+        .find(|namespace| namespace.id == id)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No visible process runs in network namespace net:[{id}]; its sockets cannot be read"
+            )
+        })
+}
+
+/// Listening sockets of a namespace and the PID owning each socket inode.
+/// Sockets of processes whose descriptors cannot be read have no owner.
+pub fn listening_sockets(namespace: &Namespace) -> Result<(Vec<ProcSocket>, HashMap<u64, u32>)> {
+    let sockets = platform::read_tables(namespace.pid)?;
+    Ok((sockets, socket_owners(Some(namespace.id))?))
+}
+
+/// Listening sockets of the caller's own network namespace
+pub fn current_sockets() -> Result<Vec<ProcSocket>> {
+    platform::read_tables(std::process::id())
+}
+
+/// Established TCP sockets of the caller's own network namespace
+pub fn current_connections() -> Result<Vec<ProcConnection>> {
+    platform::read_connections(std::process::id())
+}
+
+/// The PID owning each socket inode, from the open descriptors of every
+/// process (or only those in one namespace). Descriptors of other users'
+/// processes cannot be read without root.
+pub fn socket_owners(namespace_id: Option<u64>) -> Result<HashMap<u64, u32>> {
+    let mut owners = HashMap::new();
+    for pid in platform::pids()? {
+        if namespace_id.is_some() && platform::namespace_of(pid) != namespace_id {
+            continue;
+        }
+        for inode in platform::socket_inodes(pid) {
+            owners.entry(inode).or_insert(pid);
+        }
+    }
+    Ok(owners)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::{
+        parse_namespace_link, parse_proc_net_connection, parse_proc_net_line, parse_socket_link,
+        ProcConnection, ProcSocket,
+    };
+    use anyhow::{Context, Result};
+    use std::fs;
+
+    pub fn pids() -> Result<Vec<u32>> {
+        Ok(fs::read_dir("/proc")
+            .context("Failed to read /proc")?
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+            .collect())
+    }
+
+    pub fn namespace_of(pid: u32) -> Option<u64> {
+        let link = fs::read_link(format!("/proc/{pid}/ns/net")).ok()?;
+        parse_namespace_link(&link.to_string_lossy())
+    }
+
+    /// The inode of an nsfs file is the namespace id
+    pub fn namespace_file_id(path: &str) -> Result<u64> {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata =
+            fs::metadata(path).with_context(|| format!("Cannot open network namespace {path}"))?;
+        Ok(metadata.ino())
+    }
+
+    pub fn process_name(pid: u32) -> String {
+        fs::read_to_string(format!("/proc/{pid}/comm"))
+            .map(|name| name.trim().to_string())
+            .unwrap_or_else(|_| "?".to_string())
+    }
+
+    pub fn read_cgroup(pid: u32) -> Option<String> {
+        fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()
+    }
+
+    pub fn read_tables(pid: u32) -> Result<Vec<ProcSocket>> {
+        let mut sockets = Vec::new();
+        for (table, udp) in [
+            ("tcp", false),
+            ("tcp6", false),
+            ("udp", true),
+            ("udp6", true),
+        ] {
+            let path = format!("/proc/{pid}/net/{table}");
+            // IPv6 tables are missing when IPv6 is disabled
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            sockets.extend(
+                content
+                    .lines()
+                    .skip(1)
+                    .filter_map(|line| parse_proc_net_line(line, udp)),
+            );
+        }
+        Ok(sockets)
+    }
+
+    pub fn read_connections(pid: u32) -> Result<Vec<ProcConnection>> {
+        let mut connections = Vec::new();
+        for table in ["tcp", "tcp6"] {
+            // The IPv6 table is missing when IPv6 is disabled
+            let Ok(content) = fs::read_to_string(format!("/proc/{pid}/net/{table}")) else {
+                continue;
+            };
+            connections.extend(
+                content
+                    .lines()
+                    .skip(1)
+                    .filter_map(parse_proc_net_connection),
+            );
+        }
+        Ok(connections)
+    }
+
+    pub fn socket_inodes(pid: u32) -> Vec<u64> {
+        let Ok(entries) = fs::read_dir(format!("/proc/{pid}/fd")) else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|entry| fs::read_link(entry.path()).ok())
+            .filter_map(|link| parse_socket_link(&link.to_string_lossy()))
+            .collect()
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use super::{ProcConnection, ProcSocket};
+    use anyhow::Result;
+
+    fn unsupported<T>() -> Result<T> {
+        Err(anyhow::anyhow!(
+            "Network namespaces are only supported on Linux"
+        ))
+    }
+
+    pub fn pids() -> Result<Vec<u32>> {
+        unsupported()
+    }
+
+    pub fn namespace_of(_pid: u32) -> Option<u64> {
+        None
+    }
+
+    pub fn namespace_file_id(_path: &str) -> Result<u64> {
+        unsupported()
+    }
+
+    pub fn process_name(_pid: u32) -> String {
+        "?".to_string()
+    }
+
+    pub fn read_cgroup(_pid: u32) -> Option<String> {
+        None
+    }
+
+    pub fn read_tables(_pid: u32) -> Result<Vec<ProcSocket>> {
+        unsupported()
+    }
+
+    pub fn read_connections(_pid: u32) -> Result<Vec<ProcConnection>> {
+        unsupported()
+    }
+
+    pub fn socket_inodes(_pid: u32) -> Vec<u64> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_net_line() {
+        // 127.0.0.1:8080 listening with 2 connections waiting, inode 41234
+        let listen = "   0: 0100007F:1F90 00000000:0000 0A 00000000:00000002 00:00000000 00000000  1000        0 41234 1 0000000000000000 100 0 0 10 0";
+        assert_eq!(
+            parse_proc_net_line(listen, false),
+            Some(ProcSocket {
+                address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                port: 8080,
+                udp: false,
+                inode: 41234,
+                recv_q: 2,
+                send_q: 0,
+                uid: 1000
+            })
+        );
+
+        let established = "   1: 0100007F:1F90 0100007F:A2C4 01 00000000:00000000 00:00000000 00000000  1000        0 41299 1 0000000000000000 20 4 30 10 -1";
+        assert_eq!(parse_proc_net_line(established, false), None);
+
+        let udp = "  512: 00000000:14E9 00000000:0000 07 00000000:00000000 00:00000000 00000000   107        0 16051 2 0000000000000000 0";
+        assert_eq!(parse_proc_net_line(udp, true).unwrap().port, 5353);
+
+        // [::1]:5432 in tcp6
+        let tcp6 = "   0: 00000000000000000000000001000000:1538 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000   114        0 20734 1 0000000000000000 100 0 0 10 0";
+        let socket = parse_proc_net_line(tcp6, false).unwrap();
+        assert_eq!(socket.address, IpAddr::V6(Ipv6Addr::LOCALHOST));
+        assert_eq!(socket.port, 5432);
+
+        assert_eq!(parse_socket_link("socket:[41234]"), Some(41234));
+        assert_eq!(parse_socket_link("/dev/null"), None);
+        assert_eq!(parse_namespace_link("net:[4026531840]"), Some(4026531840));
+    }
+
+    #[test]
+    fn test_parse_proc_net_connection() {
+        // 127.0.0.1:41668 -> 127.0.0.1:8080 with 12 bytes unacknowledged
+        let established = "   1: 0100007F:A2C4 0100007F:1F90 01 0000000C:00000000 00:00000000 00000000  1000        0 41299 1 0000000000000000 20 4 30 10 -1";
+        assert_eq!(
+            parse_proc_net_connection(established),
+            Some(ProcConnection {
+                local: "127.0.0.1:41668".parse().unwrap(),
+                remote: "127.0.0.1:8080".parse().unwrap(),
+                inode: 41299,
+                recv_q: 0,
+                send_q: 12,
+            })
+        );
+
+        let listen = "   0: 0100007F:1F90 00000000:0000 0A 00000000:00000002 00:00000000 00000000  1000        0 41234 1 0000000000000000 100 0 0 10 0";
+        assert_eq!(parse_proc_net_connection(listen), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_own_namespace_sees_own_listener() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let namespaces = enumerate(&[]).unwrap();
+        assert_eq!(namespaces[0].owner, "host");
+
+        let own = resolve(&std::process::id().to_string(), &[]).unwrap();
+        let (sockets, owners) = listening_sockets(&own).unwrap();
+        let socket = sockets.iter().find(|socket| socket.port == port).unwrap();
+        assert_eq!(owners.get(&socket.inode), Some(&std::process::id()));
+    }
+}
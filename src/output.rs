@@ -1,26 +1,59 @@
 use crate::cli::OutputFormat;
-use crate::scanner::PortInfo;
+use crate::config::{ConfigSource, ResolvedConfig};
+use crate::containers;
+use crate::graph::{Client, Edge};
+use crate::inspect::Inspection;
+use crate::leases::{format_remaining, Lease};
+use crate::plan::KillPlan;
+use crate::projects::{PortConflict, Project};
+use crate::scanner::{PortInfo, UnixSocketInfo, ELEVATED_HINT};
+use crate::systemd::Unit;
 use colored::*;
 use serde_json::json;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tabled::{
     settings::{object::Columns, Alignment, Modify, Style},
     Table, Tabled,
 };
 
+/// Set when stdout is reserved for machine-readable output
+static MESSAGES_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Print success, warning and info messages on stderr from now on, so stdout
+/// carries nothing but the command's result
+pub fn messages_to_stderr() {
+    MESSAGES_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+fn print_message(message: String) {
+    if MESSAGES_TO_STDERR.load(Ordering::Relaxed) {
+        eprintln!("{message}");
+    } else {
+        println!("{message}");
+    }
+}
+
 #[derive(Tabled)]
 struct PortTableRow {
     #[tabled(rename = "Port")]
     port: String,
+    #[tabled(rename = "Service")]
+    service: String,
     #[tabled(rename = "PID")]
     pid: String,
     #[tabled(rename = "Process")]
     process: String,
+    #[tabled(rename = "Project")]
+    project: String,
     #[tabled(rename = "Command")]
     command: String,
     #[tabled(rename = "Duration")]
     duration: String,
     #[tabled(rename = "Memory")]
     memory: String,
+    #[tabled(rename = "Clients")]
+    clients: String,
 }
 
 pub fn display_ports(ports: &[PortInfo], format: &OutputFormat) {
@@ -29,6 +62,27 @@ pub fn display_ports(ports: &[PortInfo], format: &OutputFormat) {
         OutputFormat::Json => display_json(ports),
         OutputFormat::Csv => display_csv(ports),
     }
+
+    let unowned = ports
+        .iter()
+        .filter(|port_info| port_info.pid.is_none())
+        .count();
+    display_partial_results(unowned, "port");
+}
+
+/// Warn on stderr, so JSON and CSV on stdout stay parseable, that some
+/// listeners were found without their owner
+fn display_partial_results(unowned: usize, what: &str) {
+    if unowned > 0 {
+        eprintln!(
+            "{} {}",
+            "⚠".yellow().bold(),
+            format!(
+                "Results are partial: the owner of {unowned} {what}(s) cannot be read; {ELEVATED_HINT}"
+            )
+            .yellow()
+        );
+    }
 }
 
 fn display_table(ports: &[PortInfo]) {
@@ -40,7 +94,7 @@ fn display_table(ports: &[PortInfo]) {
     // Calculate dynamic column widths based on content
     let process_width = ports
         .iter()
-        .map(|p| p.process_name.len())
+        .map(|p| p.owner_name().len())
         .max()
         .unwrap_or(7)
         .clamp(7, 20);
@@ -50,11 +104,33 @@ fn display_table(ports: &[PortInfo]) {
         .iter()
         .map(|port_info| PortTableRow {
             port: port_info.port.to_string(),
-            pid: port_info.pid.to_string(),
-            process: truncate_string(&port_info.process_name, process_width),
-            command: truncate_command(&port_info.command, command_width),
-            duration: format_duration(port_info.start_time),
-            memory: format_memory(port_info.memory_usage),
+            service: port_info
+                .service
+                .as_deref()
+                .map(|service| truncate_string(service, 16))
+                .unwrap_or_else(|| "-".to_string()),
+            pid: port_info
+                .pid
+                .map_or_else(|| "-".to_string(), |pid| pid.to_string()),
+            process: truncate_string(port_info.owner_name(), process_width),
+            project: port_info
+                .project
+                .as_deref()
+                .map(|project| truncate_string(project, 20))
+                .unwrap_or_else(|| "-".to_string()),
+            command: match port_info.pid {
+                Some(_) => truncate_command(&describe_command(port_info), command_width),
+                None => "-".to_string(),
+            },
+            duration: match port_info.pid {
+                Some(_) => format_duration(port_info.start_time),
+                None => "-".to_string(),
+            },
+            memory: match port_info.pid {
+                Some(_) => format_memory(port_info.memory_usage),
+                None => "-".to_string(),
+            },
+            clients: format_clients(port_info),
         })
         .collect();
 
@@ -62,11 +138,14 @@ fn display_table(ports: &[PortInfo]) {
     table
         .with(Style::modern())
         .with(Modify::new(Columns::single(0)).with(Alignment::right()))
-        .with(Modify::new(Columns::single(1)).with(Alignment::right()))
-        .with(Modify::new(Columns::single(2)).with(Alignment::left()))
+        .with(Modify::new(Columns::single(1)).with(Alignment::left()))
+        .with(Modify::new(Columns::single(2)).with(Alignment::right()))
         .with(Modify::new(Columns::single(3)).with(Alignment::left()))
-        .with(Modify::new(Columns::single(4)).with(Alignment::center()))
-        .with(Modify::new(Columns::single(5)).with(Alignment::right()));
+        .with(Modify::new(Columns::single(4)).with(Alignment::left()))
+        .with(Modify::new(Columns::single(5)).with(Alignment::left()))
+        .with(Modify::new(Columns::single(6)).with(Alignment::center()))
+        .with(Modify::new(Columns::single(7)).with(Alignment::right()))
+        .with(Modify::new(Columns::single(8)).with(Alignment::right()));
 
     println!("{table}");
 
@@ -78,17 +157,75 @@ fn display_table(ports: &[PortInfo]) {
     );
 }
 
+/// The command, or what the port really belongs to when a container or
+/// systemd manages it, prefixed by the namespace owner outside the host namespace
+fn describe_command(port_info: &PortInfo) -> String {
+    let command = match (&port_info.container, &port_info.systemd_unit) {
+        (Some(container), _) => format!("container {}", container.describe()),
+        // Socket activation: systemd itself holds the port
+        (None, Some(unit)) if unit.activates.is_some() => format!("socket {unit}"),
+        (None, Some(unit)) => format!(
+            "[{name}] {command}",
+            name = unit.name,
+            command = port_info.command
+        ),
+        // The runtime did not say which container this proxy serves
+        (None, None) if containers::is_proxy(&port_info.process_name) => {
+            format!("[container] {command}", command = port_info.command)
+        }
+        (None, None) => port_info.command.clone(),
+    };
+
+    match &port_info.netns {
+        Some(namespace) if namespace.owner != "host" => {
+            format!("[{owner}] {command}", owner = namespace.owner)
+        }
+        _ => command,
+    }
+}
+
+/// Established connections, plus the accept backlog when clients are waiting
+fn format_clients(port_info: &PortInfo) -> String {
+    if port_info.recv_q > 0 {
+        format!(
+            "{connections} (+{queued} queued)",
+            connections = port_info.connections,
+            queued = port_info.recv_q
+        )
+    } else {
+        port_info.connections.to_string()
+    }
+}
+
 fn display_json(ports: &[PortInfo]) {
     let json_data = ports
         .iter()
         .map(|port_info| {
             json!({
                 "port": port_info.port,
+                "service": port_info.service,
                 "pid": port_info.pid,
                 "process_name": port_info.process_name,
                 "command": port_info.command,
                 "start_time": port_info.start_time,
-                "memory_usage": port_info.memory_usage
+                "memory_usage": port_info.memory_usage,
+                "connections": port_info.connections,
+                "peers": port_info.peers,
+                "recv_q": port_info.recv_q,
+                "send_q": port_info.send_q,
+                "project": port_info.project,
+                "project_root": port_info.project_root,
+                "container": port_info.container.as_ref().map(|container| json!({
+                    "id": container.id,
+                    "name": container.name,
+                    "image": container.image,
+                    "compose_service": container.compose_service
+                })),
+                "systemd_unit": port_info.systemd_unit.as_ref().map(unit_json),
+                "netns": port_info.netns.as_ref().map(|namespace| json!({
+                    "id": namespace.id,
+                    "owner": namespace.owner
+                }))
             })
         })
         .collect::<Vec<_>>();
@@ -96,19 +233,877 @@ fn display_json(ports: &[PortInfo]) {
     println!("{}", serde_json::to_string_pretty(&json_data).unwrap());
 }
 
+fn unit_json(unit: &Unit) -> serde_json::Value {
+    json!({
+        "name": unit.name,
+        "scope": unit.scope.to_string(),
+        "activates": unit.activates
+    })
+}
+
 fn display_csv(ports: &[PortInfo]) {
-    println!("Port,PID,Process,Command,StartTime,MemoryUsage");
+    println!(
+        "Port,PID,Process,Command,StartTime,MemoryUsage,Service,Connections,RecvQ,SendQ,Project,Container,Unit,Netns"
+    );
     for port_info in ports {
         println!(
-            "{},{},{},\"{}\",{},{}",
+            "{},{},{},\"{}\",{},{},{},{},{},{},\"{}\",{},{},{}",
             port_info.port,
-            port_info.pid,
+            port_info.pid.map(|pid| pid.to_string()).unwrap_or_default(),
             port_info.process_name,
             port_info.command.replace('"', "\"\""), // Escape quotes in CSV
             port_info.start_time,
-            port_info.memory_usage
+            port_info.memory_usage,
+            port_info.service.as_deref().unwrap_or(""),
+            port_info.connections,
+            port_info.recv_q,
+            port_info.send_q,
+            port_info
+                .project
+                .as_deref()
+                .unwrap_or("")
+                .replace('"', "\"\""),
+            port_info
+                .container
+                .as_ref()
+                .map_or("", |container| container.name.as_str()),
+            port_info
+                .systemd_unit
+                .as_ref()
+                .map_or("", |unit| unit.name.as_str()),
+            port_info
+                .netns
+                .as_ref()
+                .map_or_else(String::new, |namespace| namespace.id.to_string())
+        );
+    }
+}
+
+pub fn display_kill_plan(plan: &KillPlan, format: &OutputFormat) {
+    match format {
+        OutputFormat::Table => display_kill_plan_table(plan),
+        OutputFormat::Json => display_kill_plan_json(plan),
+        OutputFormat::Csv => display_kill_plan_csv(plan),
+    }
+}
+
+fn display_kill_plan_table(plan: &KillPlan) {
+    display_warning("Dry run: no signals will be sent");
+
+    if plan.targets.is_empty() {
+        println!("{}", "Nothing to kill.".yellow());
+    }
+
+    for target in &plan.targets {
+        let origin = match target.tree_root {
+            Some(root) => format!("child of PID {root}"),
+            None if target.ports.is_empty() => "socket owner".to_string(),
+            None => format!("ports {}", join_ports(&target.ports)),
+        };
+        println!(
+            "\n{} {} {} ({origin})",
+            "PID".bold(),
+            target.pid.to_string().cyan().bold(),
+            target.process_name
+        );
+        println!("  {}", truncate_command(&target.command, 70).dimmed());
+        for (index, step) in target.steps.iter().enumerate() {
+            println!("  {}. {step}", index + 1);
+        }
+    }
+
+    if !plan.skipped.is_empty() {
+        println!();
+        for skipped in &plan.skipped {
+            let subject = match (skipped.pid, &skipped.process_name) {
+                (Some(pid), Some(name)) => format!("PID {pid} {name}"),
+                _ if skipped.ports.is_empty() => "socket".to_string(),
+                _ => format!("Port {}", join_ports(&skipped.ports)),
+            };
+            display_warning(&format!(
+                "Skip {subject}: {reason}",
+                reason = skipped.reason
+            ));
+        }
+    }
+}
+
+fn display_kill_plan_json(plan: &KillPlan) {
+    let json_data = json!({
+        "dry_run": true,
+        "targets": plan
+            .targets
+            .iter()
+            .map(|target| {
+                json!({
+                    "pid": target.pid,
+                    "process_name": target.process_name,
+                    "command": target.command,
+                    "ports": target.ports,
+                    "tree_root": target.tree_root,
+                    "signals": target.steps.iter().map(|step| step.to_string()).collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>(),
+        "skipped": plan
+            .skipped
+            .iter()
+            .map(|skipped| {
+                json!({
+                    "ports": skipped.ports,
+                    "pid": skipped.pid,
+                    "process_name": skipped.process_name,
+                    "reason": skipped.reason
+                })
+            })
+            .collect::<Vec<_>>()
+    });
+
+    println!("{}", serde_json::to_string_pretty(&json_data).unwrap());
+}
+
+fn display_kill_plan_csv(plan: &KillPlan) {
+    println!("PID,Process,Ports,TreeRoot,Signals,Skipped");
+    for target in &plan.targets {
+        println!(
+            "{},\"{}\",\"{}\",{},\"{}\",",
+            target.pid,
+            target.process_name.replace('"', "\"\""),
+            join_ports(&target.ports),
+            target
+                .tree_root
+                .map(|pid| pid.to_string())
+                .unwrap_or_default(),
+            target
+                .steps
+                .iter()
+                .map(|step| step.to_string())
+                .collect::<Vec<_>>()
+                .join("; ")
         );
     }
+    for skipped in &plan.skipped {
+        println!(
+            "{},\"{}\",\"{}\",,,\"{}\"",
+            skipped.pid.map(|pid| pid.to_string()).unwrap_or_default(),
+            skipped
+                .process_name
+                .as_deref()
+                .unwrap_or("")
+                .replace('"', "\"\""),
+            join_ports(&skipped.ports),
+            skipped.reason.replace('"', "\"\"")
+        );
+    }
+}
+
+fn join_ports(ports: &[u16]) -> String {
+    ports
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A quoted CSV field with its quotes doubled, safe for commas and newlines
+fn csv_field(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+#[derive(Tabled)]
+struct SettingTableRow {
+    #[tabled(rename = "Setting")]
+    setting: String,
+    #[tabled(rename = "Value")]
+    value: String,
+    #[tabled(rename = "Source")]
+    source: String,
+}
+
+pub fn display_resolved_config(config: &ResolvedConfig, format: &OutputFormat) {
+    let mut rows: Vec<SettingTableRow> = config
+        .port_sets
+        .iter()
+        .map(|(name, set)| {
+            setting_row(
+                &format!("port_sets.{name}"),
+                join_ports(&set.value),
+                &set.source,
+            )
+        })
+        .collect();
+
+    rows.extend([
+        setting_row(
+            "output_format",
+            format_name(&config.output_format.value),
+            &config.output_format.source,
+        ),
+        setting_row(
+            "color",
+            config.color.value.to_string(),
+            &config.color.source,
+        ),
+        setting_row(
+            "kill_timeout_ms",
+            config.kill_timeout.value.as_millis().to_string(),
+            &config.kill_timeout.source,
+        ),
+    ]);
+
+    for (port, name) in &config.services {
+        rows.push(setting_row(
+            &format!("services.{port}"),
+            name.value.clone(),
+            &name.source,
+        ));
+    }
+
+    for layer in &config.protected {
+        let rules = &layer.value;
+        let fields = [
+            ("protected.names", rules.names.join(", ")),
+            (
+                "protected.paths",
+                rules
+                    .paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            (
+                "protected.uids",
+                rules
+                    .uids
+                    .iter()
+                    .map(|uid| uid.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            ("protected.ports", join_ports(&rules.ports)),
+        ];
+        for (name, value) in fields {
+            if !value.is_empty() {
+                rows.push(setting_row(name, value, &layer.source));
+            }
+        }
+    }
+
+    match format {
+        OutputFormat::Json => {
+            let json_data = rows
+                .iter()
+                .map(|row| {
+                    json!({
+                        "setting": row.setting,
+                        "value": row.value,
+                        "source": row.source
+                    })
+                })
+                .collect::<Vec<_>>();
+            println!("{}", serde_json::to_string_pretty(&json_data).unwrap());
+        }
+        OutputFormat::Csv => {
+            println!("Setting,Value,Source");
+            for row in &rows {
+                println!(
+                    "{},\"{}\",\"{}\"",
+                    row.setting,
+                    row.value.replace('"', "\"\""),
+                    row.source.replace('"', "\"\"")
+                );
+            }
+        }
+        OutputFormat::Table => {
+            let mut table = Table::new(rows);
+            table.with(Style::modern());
+            println!("{table}");
+        }
+    }
+}
+
+fn setting_row(setting: &str, value: String, source: &ConfigSource) -> SettingTableRow {
+    SettingTableRow {
+        setting: setting.to_string(),
+        value,
+        source: source.to_string(),
+    }
+}
+
+fn format_name(format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Table => "table",
+        OutputFormat::Json => "json",
+        OutputFormat::Csv => "csv",
+    }
+    .to_string()
+}
+
+#[derive(Tabled)]
+struct ConflictTableRow {
+    #[tabled(rename = "Port")]
+    port: String,
+    #[tabled(rename = "Project")]
+    project: String,
+    #[tabled(rename = "Service")]
+    service: String,
+    #[tabled(rename = "Config")]
+    config: String,
+    #[tabled(rename = "Suggested")]
+    suggested: String,
+}
+
+pub fn display_conflicts(projects: &[Project], conflicts: &[PortConflict], format: &OutputFormat) {
+    // Every claimant after the first gets one suggestion, when one was found
+    let claims = |conflict: &PortConflict| {
+        conflict
+            .claimants
+            .iter()
+            .enumerate()
+            .map(|(position, &index)| {
+                let suggestion = position
+                    .checked_sub(1)
+                    .and_then(|i| conflict.suggestions.get(i).copied());
+                (&projects[index], suggestion)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    match format {
+        OutputFormat::Json => {
+            let json_data = json!({
+                "projects": projects.len(),
+                "conflicts": conflicts
+                    .iter()
+                    .map(|conflict| {
+                        json!({
+                            "port": conflict.port,
+                            "claims": claims(conflict)
+                                .into_iter()
+                                .map(|(project, suggestion)| {
+                                    json!({
+                                        "project": project.name,
+                                        "service": project.services.get(&conflict.port),
+                                        "config": project.config_path.display().to_string(),
+                                        "suggested_port": suggestion
+                                    })
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            });
+            println!("{}", serde_json::to_string_pretty(&json_data).unwrap());
+        }
+        OutputFormat::Csv => {
+            println!("Port,Project,Service,Config,SuggestedPort");
+            for conflict in conflicts {
+                for (project, suggestion) in claims(conflict) {
+                    println!(
+                        "{},{},{},{},{}",
+                        conflict.port,
+                        csv_field(&project.name),
+                        csv_field(
+                            project
+                                .services
+                                .get(&conflict.port)
+                                .map(String::as_str)
+                                .unwrap_or("")
+                        ),
+                        csv_field(&project.config_path.display().to_string()),
+                        suggestion.map(|port| port.to_string()).unwrap_or_default()
+                    );
+                }
+            }
+        }
+        OutputFormat::Table => {
+            display_info(&format!(
+                "Checked {count} project configs",
+                count = projects.len()
+            ));
+
+            if conflicts.is_empty() {
+                display_success("No port conflicts found");
+                return;
+            }
+
+            let rows: Vec<ConflictTableRow> = conflicts
+                .iter()
+                .flat_map(|conflict| {
+                    claims(conflict).into_iter().enumerate().map(
+                        move |(position, (project, suggestion))| ConflictTableRow {
+                            port: conflict.port.to_string(),
+                            project: project.name.clone(),
+                            service: project
+                                .services
+                                .get(&conflict.port)
+                                .cloned()
+                                .unwrap_or_else(|| "-".to_string()),
+                            config: project.config_path.display().to_string(),
+                            suggested: match (position, suggestion) {
+                                (0, _) => "keep".to_string(),
+                                (_, Some(port)) => port.to_string(),
+                                (_, None) => "-".to_string(),
+                            },
+                        },
+                    )
+                })
+                .collect();
+
+            let mut table = Table::new(rows);
+            table
+                .with(Style::modern())
+                .with(Modify::new(Columns::single(0)).with(Alignment::right()))
+                .with(Modify::new(Columns::single(4)).with(Alignment::right()));
+            println!("{table}");
+
+            display_warning(&format!(
+                "{count} port(s) claimed by more than one project",
+                count = conflicts.len()
+            ));
+        }
+    }
+}
+
+#[derive(Tabled)]
+struct UnixSocketTableRow {
+    #[tabled(rename = "Path")]
+    path: String,
+    #[tabled(rename = "Type")]
+    kind: String,
+    #[tabled(rename = "PID")]
+    pid: String,
+    #[tabled(rename = "Process")]
+    process: String,
+    #[tabled(rename = "Command")]
+    command: String,
+}
+
+pub fn display_unix_sockets(sockets: &[UnixSocketInfo], format: &OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let json_data: Vec<_> = sockets
+                .iter()
+                .map(|socket| {
+                    json!({
+                        "path": socket.path,
+                        "type": socket.kind,
+                        "pid": socket.pid,
+                        "process_name": socket.process_name,
+                        "command": socket.command
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&json_data).unwrap());
+        }
+        OutputFormat::Csv => {
+            println!("Path,Type,PID,Process,Command");
+            for socket in sockets {
+                println!(
+                    "\"{}\",{},{},{},\"{}\"",
+                    socket.path.replace('"', "\"\""),
+                    socket.kind,
+                    socket.pid.map(|pid| pid.to_string()).unwrap_or_default(),
+                    socket.process_name,
+                    socket.command.replace('"', "\"\"")
+                );
+            }
+        }
+        OutputFormat::Table => {
+            if sockets.is_empty() {
+                println!("{}", "No listening Unix sockets found.".yellow());
+                return;
+            }
+
+            let rows: Vec<UnixSocketTableRow> = sockets
+                .iter()
+                .map(|socket| UnixSocketTableRow {
+                    path: socket.path.clone(),
+                    kind: socket.kind.to_string(),
+                    pid: socket
+                        .pid
+                        .map_or_else(|| "-".to_string(), |pid| pid.to_string()),
+                    process: truncate_string(socket.owner_name(), 20),
+                    command: truncate_command(&socket.command, 45),
+                })
+                .collect();
+
+            let mut table = Table::new(rows);
+            table
+                .with(Style::modern())
+                .with(Modify::new(Columns::single(2)).with(Alignment::right()));
+            println!("{table}");
+
+            println!(
+                "\n{} {} sockets found",
+                "📊".bold(),
+                sockets.len().to_string().cyan().bold()
+            );
+        }
+    }
+
+    let unowned = sockets.iter().filter(|socket| socket.pid.is_none()).count();
+    display_partial_results(unowned, "socket");
+}
+
+#[derive(Tabled)]
+struct LeaseTableRow {
+    #[tabled(rename = "Port")]
+    port: String,
+    #[tabled(rename = "Project")]
+    project: String,
+    #[tabled(rename = "Path")]
+    path: String,
+    #[tabled(rename = "Expires In")]
+    expires_in: String,
+}
+
+pub fn display_leases(leases: &[Lease], format: &OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let json_data: Vec<_> = leases
+                .iter()
+                .map(|lease| {
+                    json!({
+                        "port": lease.port,
+                        "project": lease.project,
+                        "path": lease.path.display().to_string(),
+                        "created_at": lease.created_at,
+                        "expires_at": lease.expires_at
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&json_data).unwrap());
+        }
+        OutputFormat::Csv => {
+            println!("Port,Project,Path,CreatedAt,ExpiresAt");
+            for lease in leases {
+                println!(
+                    "{},{},{},{},{}",
+                    lease.port,
+                    csv_field(&lease.project),
+                    csv_field(&lease.path.display().to_string()),
+                    lease.created_at,
+                    lease
+                        .expires_at
+                        .map(|expires_at| expires_at.to_string())
+                        .unwrap_or_default()
+                );
+            }
+        }
+        OutputFormat::Table => {
+            if leases.is_empty() {
+                display_info("No ports are reserved");
+                return;
+            }
+
+            let rows: Vec<LeaseTableRow> = leases
+                .iter()
+                .map(|lease| LeaseTableRow {
+                    port: lease.port.to_string(),
+                    project: lease.project.clone(),
+                    path: lease.path.display().to_string(),
+                    expires_in: lease
+                        .expires_at
+                        .map(format_remaining)
+                        .unwrap_or_else(|| "never".to_string()),
+                })
+                .collect();
+
+            let mut table = Table::new(rows);
+            table
+                .with(Style::modern())
+                .with(Modify::new(Columns::single(0)).with(Alignment::right()));
+            println!("{table}");
+        }
+    }
+}
+
+#[derive(Tabled)]
+struct EdgeTableRow {
+    #[tabled(rename = "Port")]
+    port: String,
+    #[tabled(rename = "Service")]
+    service: String,
+    #[tabled(rename = "Server")]
+    server: String,
+    #[tabled(rename = "Client")]
+    client: String,
+    #[tabled(rename = "Connections")]
+    connections: String,
+}
+
+pub fn display_edges(edges: &[Edge], format: &OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let json_data: Vec<_> = edges
+                .iter()
+                .map(|edge| {
+                    let client = match &edge.client {
+                        Client::Process { pid, name } => {
+                            json!({"kind": "process", "pid": pid, "name": name})
+                        }
+                        Client::Unknown => json!({"kind": "unknown"}),
+                        Client::Remote(address) => json!({"kind": "remote", "address": address}),
+                    };
+                    json!({
+                        "port": edge.port,
+                        "service": edge.service,
+                        "server_pid": edge.server_pid,
+                        "server_name": edge.server_name,
+                        "client": client,
+                        "connections": edge.connections
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&json_data).unwrap());
+        }
+        OutputFormat::Csv => {
+            println!("Port,Service,ServerPID,ServerProcess,Client,Connections");
+            for edge in edges {
+                println!(
+                    "{},{},{},{},{},{}",
+                    edge.port,
+                    csv_field(edge.service.as_deref().unwrap_or("")),
+                    edge.server_pid
+                        .map(|pid| pid.to_string())
+                        .unwrap_or_default(),
+                    csv_field(&edge.server_name),
+                    csv_field(&edge.client.to_string()),
+                    edge.connections
+                );
+            }
+        }
+        OutputFormat::Table => {
+            if edges.is_empty() {
+                display_info("No established connections to local listeners");
+                return;
+            }
+
+            let rows: Vec<EdgeTableRow> = edges
+                .iter()
+                .map(|edge| EdgeTableRow {
+                    port: edge.port.to_string(),
+                    service: edge.service.clone().unwrap_or_else(|| "-".to_string()),
+                    server: match edge.server_pid {
+                        Some(pid) => format!("{name} ({pid})", name = edge.server_name),
+                        None => edge.server_name.clone(),
+                    },
+                    client: edge.client.to_string(),
+                    connections: edge.connections.to_string(),
+                })
+                .collect();
+
+            let mut table = Table::new(rows);
+            table
+                .with(Style::modern())
+                .with(Modify::new(Columns::single(0)).with(Alignment::right()))
+                .with(Modify::new(Columns::single(4)).with(Alignment::right()));
+            println!("{table}");
+
+            let clients = edges
+                .iter()
+                .map(|edge| &edge.client)
+                .collect::<HashSet<_>>();
+            display_info(&format!(
+                "{count} client(s) connected to {ports} port(s)",
+                count = clients.len(),
+                ports = edges
+                    .iter()
+                    .map(|edge| edge.port)
+                    .collect::<HashSet<_>>()
+                    .len()
+            ));
+        }
+    }
+}
+
+#[derive(Tabled)]
+struct InspectTableRow {
+    #[tabled(rename = "Field")]
+    field: String,
+    #[tabled(rename = "Value")]
+    value: String,
+}
+
+pub fn display_inspection(inspection: &Inspection, show_env: bool, format: &OutputFormat) {
+    let process = &inspection.process;
+    let environment = inspection.environment(show_env);
+    let format_chain = |chain: &[(u32, String)], separator: &str| {
+        if chain.is_empty() {
+            return "-".to_string();
+        }
+        chain
+            .iter()
+            .map(|(pid, name)| format!("{name} ({pid})"))
+            .collect::<Vec<_>>()
+            .join(separator)
+    };
+    let format_path = |path: &Option<std::path::PathBuf>| {
+        path.as_ref()
+            .map_or_else(|| "-".to_string(), |path| path.display().to_string())
+    };
+
+    if let OutputFormat::Json = format {
+        let listening: Vec<_> = inspection
+            .listening
+            .iter()
+            .map(|port_info| {
+                json!({
+                    "port": port_info.port,
+                    "service": inspection.service_name(port_info),
+                    "container": port_info.container.as_ref().map(|container| &container.name),
+                    "systemd_unit": port_info.systemd_unit.as_ref().map(unit_json),
+                    "connections": port_info.connections,
+                    "peers": port_info.peers
+                })
+            })
+            .collect();
+        let connections: Vec<_> = inspection
+            .connections
+            .iter()
+            .map(|connection| {
+                json!({
+                    "local_addr": connection.local_addr,
+                    "local_port": connection.local_port,
+                    "remote_addr": connection.remote_addr,
+                    "remote_port": connection.remote_port
+                })
+            })
+            .collect();
+        let chain_json = |chain: &[(u32, String)]| {
+            chain
+                .iter()
+                .map(|(pid, name)| json!({"pid": pid, "name": name}))
+                .collect::<Vec<_>>()
+        };
+        let json_data = json!({
+            "pid": process.pid,
+            "name": process.name,
+            "argv": process.argv,
+            "exe": process.exe,
+            "cwd": process.cwd,
+            "uid": process.uid,
+            "user": process.user,
+            "parents": chain_json(&process.parent_chain),
+            "children": chain_json(&process.children),
+            "cpu_usage": process.cpu_usage,
+            "memory_usage": process.memory_usage,
+            "start_time": process.start_time,
+            "runtime": process.runtime_duration,
+            "listening": listening,
+            "connections": connections,
+            "systemd_unit": inspection.systemd_unit.as_ref().map(unit_json),
+            "project": inspection.project.as_ref().map(|project| json!({
+                "name": project.name,
+                "config_path": project.config_path
+            })),
+            "environment": environment
+                .iter()
+                .map(|(key, value)| (key.clone(), json!(value)))
+                .collect::<serde_json::Map<_, _>>()
+        });
+        println!("{}", serde_json::to_string_pretty(&json_data).unwrap());
+        return;
+    }
+
+    let row = |field: &str, value: String| InspectTableRow {
+        field: field.to_string(),
+        value,
+    };
+    let mut rows = vec![
+        row("PID", process.pid.to_string()),
+        row("Process", process.name.clone()),
+        row("Command", process.argv.join(" ")),
+        row("Executable", format_path(&process.exe)),
+        row("Working Dir", format_path(&process.cwd)),
+        row(
+            "User",
+            match (&process.user, process.uid) {
+                (Some(user), Some(uid)) => format!("{user} ({uid})"),
+                (None, Some(uid)) => uid.to_string(),
+                _ => "-".to_string(),
+            },
+        ),
+        row("Parents", format_chain(&process.parent_chain, " <- ")),
+        row("Children", format_chain(&process.children, ", ")),
+        row("CPU", format!("{:.1}%", process.cpu_usage)),
+        row("Memory", format_memory(process.memory_usage)),
+        row("Running For", process.runtime_duration.clone()),
+        row(
+            "Systemd Unit",
+            inspection
+                .systemd_unit
+                .as_ref()
+                .map_or_else(|| "-".to_string(), Unit::to_string),
+        ),
+        row(
+            "Project",
+            inspection.project.as_ref().map_or_else(
+                || "-".to_string(),
+                |project| format!("{} ({})", project.name, project.config_path.display()),
+            ),
+        ),
+    ];
+    for port_info in &inspection.listening {
+        let service = inspection
+            .service_name(port_info)
+            .map(|service| format!(" {service}"))
+            .unwrap_or_default();
+        let container = port_info
+            .container
+            .as_ref()
+            .map(|container| format!(", container {}", container.describe()))
+            .unwrap_or_default();
+        // Socket units only; other units are the process's own, shown above
+        let socket = port_info
+            .systemd_unit
+            .as_ref()
+            .filter(|unit| unit.activates.is_some())
+            .map(|unit| format!(", socket {unit}"))
+            .unwrap_or_default();
+        rows.push(row(
+            "Listening",
+            format!(
+                "{port}{service}, {clients} client(s){container}{socket}",
+                port = port_info.port,
+                clients = port_info.connections
+            ),
+        ));
+    }
+    for connection in &inspection.connections {
+        rows.push(row(
+            "Connection",
+            format!(
+                "{}:{} -> {}:{}",
+                connection.local_addr,
+                connection.local_port,
+                connection.remote_addr,
+                connection.remote_port
+            ),
+        ));
+    }
+    for (key, value) in environment {
+        rows.push(row(&format!("env.{key}"), value));
+    }
+
+    match format {
+        OutputFormat::Csv => {
+            println!("Field,Value");
+            for row in &rows {
+                println!("{},\"{}\"", row.field, row.value.replace('"', "\"\""));
+            }
+        }
+        _ => {
+            let mut table = Table::new(rows);
+            table.with(Style::modern());
+            println!("{table}");
+            if !show_env && !process.environment.is_empty() {
+                display_info("Environment values are redacted; use --show-env to reveal them");
+            }
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -196,7 +1191,7 @@ fn format_memory(bytes: u64) -> String {
 }
 
 pub fn display_success(message: &str) {
-    println!("{} {}", "✓".green().bold(), message);
+    print_message(format!("{} {}", "✓".green().bold(), message));
 }
 
 pub fn display_error(message: &str) {
@@ -204,11 +1199,25 @@ pub fn display_error(message: &str) {
 }
 
 pub fn display_warning(message: &str) {
-    println!("{} {}", "⚠".yellow().bold(), message.yellow());
+    print_message(format!("{} {}", "⚠".yellow().bold(), message.yellow()));
 }
 
 pub fn display_info(message: &str) {
-    println!("{} {}", "ℹ".blue().bold(), message);
+    print_message(format!("{} {}", "ℹ".blue().bold(), message));
+}
+
+/// Ask for one of `choices`, accepted by name or first letter; anything else declines
+pub fn choose_action<'a>(message: &str, choices: &[&'a str]) -> Option<&'a str> {
+    print!("{message} [{choices}/N]: ", choices = choices.join("/"));
+    std::io::Write::flush(&mut std::io::stdout()).unwrap();
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input).unwrap();
+    let input = input.trim().to_lowercase();
+
+    choices.iter().copied().find(|choice| {
+        !input.is_empty() && (input == *choice || choice.starts_with(input.as_str()))
+    })
 }
 
 pub fn confirm_action(message: &str) -> bool {
@@ -0,0 +1,640 @@
+use crate::containers::{self, Container};
+use crate::process::{termination_sequence, ProcessManager, SignalStep};
+use crate::protection::{ProcessFacts, ProtectionGuard};
+use crate::scanner::{PortInfo, ELEVATED_HINT};
+use crate::systemd::Unit;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// A process that will receive signals, together with the exact steps it gets
+#[derive(Debug, Clone)]
+pub struct KillTarget {
+    pub pid: u32,
+    pub process_name: String,
+    pub command: String,
+    /// Requested ports owned by this process (empty for tree descendants)
+    pub ports: Vec<u16>,
+    /// PID of the port owner this process was reached from via `--tree`
+    pub tree_root: Option<u32>,
+    pub steps: Vec<SignalStep>,
+}
+
+/// A container whose ports are freed by stopping it through its runtime;
+/// killing the proxy that publishes them would only unpublish the ports
+#[derive(Debug, Clone)]
+pub struct ContainerTarget {
+    pub container: Container,
+    /// The listener holding the ports on the host
+    pub proxy: PortInfo,
+    /// Requested ports published by the container
+    pub ports: Vec<u16>,
+}
+
+/// A systemd unit offered `systemctl stop/restart`, since killing its process
+/// only gets it restarted
+#[derive(Debug, Clone)]
+pub struct UnitTarget {
+    pub unit: Unit,
+    /// The listener serving the ports
+    pub owner: PortInfo,
+    /// Requested ports served by the unit
+    pub ports: Vec<u16>,
+    /// Signalled instead if `kill` is chosen; `None` when systemd itself holds
+    /// the port for a socket unit
+    pub kill: Option<KillTarget>,
+}
+
+/// A port or process that was resolved but will not be signalled
+#[derive(Debug, Clone)]
+pub struct SkippedTarget {
+    pub ports: Vec<u16>,
+    pub pid: Option<u32>,
+    pub process_name: Option<String>,
+    pub reason: String,
+}
+
+/// Everything `kill` would do for a set of ports, resolved before any signal is sent
+#[derive(Debug, Clone, Default)]
+pub struct KillPlan {
+    pub targets: Vec<KillTarget>,
+    pub containers: Vec<ContainerTarget>,
+    pub units: Vec<UnitTarget>,
+    pub skipped: Vec<SkippedTarget>,
+}
+
+impl KillPlan {
+    /// Resolve requested ports against a scan: group by PID (by container for
+    /// published ports, by systemd unit when `systemctl` is set), drop
+    /// protected processes, containers and units and optionally expand each
+    /// owner to its process tree
+    pub fn build(
+        ports: &[u16],
+        scanned: &[PortInfo],
+        process_manager: &ProcessManager,
+        guard: &ProtectionGuard,
+        tree: bool,
+        grace_period: Duration,
+        systemctl: bool,
+    ) -> Self {
+        let mut plan = KillPlan::default();
+        let mut owners: BTreeMap<u32, (&PortInfo, Vec<u16>)> = BTreeMap::new();
+        let mut published: BTreeMap<&str, (&Container, &PortInfo, Vec<u16>)> = BTreeMap::new();
+        let mut managed: BTreeMap<&Unit, (&PortInfo, Vec<u16>)> = BTreeMap::new();
+
+        for &port in ports {
+            let mut found = false;
+            for port_info in scanned.iter().filter(|info| info.port == port) {
+                found = true;
+                if let Some(container) = &port_info.container {
+                    let entry = published
+                        .entry(&container.id)
+                        .or_insert_with(|| (container, port_info, Vec::new()));
+                    if !entry.2.contains(&port) {
+                        entry.2.push(port);
+                    }
+                    continue;
+                }
+                let Some(pid) = port_info.pid else {
+                    plan.skipped.push(SkippedTarget {
+                        ports: vec![port],
+                        pid: None,
+                        process_name: None,
+                        reason: format!("Owner cannot be read; {ELEVATED_HINT}"),
+                    });
+                    continue;
+                };
+                if containers::is_proxy(&port_info.process_name) {
+                    plan.skipped.push(SkippedTarget {
+                        ports: vec![port],
+                        pid: Some(pid),
+                        process_name: Some(port_info.process_name.clone()),
+                        reason: "Container proxy for an unidentified container; killing it \
+                                 only unpublishes the port, stop the container with docker or \
+                                 podman instead"
+                            .to_string(),
+                    });
+                    continue;
+                }
+                let entry = match &port_info.systemd_unit {
+                    Some(unit) if systemctl => managed
+                        .entry(unit)
+                        .or_insert_with(|| (port_info, Vec::new())),
+                    _ => owners.entry(pid).or_insert_with(|| (port_info, Vec::new())),
+                };
+                if !entry.1.contains(&port) {
+                    entry.1.push(port);
+                }
+            }
+
+            if !found {
+                plan.skipped.push(SkippedTarget {
+                    ports: vec![port],
+                    pid: None,
+                    process_name: None,
+                    reason: "Port not in use".to_string(),
+                });
+            }
+        }
+
+        let owners: Vec<(u32, String, String, Vec<u16>)> = owners
+            .into_iter()
+            .map(|(pid, (port_info, owned_ports))| {
+                (
+                    pid,
+                    port_info.process_name.clone(),
+                    port_info.command.clone(),
+                    owned_ports,
+                )
+            })
+            .collect();
+        plan.add_owners(owners, scanned, process_manager, guard, tree, grace_period);
+
+        for (unit, (owner, unit_ports)) in managed {
+            if let Some(reason) =
+                unit_refusal(unit, owner, &unit_ports, scanned, process_manager, guard)
+            {
+                plan.skipped.push(SkippedTarget {
+                    ports: unit_ports,
+                    pid: owner.pid,
+                    process_name: Some(owner.process_name.clone()),
+                    reason,
+                });
+                continue;
+            }
+            let kill = owner
+                .pid
+                .filter(|_| unit.activates.is_none())
+                .map(|pid| KillTarget {
+                    pid,
+                    process_name: owner.process_name.clone(),
+                    command: owner.command.clone(),
+                    ports: unit_ports.clone(),
+                    tree_root: None,
+                    steps: termination_sequence(grace_period),
+                });
+            plan.units.push(UnitTarget {
+                unit: unit.clone(),
+                owner: owner.clone(),
+                ports: unit_ports,
+                kill,
+            });
+        }
+
+        for (container, proxy, container_ports) in published.into_values() {
+            // Stopping the container frees every port it publishes
+            let mut all_ports = container.published.clone();
+            all_ports.extend(&container_ports);
+            match guard.managed_refusal("Container", &container.name, &all_ports) {
+                Some(reason) => plan.skipped.push(SkippedTarget {
+                    ports: container_ports,
+                    pid: proxy.pid,
+                    process_name: Some(proxy.process_name.clone()),
+                    reason,
+                }),
+                None => plan.containers.push(ContainerTarget {
+                    container: container.clone(),
+                    proxy: proxy.clone(),
+                    ports: container_ports,
+                }),
+            }
+        }
+        plan
+    }
+
+    /// Plan for processes found by something other than a port, such as the
+    /// owners of a Unix socket, given as (pid, name, command). `scanned`
+    /// supplies the ports they listen on for the protection check.
+    pub fn for_processes(
+        processes: &[(u32, String, String)],
+        scanned: &[PortInfo],
+        process_manager: &ProcessManager,
+        guard: &ProtectionGuard,
+        tree: bool,
+        grace_period: Duration,
+    ) -> Self {
+        let mut plan = KillPlan::default();
+        let owners = processes
+            .iter()
+            .map(|(pid, name, command)| (*pid, name.clone(), command.clone(), Vec::new()))
+            .collect();
+        plan.add_owners(owners, scanned, process_manager, guard, tree, grace_period);
+        plan
+    }
+
+    /// Add each owner (pid, name, command, ports) as a target unless it is
+    /// protected, optionally followed by its process tree
+    fn add_owners(
+        &mut self,
+        owners: Vec<(u32, String, String, Vec<u16>)>,
+        scanned: &[PortInfo],
+        process_manager: &ProcessManager,
+        guard: &ProtectionGuard,
+        tree: bool,
+        grace_period: Duration,
+    ) {
+        let steps = termination_sequence(grace_period);
+
+        for (pid, process_name, command, owned_ports) in owners {
+            if let Some(reason) = refusal(pid, &process_name, scanned, process_manager, guard) {
+                self.skipped.push(SkippedTarget {
+                    ports: owned_ports,
+                    pid: Some(pid),
+                    process_name: Some(process_name),
+                    reason,
+                });
+                continue;
+            }
+
+            self.targets.push(KillTarget {
+                pid,
+                process_name,
+                command,
+                ports: owned_ports,
+                tree_root: None,
+                steps: steps.clone(),
+            });
+        }
+
+        if tree {
+            let roots: Vec<u32> = self.targets.iter().map(|target| target.pid).collect();
+            for root in roots {
+                for child in process_manager.descendants(root) {
+                    if self.contains_pid(child) {
+                        continue;
+                    }
+
+                    let Some((name, command)) = process_manager.process_identity(child) else {
+                        continue;
+                    };
+
+                    if let Some(reason) = refusal(child, &name, scanned, process_manager, guard) {
+                        self.skipped.push(SkippedTarget {
+                            ports: Vec::new(),
+                            pid: Some(child),
+                            process_name: Some(name),
+                            reason,
+                        });
+                        continue;
+                    }
+
+                    self.targets.push(KillTarget {
+                        pid: child,
+                        process_name: name,
+                        command,
+                        ports: Vec::new(),
+                        tree_root: Some(root),
+                        steps: steps.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn contains_pid(&self, pid: u32) -> bool {
+        self.targets.iter().any(|target| target.pid == pid)
+            || self.skipped.iter().any(|skipped| skipped.pid == Some(pid))
+    }
+
+    /// Descendants that will be signalled together with `root`
+    pub fn tree_of(&self, root: u32) -> impl Iterator<Item = &KillTarget> {
+        self.targets
+            .iter()
+            .filter(move |target| target.tree_root == Some(root))
+    }
+}
+
+/// Why a process may not be signalled: built-in critical list first, then
+/// config rules. Protected ports are matched against every port the process
+/// listens on in `scanned`, not only the ones that were asked for.
+fn refusal(
+    pid: u32,
+    name: &str,
+    scanned: &[PortInfo],
+    process_manager: &ProcessManager,
+    guard: &ProtectionGuard,
+) -> Option<String> {
+    if process_manager.is_system_critical(name) {
+        return Some(format!("System-critical process: {name}"));
+    }
+
+    let ports: Vec<u16> = scanned
+        .iter()
+        .filter(|port_info| port_info.pid == Some(pid))
+        .map(|port_info| port_info.port)
+        .collect();
+    let exe = process_manager.process_exe(pid);
+    guard.refusal(&ProcessFacts {
+        name,
+        exe: exe.as_deref(),
+        uid: process_manager.process_owner(pid),
+        ports: &ports,
+    })
+}
+
+/// Why a unit may not be stopped: a critical service, a protected port or
+/// name, or a main process (or listener) that may not be signalled. For
+/// socket units systemd itself holds the port, so only the activated
+/// service's main process is checked.
+fn unit_refusal(
+    unit: &Unit,
+    owner: &PortInfo,
+    ports: &[u16],
+    scanned: &[PortInfo],
+    process_manager: &ProcessManager,
+    guard: &ProtectionGuard,
+) -> Option<String> {
+    let services = std::iter::once(unit.stem()).chain(
+        unit.activates
+            .as_deref()
+            .map(|service| service.trim_end_matches(".service")),
+    );
+    for service in services {
+        if process_manager.is_system_critical(service) {
+            return Some(format!("System-critical unit: {unit}"));
+        }
+    }
+    if let Some(reason) = guard.managed_refusal("Unit", &unit.name, ports) {
+        return Some(reason);
+    }
+
+    let listener = owner.pid.filter(|_| unit.activates.is_none());
+    let mut pids: Vec<u32> = listener.into_iter().chain(unit.main_pid).collect();
+    pids.dedup();
+    pids.into_iter().find_map(|pid| {
+        let name = match process_manager.process_identity(pid) {
+            Some((name, _)) => name,
+            None if Some(pid) == owner.pid => owner.process_name.clone(),
+            None => return None,
+        };
+        refusal(pid, &name, scanned, process_manager, guard)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::TERM_GRACE_PERIOD;
+    use crate::protection::ProtectionRules;
+
+    fn open_guard() -> ProtectionGuard {
+        ProtectionGuard::new(&ProtectionRules::default(), None, false).unwrap()
+    }
+
+    fn port_info(port: u16, pid: u32, name: &str) -> PortInfo {
+        PortInfo {
+            command: format!("{name} --serve"),
+            ..PortInfo::listener(port, pid, name)
+        }
+    }
+
+    #[test]
+    fn test_plan_groups_ports_by_pid() {
+        let manager = ProcessManager::new();
+        let scanned = vec![
+            port_info(3000, 4242, "node"),
+            port_info(3001, 4242, "node"),
+            port_info(8080, 5151, "java"),
+        ];
+
+        let plan = KillPlan::build(
+            &[3000, 3001, 8080],
+            &scanned,
+            &manager,
+            &open_guard(),
+            false,
+            TERM_GRACE_PERIOD,
+            false,
+        );
+
+        assert_eq!(plan.targets.len(), 2);
+        assert_eq!(plan.targets[0].pid, 4242);
+        assert_eq!(plan.targets[0].ports, vec![3000, 3001]);
+        assert_eq!(plan.targets[1].ports, vec![8080]);
+        assert!(plan.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_plan_skips_unused_and_critical() {
+        let manager = ProcessManager::new();
+        let scanned = vec![port_info(22, 1, "sshd")];
+
+        let plan = KillPlan::build(
+            &[22, 3000],
+            &scanned,
+            &manager,
+            &open_guard(),
+            false,
+            TERM_GRACE_PERIOD,
+            false,
+        );
+
+        assert!(plan.targets.is_empty());
+        assert_eq!(plan.skipped.len(), 2);
+        assert_eq!(plan.skipped[0].reason, "Port not in use");
+        assert_eq!(plan.skipped[1].pid, Some(1));
+    }
+
+    #[test]
+    fn test_plan_skips_unknown_owner() {
+        let manager = ProcessManager::new();
+        let scanned = vec![PortInfo {
+            pid: None,
+            ..port_info(5432, 0, "")
+        }];
+
+        let plan = KillPlan::build(
+            &[5432],
+            &scanned,
+            &manager,
+            &open_guard(),
+            false,
+            TERM_GRACE_PERIOD,
+            false,
+        );
+
+        assert!(plan.targets.is_empty());
+        assert_eq!(plan.skipped.len(), 1);
+        assert_eq!(plan.skipped[0].ports, vec![5432]);
+        assert!(plan.skipped[0].reason.starts_with("Owner cannot be read"));
+    }
+
+    #[test]
+    fn test_plan_protects_every_port_of_a_process() {
+        let manager = ProcessManager::new();
+        // Asking for 8080 must not bypass the rule on 5432 of the same PID
+        let scanned = vec![
+            port_info(5432, 4242, "postgres"),
+            port_info(8080, 4242, "postgres"),
+        ];
+        let rules = ProtectionRules {
+            ports: vec![5432],
+            ..Default::default()
+        };
+        let guard = ProtectionGuard::new(&rules, None, false).unwrap();
+
+        let plan = KillPlan::build(
+            &[8080],
+            &scanned,
+            &manager,
+            &guard,
+            false,
+            TERM_GRACE_PERIOD,
+            false,
+        );
+
+        assert!(plan.targets.is_empty());
+        assert_eq!(plan.skipped[0].ports, vec![8080]);
+        assert_eq!(plan.skipped[0].reason, "Port 5432 is protected by config");
+    }
+
+    #[test]
+    fn test_plan_stops_containers_unless_protected() {
+        let manager = ProcessManager::new();
+        let container = |name: &str, published: Vec<u16>| Container {
+            id: format!("{name}-id"),
+            name: name.to_string(),
+            image: format!("{name}:latest"),
+            compose_service: None,
+            published,
+        };
+        let scanned = vec![
+            PortInfo {
+                container: Some(container("web", vec![8080])),
+                ..port_info(8080, 812, "docker-proxy")
+            },
+            // The request names 5433, but the container also publishes 5432
+            PortInfo {
+                container: Some(container("pg", vec![5432, 5433])),
+                ..port_info(5433, 813, "docker-proxy")
+            },
+            // No runtime answered for this one
+            port_info(9000, 814, "rootlessport"),
+        ];
+        let rules = ProtectionRules {
+            ports: vec![5432],
+            ..Default::default()
+        };
+        let guard = ProtectionGuard::new(&rules, None, false).unwrap();
+
+        let plan = KillPlan::build(
+            &[8080, 5433, 9000],
+            &scanned,
+            &manager,
+            &guard,
+            false,
+            TERM_GRACE_PERIOD,
+            false,
+        );
+
+        assert!(plan.targets.is_empty());
+        assert_eq!(plan.containers.len(), 1);
+        assert_eq!(plan.containers[0].container.name, "web");
+        assert_eq!(plan.containers[0].ports, vec![8080]);
+        assert_eq!(plan.skipped.len(), 2);
+        assert!(plan.skipped[0].reason.starts_with("Container proxy"));
+        assert_eq!(plan.skipped[1].ports, vec![5433]);
+        assert_eq!(plan.skipped[1].reason, "Port 5432 is protected by config");
+    }
+
+    #[test]
+    fn test_plan_offers_units_unless_protected() {
+        use crate::systemd::UnitScope;
+
+        let manager = ProcessManager::new();
+        let unit = |name: &str| Unit {
+            name: name.to_string(),
+            scope: UnitScope::System,
+            activates: None,
+            main_pid: None,
+        };
+        let scanned = vec![
+            PortInfo {
+                systemd_unit: Some(unit("sshd.service")),
+                ..port_info(22, 4242, "sshd-session")
+            },
+            PortInfo {
+                systemd_unit: Some(unit("api.service")),
+                ..port_info(8080, 5151, "node")
+            },
+        ];
+
+        let plan = KillPlan::build(
+            &[22, 8080],
+            &scanned,
+            &manager,
+            &open_guard(),
+            false,
+            TERM_GRACE_PERIOD,
+            true,
+        );
+
+        assert!(plan.targets.is_empty());
+        assert_eq!(plan.units.len(), 1);
+        assert_eq!(plan.units[0].unit.name, "api.service");
+        assert_eq!(plan.units[0].kill.as_ref().unwrap().pid, 5151);
+        assert_eq!(plan.skipped[0].ports, vec![22]);
+        assert!(plan.skipped[0].reason.starts_with("System-critical unit"));
+
+        // With --force the owners are planned like any other process
+        let forced = KillPlan::build(
+            &[8080],
+            &scanned,
+            &manager,
+            &open_guard(),
+            false,
+            TERM_GRACE_PERIOD,
+            false,
+        );
+        assert!(forced.units.is_empty());
+        assert_eq!(forced.targets[0].pid, 5151);
+
+        // A protected name refuses the unit as a unit, not as a container
+        let rules = ProtectionRules {
+            names: vec!["^api\\.".to_string()],
+            ..Default::default()
+        };
+        let guard = ProtectionGuard::new(&rules, None, false).unwrap();
+        let protected = KillPlan::build(
+            &[8080],
+            &scanned,
+            &manager,
+            &guard,
+            false,
+            TERM_GRACE_PERIOD,
+            true,
+        );
+        assert!(protected.units.is_empty());
+        assert_eq!(
+            protected.skipped[0].reason,
+            "Unit name matches protected pattern '^api\\.'"
+        );
+    }
+
+    #[test]
+    fn test_plan_honors_config_rules() {
+        let manager = ProcessManager::new();
+        let scanned = vec![
+            port_info(5432, 4242, "postgres"),
+            port_info(3000, 5151, "node"),
+        ];
+        let rules = ProtectionRules {
+            names: vec!["^postgres$".to_string()],
+            ..Default::default()
+        };
+        let guard = ProtectionGuard::new(&rules, None, false).unwrap();
+
+        let plan = KillPlan::build(
+            &[5432, 3000],
+            &scanned,
+            &manager,
+            &guard,
+            false,
+            TERM_GRACE_PERIOD,
+            false,
+        );
+
+        assert_eq!(plan.targets.len(), 1);
+        assert_eq!(plan.targets[0].pid, 5151);
+        assert_eq!(plan.skipped[0].ports, vec![5432]);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::config::Resolved;
+use crate::scanner::COMMON_DEV_PORTS;
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Name of the set used by `--common`
+pub const COMMON_SET: &str = "common";
+
+/// Port sets that ship with portman; a config set with the same name replaces them
+pub const BUILTIN_PORT_SETS: &[(&str, &[u16])] = &[
+    (COMMON_SET, COMMON_DEV_PORTS),
+    (
+        "web",
+        &[
+            3000, 3001, 3002, 3003, // React, Next.js, Node.js
+            4000, 4200, 4321, // Angular, Astro
+            5000, 5173, 5174, // Flask, Vite
+            6006, // Storybook
+            8000, 8080, 8081, 8443, // Django, Java, HTTPS dev proxies
+        ],
+    ),
+    (
+        "db",
+        &[
+            1433,  // SQL Server
+            3306,  // MySQL / MariaDB
+            5432,  // PostgreSQL
+            5984,  // CouchDB
+            6379,  // Redis
+            7474,  // Neo4j
+            9042,  // Cassandra
+            9200,  // Elasticsearch
+            11211, // Memcached
+            27017, // MongoDB
+        ],
+    ),
+    (
+        "debuggers",
+        &[
+            2345, // Delve (Go)
+            5005, // Java JDWP
+            5678, // debugpy (Python)
+            9222, // Chrome DevTools
+            9229, // Node.js inspector
+        ],
+    ),
+];
+
+/// Resolve comma-separated or repeated set names into a sorted list of ports
+pub fn ports_in_sets(
+    names: &[String],
+    sets: &BTreeMap<String, Resolved<Vec<u16>>>,
+) -> Result<Vec<u16>> {
+    let mut ports = Vec::new();
+
+    for name in names.iter().map(|name| name.trim()) {
+        match sets.get(name) {
+            Some(set) => ports.extend(set.value.iter().copied()),
+            None => {
+                return Err(anyhow::anyhow!(
+                    "Unknown port set '{name}'. Available sets: {available}",
+                    available = sets.keys().cloned().collect::<Vec<_>>().join(", ")
+                ))
+            }
+        }
+    }
+
+    ports.sort_unstable();
+    ports.dedup();
+    Ok(ports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigSource;
+
+    fn sets(sets: &[(&str, &[u16])]) -> BTreeMap<String, Resolved<Vec<u16>>> {
+        sets.iter()
+            .map(|(name, ports)| {
+                (
+                    name.to_string(),
+                    Resolved {
+                        value: ports.to_vec(),
+                        source: ConfigSource::Default,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_set_ports_are_sorted_and_deduplicated() {
+        let sets = sets(&[("web", &[8080, 3000, 3000])]);
+
+        let ports = ports_in_sets(&["web".to_string()], &sets).unwrap();
+        assert_eq!(ports, vec![3000, 8080]);
+    }
+
+    #[test]
+    fn test_multiple_sets_are_merged() {
+        let sets = sets(&[
+            ("web", &[5173, 3000]),
+            ("debuggers", &[9229, 3000]),
+            ("db", &[5432]),
+        ]);
+        let names = vec!["web".to_string(), " debuggers".to_string()];
+
+        let ports = ports_in_sets(&names, &sets).unwrap();
+        assert_eq!(ports, vec![3000, 5173, 9229]);
+    }
+
+    #[test]
+    fn test_unknown_set() {
+        let sets = sets(&[("common", &[3000]), ("db", &[5432]), ("web", &[8080])]);
+
+        let err = ports_in_sets(&["nope".to_string()], &sets).unwrap_err();
+        assert!(err.to_string().contains("Available sets: common, db, web"));
+    }
+}
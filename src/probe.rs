@@ -0,0 +1,131 @@
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, UdpSocket};
+
+/// Addresses probed when none are given: loopback and wildcard, IPv4 and IPv6.
+/// A port can be free on one and taken on another, e.g. when a server binds
+/// `::` with dual-stack enabled.
+pub const DEFAULT_ADDRESSES: [IpAddr; 4] = [
+    IpAddr::V4(Ipv4Addr::LOCALHOST),
+    IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+    IpAddr::V6(Ipv6Addr::LOCALHOST),
+    IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transport::Tcp => write!(f, "TCP"),
+            Transport::Udp => write!(f, "UDP"),
+        }
+    }
+}
+
+/// Outcome of binding one address
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindOutcome {
+    Free,
+    InUse,
+    /// The address does not exist on this host (e.g. IPv6 is disabled), so
+    /// it says nothing about the port
+    Unavailable,
+    /// Any other refusal, such as a privileged port without root
+    Failed(String),
+}
+
+/// One bind attempt on one address and transport
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindProbe {
+    pub transport: Transport,
+    pub address: SocketAddr,
+    pub outcome: BindOutcome,
+}
+
+impl BindProbe {
+    /// Whether this probe shows the port cannot be used
+    pub fn blocks(&self) -> bool {
+        matches!(self.outcome, BindOutcome::InUse | BindOutcome::Failed(_))
+    }
+
+    pub fn describe(&self) -> String {
+        let target = format!("{} {}", self.transport, self.address);
+        match &self.outcome {
+            BindOutcome::Free => format!("{target} is free"),
+            BindOutcome::InUse => format!("{target} is in use"),
+            BindOutcome::Unavailable => format!("{target} skipped: address not available"),
+            BindOutcome::Failed(reason) => format!("{target} cannot be bound: {reason}"),
+        }
+    }
+}
+
+/// Try binding `port` on every address for each transport. Sockets are
+/// closed again right away.
+pub fn probe_port(port: u16, addresses: &[IpAddr], transports: &[Transport]) -> Vec<BindProbe> {
+    let mut probes = Vec::new();
+    for &transport in transports {
+        for &ip in addresses {
+            let address = SocketAddr::new(ip, port);
+            let bound = match transport {
+                Transport::Tcp => TcpListener::bind(address).map(drop),
+                Transport::Udp => UdpSocket::bind(address).map(drop),
+            };
+            let outcome = match bound {
+                Ok(()) => BindOutcome::Free,
+                Err(e) if e.kind() == ErrorKind::AddrInUse => BindOutcome::InUse,
+                Err(e) if e.kind() == ErrorKind::AddrNotAvailable => BindOutcome::Unavailable,
+                Err(e) => BindOutcome::Failed(e.to_string()),
+            };
+            probes.push(BindProbe {
+                transport,
+                address,
+                outcome,
+            });
+        }
+    }
+    probes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_reports_each_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let probes = probe_port(
+            port,
+            &[IpAddr::V4(Ipv4Addr::LOCALHOST)],
+            &[Transport::Tcp, Transport::Udp],
+        );
+        assert_eq!(probes.len(), 2);
+        assert_eq!(probes[0].outcome, BindOutcome::InUse);
+        assert!(probes[0].blocks());
+        // The TCP listener leaves the UDP port alone
+        assert_eq!(probes[1].outcome, BindOutcome::Free);
+        assert_eq!(
+            probes[1].describe(),
+            format!("UDP 127.0.0.1:{port} is free")
+        );
+    }
+
+    #[test]
+    fn test_wildcard_conflicts_with_loopback_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let probes = probe_port(
+            port,
+            &[IpAddr::V4(Ipv4Addr::UNSPECIFIED)],
+            &[Transport::Tcp],
+        );
+        assert!(probes[0].blocks());
+    }
+}
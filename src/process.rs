@@ -1,86 +1,275 @@
-use anyhow::Result;
-use std::time::{SystemTime, UNIX_EPOCH};
-use sysinfo::{Pid, System};
+use anyhow::{Context, Result};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sysinfo::{Pid, ProcessRefreshKind, Signal, System, Uid, UpdateKind, Users};
 
-#[cfg(unix)]
-use std::time::Duration;
-#[cfg(unix)]
-use sysinfo::Signal;
+/// Default time a process gets to exit after SIGTERM before it is force killed
+pub const TERM_GRACE_PERIOD: Duration = Duration::from_millis(100);
+
+/// Length Linux cuts process names to (`TASK_COMM_LEN` without the NUL)
+const TRUNCATED_NAME_LEN: usize = 15;
+
+/// A single step of the sequence used to terminate a process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalStep {
+    /// Ask the process to exit (SIGTERM)
+    Terminate,
+    /// Give the process time to exit
+    Wait(Duration),
+    /// Force kill the process if it is still running (SIGKILL)
+    KillIfRunning,
+    /// Force kill the process unconditionally
+    Kill,
+}
+
+impl fmt::Display for SignalStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignalStep::Terminate => write!(f, "SIGTERM"),
+            SignalStep::Wait(duration) => write!(f, "wait {}ms", duration.as_millis()),
+            SignalStep::KillIfRunning => write!(f, "SIGKILL if still running"),
+            #[cfg(windows)]
+            SignalStep::Kill => write!(f, "TerminateProcess"),
+            #[cfg(not(windows))]
+            SignalStep::Kill => write!(f, "SIGKILL"),
+        }
+    }
+}
+
+/// The steps `ProcessManager::kill_process` performs on this platform
+pub fn termination_sequence(grace_period: Duration) -> Vec<SignalStep> {
+    if cfg!(unix) {
+        vec![
+            SignalStep::Terminate,
+            SignalStep::Wait(grace_period),
+            SignalStep::KillIfRunning,
+        ]
+    } else {
+        vec![SignalStep::Kill]
+    }
+}
 
+/// Signals processes and answers questions about them, loading each process
+/// from the OS only when it is first asked about
 pub struct ProcessManager {
-    system: System,
+    system: RefCell<System>,
+    /// PIDs whose metadata has been loaded during this invocation
+    loaded: RefCell<HashSet<u32>>,
+    /// Whether every process (for parent links) has been listed
+    listed: Cell<bool>,
 }
 
 impl ProcessManager {
     pub fn new() -> Self {
-        let system = System::new_all();
-        Self { system }
+        Self {
+            system: RefCell::new(System::new()),
+            loaded: RefCell::new(HashSet::new()),
+            listed: Cell::new(false),
+        }
+    }
+
+    /// Re-read a process, returning whether it is still running
+    fn reload(&self, pid: u32) -> bool {
+        self.loaded.borrow_mut().insert(pid);
+        let refresh_kind = ProcessRefreshKind::new()
+            .with_user(UpdateKind::OnlyIfNotSet)
+            .with_cmd(UpdateKind::OnlyIfNotSet)
+            .with_exe(UpdateKind::OnlyIfNotSet);
+        self.system
+            .borrow_mut()
+            .refresh_process_specifics(Pid::from(pid as usize), refresh_kind)
     }
 
-    pub fn refresh(&mut self) {
-        self.system.refresh_all();
+    /// Load a process the first time it is asked about
+    fn load(&self, pid: u32) {
+        if !self.loaded.borrow().contains(&pid) {
+            self.reload(pid);
+        }
     }
 
-    /// Kill a process by PID
-    pub fn kill_process(&mut self, pid: u32) -> Result<bool> {
-        self.refresh();
+    /// List every process once, for walking parents and children
+    fn list_all(&self) {
+        if !self.listed.replace(true) {
+            self.system
+                .borrow_mut()
+                .refresh_processes_specifics(ProcessRefreshKind::new());
+        }
+    }
+
+    /// Whether the process still exists
+    pub fn is_running(&self, pid: u32) -> bool {
+        self.reload(pid)
+    }
 
+    /// Kill a process by PID, allowing `grace_period` between SIGTERM and SIGKILL
+    pub fn kill_process(&mut self, pid: u32, grace_period: Duration) -> Result<bool> {
         let sysinfo_pid = Pid::from(pid as usize);
-        let result = if let Some(process) = self.system.process(sysinfo_pid) {
-            #[cfg(unix)]
-            {
-                let term_result = process.kill_with(Signal::Term);
-                // Give process time to terminate gracefully
-                std::thread::sleep(Duration::from_millis(100));
-
-                // Check if still running, then force kill
-                self.refresh();
-                if self.system.process(sysinfo_pid).is_some() {
-                    if let Some(process) = self.system.process(sysinfo_pid) {
-                        Ok(process.kill_with(Signal::Kill).unwrap_or(false))
-                    } else {
-                        Ok(true)
+        // The process may have exited since the plan was made
+        if !self.reload(pid) {
+            return Err(anyhow::anyhow!("Process with PID {pid} not found"));
+        }
+
+        let mut killed = false;
+        let mut running = true;
+        for step in termination_sequence(grace_period) {
+            match step {
+                SignalStep::Terminate => killed = self.send_signal(sysinfo_pid, Signal::Term),
+                SignalStep::Wait(duration) => {
+                    std::thread::sleep(duration);
+                    running = self.reload(pid);
+                }
+                SignalStep::KillIfRunning => {
+                    if running {
+                        killed = self.send_signal(sysinfo_pid, Signal::Kill);
                     }
-                } else {
-                    Ok(term_result.unwrap_or(false))
                 }
+                SignalStep::Kill => killed = self.send_signal(sysinfo_pid, Signal::Kill),
             }
+        }
 
-            #[cfg(windows)]
-            {
-                Ok(process.kill())
+        Ok(killed)
+    }
+
+    fn send_signal(&self, pid: Pid, signal: Signal) -> bool {
+        self.system
+            .borrow()
+            .process(pid)
+            .and_then(|process| process.kill_with(signal))
+            .unwrap_or(false)
+    }
+
+    /// Name and full command line of a running process
+    pub fn process_identity(&self, pid: u32) -> Option<(String, String)> {
+        self.load(pid);
+        self.system
+            .borrow()
+            .process(Pid::from(pid as usize))
+            .map(|process| {
+                (
+                    process.name().to_string(),
+                    format!("{} {}", process.name(), process.cmd().join(" ")),
+                )
+            })
+    }
+
+    /// All descendants of a process, parents listed before their children
+    pub fn descendants(&self, pid: u32) -> Vec<u32> {
+        self.list_all();
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (child_pid, process) in self.system.borrow().processes() {
+            if let Some(parent) = process.parent() {
+                children
+                    .entry(parent.as_u32())
+                    .or_default()
+                    .push(child_pid.as_u32());
             }
-        } else {
-            Err(anyhow::anyhow!("Process with PID {pid} not found"))
-        };
+        }
+
+        let mut result = Vec::new();
+        let mut queue = VecDeque::from([pid]);
+        while let Some(current) = queue.pop_front() {
+            if let Some(kids) = children.get_mut(&current) {
+                kids.sort_unstable();
+                for &kid in kids.iter() {
+                    if kid != pid && !result.contains(&kid) {
+                        result.push(kid);
+                        queue.push_back(kid);
+                    }
+                }
+            }
+        }
 
         result
     }
 
     /// Get detailed process information
-    #[allow(dead_code)]
     pub fn get_process_info(&mut self, pid: u32) -> Option<ProcessInfo> {
-        self.refresh();
-
         let sysinfo_pid = Pid::from(pid as usize);
-        self.system.process(sysinfo_pid).map(|process| ProcessInfo {
+
+        // CPU usage is measured between two refreshes
+        self.list_all();
+        let system = self.system.get_mut();
+        system.refresh_process_specifics(sysinfo_pid, ProcessRefreshKind::everything());
+        std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+        system.refresh_process_specifics(sysinfo_pid, ProcessRefreshKind::everything());
+
+        let process = system.process(sysinfo_pid)?;
+        let uid = process.user_id().and_then(uid_to_u32);
+        let user = process.user_id().and_then(|uid| {
+            Users::new_with_refreshed_list()
+                .get_user_by_id(uid)
+                .map(|user| user.name().to_string())
+        });
+
+        let mut parent_chain = Vec::new();
+        let mut current = process.parent();
+        while let Some(parent_pid) = current {
+            let Some(parent) = system.process(parent_pid) else {
+                break;
+            };
+            if parent_chain
+                .iter()
+                .any(|(pid, _)| *pid == parent_pid.as_u32())
+            {
+                break;
+            }
+            parent_chain.push((parent_pid.as_u32(), parent.name().to_string()));
+            current = parent.parent();
+        }
+
+        let mut children: Vec<(u32, String)> = system
+            .processes()
+            .iter()
+            .filter(|(_, child)| child.parent() == Some(sysinfo_pid))
+            .map(|(child_pid, child)| (child_pid.as_u32(), child.name().to_string()))
+            .collect();
+        children.sort();
+
+        let environment = process
+            .environ()
+            .iter()
+            .filter_map(|entry| {
+                let (key, value) = entry.split_once('=')?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect();
+
+        Some(ProcessInfo {
             pid,
             name: process.name().to_string(),
-            command: {
-                let cmd_args = process.cmd().join(" ");
-                format!("{} {cmd_args}", process.name())
-            },
+            argv: process.cmd().to_vec(),
+            exe: process.exe().map(Path::to_path_buf),
+            cwd: process.cwd().map(Path::to_path_buf),
+            uid,
+            user,
+            parent_chain,
+            children,
             memory_usage: process.memory(),
             cpu_usage: process.cpu_usage(),
             start_time: process.start_time(),
-            runtime_duration: self.calculate_runtime(process.start_time()),
+            runtime_duration: Self::calculate_runtime(process.start_time()),
+            environment,
         })
     }
 
     /// Check if a process is a system-critical process that should not be killed
+    ///
+    /// Names are matched exactly (case-insensitive); kernel threads also match
+    /// in their per-CPU form such as `migration/3`. Linux cuts process names
+    /// to 15 characters, so a name of exactly that length matches the start of
+    /// a longer entry (`systemd-resolve` is `systemd-resolved`).
     pub fn is_system_critical(&self, process_name: &str) -> bool {
         let critical_processes = [
             "systemd",
+            "systemd-journald",
+            "systemd-logind",
+            "systemd-networkd",
+            "systemd-resolved",
+            "systemd-timesyncd",
+            "systemd-udevd",
             "kernel",
             "init",
             "kthreadd",
@@ -90,12 +279,13 @@ impl ProcessManager {
             "ksoftirqd",
             "watchdog",
             "sshd",
-            "dbus",
-            "networkd",
+            "dbus-daemon",
+            "dbus-broker",
+            "NetworkManager",
             "wpa_supplicant",
             "dhcpcd",
             "chronyd",
-            "rsyslog",
+            "rsyslogd",
             // Windows critical processes
             "System",
             "smss.exe",
@@ -115,14 +305,42 @@ impl ProcessManager {
         ];
 
         critical_processes.iter().any(|&critical| {
-            process_name
-                .to_lowercase()
-                .contains(&critical.to_lowercase())
+            process_name.eq_ignore_ascii_case(critical)
+                || (process_name.len() == TRUNCATED_NAME_LEN
+                    && critical
+                        .get(..TRUNCATED_NAME_LEN)
+                        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(process_name)))
+                || process_name
+                    .strip_prefix(critical)
+                    .and_then(|rest| rest.strip_prefix('/'))
+                    .is_some_and(|cpu| !cpu.is_empty() && cpu.chars().all(|c| c.is_ascii_digit()))
         })
     }
 
-    #[allow(dead_code)]
-    fn calculate_runtime(&self, start_time: u64) -> String {
+    /// UID of the user owning a process
+    pub fn process_owner(&self, pid: u32) -> Option<u32> {
+        self.load(pid);
+        let system = self.system.borrow();
+        let process = system.process(Pid::from(pid as usize))?;
+        uid_to_u32(process.user_id()?)
+    }
+
+    /// UID of the user running portman
+    pub fn current_user(&self) -> Option<u32> {
+        self.process_owner(sysinfo::get_current_pid().ok()?.as_u32())
+    }
+
+    /// Path of the executable a process was started from
+    pub fn process_exe(&self, pid: u32) -> Option<PathBuf> {
+        self.load(pid);
+        self.system
+            .borrow()
+            .process(Pid::from(pid as usize))?
+            .exe()
+            .map(Path::to_path_buf)
+    }
+
+    fn calculate_runtime(start_time: u64) -> String {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
@@ -134,15 +352,73 @@ impl ProcessManager {
 }
 
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct ProcessInfo {
     pub pid: u32,
     pub name: String,
-    pub command: String,
+    pub argv: Vec<String>,
+    pub exe: Option<PathBuf>,
+    pub cwd: Option<PathBuf>,
+    pub uid: Option<u32>,
+    pub user: Option<String>,
+    /// Parent first, then its parent, up to the root
+    pub parent_chain: Vec<(u32, String)>,
+    /// Direct children
+    pub children: Vec<(u32, String)>,
     pub memory_usage: u64,
     pub cpu_usage: f32,
     pub start_time: u64,
     pub runtime_duration: String,
+    pub environment: Vec<(String, String)>,
+}
+
+#[cfg(unix)]
+fn uid_to_u32(uid: &Uid) -> Option<u32> {
+    Some(**uid)
+}
+
+#[cfg(not(unix))]
+fn uid_to_u32(_uid: &Uid) -> Option<u32> {
+    None
+}
+
+/// Parse durations like `250ms`, `90s`, `30m`, `8h`, `2d` or `1h30m`
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let mut total = 0u64;
+    let mut number = String::new();
+
+    let mut chars = input.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        // Units in milliseconds
+        let unit = match c {
+            'm' if chars.next_if_eq(&'s').is_some() => 1,
+            's' => 1000,
+            'm' => 60_000,
+            'h' => 3_600_000,
+            'd' => 86_400_000,
+            _ => return Err(anyhow::anyhow!("Invalid duration unit '{c}' in '{input}'")),
+        };
+        let value: u64 = number
+            .parse()
+            .with_context(|| format!("Invalid duration: {input}"))?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|millis| total.checked_add(millis))
+            .with_context(|| format!("Duration is too long: {input}"))?;
+        number.clear();
+    }
+
+    if !number.is_empty() || total == 0 {
+        return Err(anyhow::anyhow!(
+            "Invalid duration '{input}'. Use a number with a unit, e.g. 30m, 8h or 2d"
+        ));
+    }
+
+    Ok(Duration::from_millis(total))
 }
 
 /// Format duration in seconds to human-readable format
@@ -194,6 +470,21 @@ mod tests {
         assert_eq!(format_duration(90061), "1d 1h");
     }
 
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("8h").unwrap(), Duration::from_secs(8 * 3600));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("2d").unwrap(), Duration::from_secs(172800));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("1m30s").unwrap(), Duration::from_secs(90));
+        assert!(parse_duration("8").is_err());
+        assert!(parse_duration("8x").is_err());
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("99999999999999999d").is_err());
+        assert!(parse_duration("18446744073709551s1s").is_err());
+    }
+
     #[test]
     fn test_format_memory() {
         assert_eq!(format_memory(512), "512 B");
@@ -210,6 +501,36 @@ mod tests {
         // Just verify we can create a manager without panicking
     }
 
+    #[test]
+    fn test_termination_sequence() {
+        let steps = termination_sequence(Duration::from_millis(250));
+
+        #[cfg(unix)]
+        {
+            assert_eq!(
+                steps,
+                vec![
+                    SignalStep::Terminate,
+                    SignalStep::Wait(Duration::from_millis(250)),
+                    SignalStep::KillIfRunning,
+                ]
+            );
+            assert_eq!(steps[1].to_string(), "wait 250ms");
+        }
+
+        #[cfg(windows)]
+        assert_eq!(steps, vec![SignalStep::Kill]);
+    }
+
+    #[test]
+    fn test_descendants_of_current_process() {
+        let manager = ProcessManager::new();
+        let current = sysinfo::get_current_pid().unwrap().as_u32();
+
+        // The test runner itself is a child of something, never of itself
+        assert!(!manager.descendants(current).contains(&current));
+    }
+
     #[test]
     fn test_is_system_critical() {
         let manager = ProcessManager::new();
@@ -226,5 +547,24 @@ mod tests {
         assert!(!manager.is_system_critical("python"));
         assert!(!manager.is_system_critical("java"));
         assert!(!manager.is_system_critical("firefox"));
+
+        // Kernel threads match in their per-CPU form
+        assert!(manager.is_system_critical("migration/3"));
+        assert!(manager.is_system_critical("ksoftirqd/0"));
+        assert!(!manager.is_system_critical("migration/"));
+
+        // Linux reports names cut to 15 characters
+        assert!(manager.is_system_critical("systemd-journal"));
+        assert!(manager.is_system_critical("systemd-resolve"));
+        assert!(manager.is_system_critical("systemd-network"));
+        assert!(manager.is_system_critical("systemd-timesyn"));
+        assert!(!manager.is_system_critical("systemd-resolv"));
+        assert!(!manager.is_system_critical("systemd-resolvex"));
+
+        // Substrings of critical names are no longer protected
+        assert!(!manager.is_system_critical("init-db"));
+        assert!(!manager.is_system_critical("kernelci-runner"));
+        assert!(!manager.is_system_critical("my-sshd-proxy"));
+        assert!(!manager.is_system_critical("systemd-fake"));
     }
 }
@@ -0,0 +1,373 @@
+use crate::config::{user_data_dir, PortmanConfig, CONFIG_FILE_NAMES};
+use crate::scanner::PortInfo;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directories never searched for project configs
+const SKIPPED_DIRS: &[&str] = &[
+    ".git",
+    "node_modules",
+    "target",
+    "vendor",
+    "dist",
+    "build",
+    ".venv",
+    "venv",
+    "__pycache__",
+];
+
+/// Package manifests that mark the root of a project
+const MANIFEST_FILES: &[&str] = &[
+    "package.json",
+    "Cargo.toml",
+    "go.mod",
+    "pyproject.toml",
+    "requirements.txt",
+    "Gemfile",
+    "composer.json",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "mix.exs",
+    "deno.json",
+];
+
+/// How far past a conflicting port to look for a free alternative
+const SUGGESTION_WINDOW: u16 = 100;
+
+/// Projects known to portman, stored in the user data dir and filled by `portman init`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectRegistry {
+    pub projects: Vec<PathBuf>,
+}
+
+impl ProjectRegistry {
+    pub fn path() -> Option<PathBuf> {
+        user_data_dir().map(|dir| dir.join("projects.json"))
+    }
+
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path().filter(|path| path.exists()) else {
+            return Ok(Self::default());
+        };
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read project registry: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse project registry: {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().context("Could not determine the user data directory")?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write project registry: {}", path.display()))
+    }
+
+    /// Add a project directory; returns false if it was already known
+    pub fn register(&mut self, dir: &Path) -> bool {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        if self.projects.contains(&dir) {
+            return false;
+        }
+        self.projects.push(dir);
+        true
+    }
+}
+
+/// A project config and the ports it claims
+#[derive(Debug, Clone)]
+pub struct Project {
+    pub name: String,
+    pub config_path: PathBuf,
+    pub ports: Vec<u16>,
+    pub services: BTreeMap<u16, String>,
+}
+
+impl Project {
+    pub fn load(config_path: &Path) -> Result<Self> {
+        let config = PortmanConfig::load(config_path)?;
+        let name = config.project.clone().unwrap_or_else(|| {
+            config_path
+                .parent()
+                .and_then(|dir| dir.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| config_path.display().to_string())
+        });
+
+        Ok(Self {
+            name,
+            config_path: config_path.to_path_buf(),
+            ports: config.ports.clone(),
+            services: config.service_names().unwrap_or_default(),
+        })
+    }
+}
+
+/// The project config directly inside `dir`, if any
+pub fn config_in_dir(dir: &Path) -> Option<PathBuf> {
+    CONFIG_FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// The nearest project config in `dir` or one of its parents
+pub fn config_in_ancestors(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().find_map(config_in_dir)
+}
+
+/// The root of the project `dir` belongs to: the nearest directory holding a
+/// portman config, a git checkout (`.git` is a file in worktrees) or a package manifest
+pub fn project_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| {
+            config_in_dir(ancestor).is_some()
+                || ancestor.join(".git").exists()
+                || MANIFEST_FILES
+                    .iter()
+                    .any(|manifest| ancestor.join(manifest).is_file())
+        })
+        .map(Path::to_path_buf)
+}
+
+/// Display name of the project rooted at `root`: the config's `project`
+/// setting, otherwise the directory name
+pub fn project_name(root: &Path) -> String {
+    config_in_dir(root)
+        .and_then(|config_path| Project::load(&config_path).ok())
+        .map(|project| project.name)
+        .or_else(|| {
+            root.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| root.display().to_string())
+}
+
+/// Fill in the project of every port from the working directory of its process
+pub fn annotate(ports: &mut [PortInfo]) {
+    let mut resolved: HashMap<PathBuf, Option<(String, PathBuf)>> = HashMap::new();
+    for port_info in ports {
+        let Some(cwd) = &port_info.cwd else {
+            continue;
+        };
+        let project = resolved
+            .entry(cwd.clone())
+            .or_insert_with(|| project_root(cwd).map(|root| (project_name(&root), root)));
+        if let Some((name, root)) = project {
+            port_info.project = Some(name.clone());
+            port_info.project_root = Some(root.clone());
+        }
+    }
+}
+
+/// All project configs under `root`, or `root` itself if it is a config file
+pub fn discover_config_files(root: &Path) -> Vec<PathBuf> {
+    if root.is_file() {
+        return vec![root.to_path_buf()];
+    }
+
+    let mut found = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        if let Some(config) = config_in_dir(&dir) {
+            found.push(config);
+        }
+
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let is_dir = entry.file_type().is_ok_and(|kind| kind.is_dir());
+            let name = entry.file_name();
+            if is_dir && !SKIPPED_DIRS.contains(&name.to_string_lossy().as_ref()) {
+                pending.push(entry.path());
+            }
+        }
+    }
+
+    found.sort();
+    found
+}
+
+/// A port claimed by more than one project
+#[derive(Debug, Clone)]
+pub struct PortConflict {
+    pub port: u16,
+    /// Indexes into the project list, in discovery order
+    pub claimants: Vec<usize>,
+    /// A free port for every claimant after the first
+    pub suggestions: Vec<u16>,
+}
+
+/// Ports claimed by several projects, with alternatives that no project
+/// claims and nothing currently listens on
+pub fn find_conflicts(projects: &[Project], occupied: &HashSet<u16>) -> Vec<PortConflict> {
+    let mut claims: BTreeMap<u16, Vec<usize>> = BTreeMap::new();
+    for (index, project) in projects.iter().enumerate() {
+        for &port in &project.ports {
+            let claimants = claims.entry(port).or_default();
+            if !claimants.contains(&index) {
+                claimants.push(index);
+            }
+        }
+    }
+
+    let mut taken: HashSet<u16> = claims.keys().copied().collect();
+    taken.extend(occupied);
+
+    claims
+        .into_iter()
+        .filter(|(_, claimants)| claimants.len() > 1)
+        .map(|(port, claimants)| {
+            let suggestions = nearby_free_ports(port, claimants.len() - 1, &taken, |_| true);
+            taken.extend(&suggestions);
+
+            PortConflict {
+                port,
+                claimants,
+                suggestions,
+            }
+        })
+        .collect()
+}
+
+/// Up to `count` ports after `port`, within the suggestion window, that are
+/// not `taken` and pass `usable` (e.g. a bind probe)
+pub fn nearby_free_ports(
+    port: u16,
+    count: usize,
+    taken: &HashSet<u16>,
+    mut usable: impl FnMut(u16) -> bool,
+) -> Vec<u16> {
+    let end = port.saturating_add(SUGGESTION_WINDOW);
+    (port.saturating_add(1)..=end)
+        .filter(|candidate| *candidate != port && !taken.contains(candidate))
+        .filter(|&candidate| usable(candidate))
+        .take(count)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(name: &str, ports: &[u16]) -> Project {
+        Project {
+            name: name.to_string(),
+            config_path: PathBuf::from(format!("/work/{name}/.portman.yaml")),
+            ports: ports.to_vec(),
+            services: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let projects = vec![
+            project("billing", &[8080, 5432]),
+            project("search", &[8080, 9200]),
+            project("gateway", &[8080, 8081]),
+        ];
+        let occupied = HashSet::from([8082]);
+
+        let conflicts = find_conflicts(&projects, &occupied);
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].port, 8080);
+        assert_eq!(conflicts[0].claimants, vec![0, 1, 2]);
+        // 8081 is claimed by gateway and 8082 is in use
+        assert_eq!(conflicts[0].suggestions, vec![8083, 8084]);
+    }
+
+    #[test]
+    fn test_nearby_free_ports() {
+        let taken = HashSet::from([3001, 3002]);
+        // 3004 fails its bind probe
+        let ports = nearby_free_ports(3000, 3, &taken, |port| port != 3004);
+        assert_eq!(ports, vec![3003, 3005, 3006]);
+
+        assert_eq!(
+            nearby_free_ports(u16::MAX, 3, &HashSet::new(), |_| true),
+            Vec::<u16>::new()
+        );
+    }
+
+    #[test]
+    fn test_no_conflicts_for_single_claims() {
+        let projects = vec![project("a", &[3000, 3000]), project("b", &[3001])];
+        assert!(find_conflicts(&projects, &HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn test_discover_config_files() {
+        let root = tempfile::TempDir::new().unwrap();
+        let api = root.path().join("services").join("api");
+        let web = root.path().join("web");
+        let ignored = root.path().join("node_modules").join("pkg");
+        for dir in [&api, &web, &ignored] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(api.join(".portman.yaml"), "ports: [8080]\n").unwrap();
+        fs::write(web.join("portman.toml"), "ports = [8080]\n").unwrap();
+        fs::write(ignored.join(".portman.yaml"), "ports: [8080]\n").unwrap();
+
+        let found = discover_config_files(root.path());
+
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&api.join(".portman.yaml")));
+        assert!(found.contains(&web.join("portman.toml")));
+    }
+
+    #[test]
+    fn test_project_root() {
+        let root = tempfile::TempDir::new().unwrap();
+        let repo = root.path().join("shop");
+        let worktree = root.path().join("shop-feature");
+        let package = repo.join("packages").join("web");
+        for dir in [&repo.join(".git"), &worktree, &package.join("src")] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(
+            worktree.join(".git"),
+            "gitdir: ../shop/.git/worktrees/feature\n",
+        )
+        .unwrap();
+        fs::write(package.join("package.json"), "{}\n").unwrap();
+        fs::write(
+            repo.join(".portman.yaml"),
+            "project: storefront\nports: [3000]\n",
+        )
+        .unwrap();
+
+        assert_eq!(project_root(&repo.join("packages")), Some(repo.clone()));
+        assert_eq!(project_root(&package.join("src")), Some(package.clone()));
+        assert_eq!(project_root(&worktree), Some(worktree.clone()));
+        assert_eq!(project_name(&repo), "storefront");
+        assert_eq!(project_name(&worktree), "shop-feature");
+    }
+
+    #[test]
+    fn test_config_in_ancestors() {
+        let root = tempfile::TempDir::new().unwrap();
+        let nested = root.path().join("api").join("src").join("handlers");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(
+            root.path().join("api").join(".portman.yaml"),
+            "ports: [8080]\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            config_in_ancestors(&nested),
+            Some(root.path().join("api").join(".portman.yaml"))
+        );
+        assert_eq!(config_in_ancestors(&root.path().join("web")), None);
+    }
+}
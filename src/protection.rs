@@ -0,0 +1,267 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Processes that must never be signalled, as written in a config file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProtectionRules {
+    /// Regular expressions matched against the process name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>,
+    /// Exact executable paths
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<PathBuf>,
+    /// Owning user IDs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uids: Vec<u32>,
+    /// Ports whose owners must never be touched
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<u16>,
+}
+
+impl ProtectionRules {
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+            && self.paths.is_empty()
+            && self.uids.is_empty()
+            && self.ports.is_empty()
+    }
+}
+
+/// What is known about a process when deciding whether it may be signalled
+pub struct ProcessFacts<'a> {
+    pub name: &'a str,
+    pub exe: Option<&'a Path>,
+    pub uid: Option<u32>,
+    pub ports: &'a [u16],
+}
+
+/// Compiled protection rules plus the ownership policy for one invocation
+pub struct ProtectionGuard {
+    name_patterns: Vec<Regex>,
+    paths: Vec<PathBuf>,
+    uids: Vec<u32>,
+    ports: Vec<u16>,
+    current_uid: Option<u32>,
+    allow_other_users: bool,
+}
+
+impl ProtectionGuard {
+    pub fn new(
+        rules: &ProtectionRules,
+        current_uid: Option<u32>,
+        allow_other_users: bool,
+    ) -> Result<Self> {
+        let name_patterns = rules
+            .names
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid protected name pattern: {pattern}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            name_patterns,
+            paths: rules.paths.clone(),
+            uids: rules.uids.clone(),
+            ports: rules.ports.clone(),
+            current_uid,
+            allow_other_users,
+        })
+    }
+
+    /// Reason a process must not be signalled, if any rule applies
+    pub fn refusal(&self, process: &ProcessFacts) -> Option<String> {
+        if let Some(reason) = self.port_refusal(process.ports) {
+            return Some(reason);
+        }
+
+        if let Some(pattern) = self.matching_pattern(process.name) {
+            return Some(format!(
+                "Process name matches protected pattern '{pattern}'"
+            ));
+        }
+
+        if let Some(exe) = process.exe {
+            if self.paths.iter().any(|path| path == exe) {
+                return Some(format!(
+                    "Executable {path} is protected by config",
+                    path = exe.display()
+                ));
+            }
+        }
+
+        match process.uid {
+            Some(uid) => {
+                if self.uids.contains(&uid) {
+                    return Some(format!("Processes of UID {uid} are protected by config"));
+                }
+
+                if !self.allow_other_users && self.current_uid.is_some_and(|current| current != uid)
+                {
+                    return Some(format!(
+                        "Owned by another user (UID {uid}); use --allow-other-users to override"
+                    ));
+                }
+            }
+            // Without the owner neither rule can be verified, so refuse
+            None if !self.uids.is_empty()
+                || (!self.allow_other_users && self.current_uid.is_some()) =>
+            {
+                return Some(
+                    "Owning user cannot be read, so the process cannot be verified".to_string(),
+                );
+            }
+            None => {}
+        }
+
+        None
+    }
+
+    /// Reason a container or systemd unit (`kind` names which, as in
+    /// "Container" or "Unit") must not be stopped: one of its ports is
+    /// protected or its name matches a protected pattern. Ownership is left
+    /// to the runtime or systemd, which decide who may stop it.
+    pub fn managed_refusal(&self, kind: &str, name: &str, ports: &[u16]) -> Option<String> {
+        if let Some(reason) = self.port_refusal(ports) {
+            return Some(reason);
+        }
+
+        self.matching_pattern(name)
+            .map(|pattern| format!("{kind} name matches protected pattern '{pattern}'"))
+    }
+
+    fn port_refusal(&self, ports: &[u16]) -> Option<String> {
+        ports
+            .iter()
+            .find(|port| self.ports.contains(port))
+            .map(|port| format!("Port {port} is protected by config"))
+    }
+
+    fn matching_pattern(&self, name: &str) -> Option<&str> {
+        self.name_patterns
+            .iter()
+            .find(|pattern| pattern.is_match(name))
+            .map(Regex::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facts<'a>(name: &'a str, uid: Option<u32>, ports: &'a [u16]) -> ProcessFacts<'a> {
+        ProcessFacts {
+            name,
+            exe: None,
+            uid,
+            ports,
+        }
+    }
+
+    #[test]
+    fn test_name_patterns_and_ports() {
+        let rules = ProtectionRules {
+            names: vec!["^postgres$".to_string(), "^redis-".to_string()],
+            ports: vec![5432],
+            ..Default::default()
+        };
+        let guard = ProtectionGuard::new(&rules, Some(1000), false).unwrap();
+
+        assert!(guard.refusal(&facts("postgres", Some(1000), &[])).is_some());
+        assert!(guard
+            .refusal(&facts("redis-server", Some(1000), &[]))
+            .is_some());
+        assert!(guard.refusal(&facts("node", Some(1000), &[5432])).is_some());
+        assert!(guard
+            .refusal(&facts("postgres-exporter", Some(1000), &[]))
+            .is_none());
+        assert!(guard.refusal(&facts("node", Some(1000), &[3000])).is_none());
+    }
+
+    #[test]
+    fn test_paths_and_uids() {
+        let rules = ProtectionRules {
+            paths: vec![PathBuf::from("/usr/lib/postgresql/bin/postgres")],
+            uids: vec![999],
+            ..Default::default()
+        };
+        let guard = ProtectionGuard::new(&rules, Some(999), false).unwrap();
+
+        let exe = PathBuf::from("/usr/lib/postgresql/bin/postgres");
+        let protected = ProcessFacts {
+            name: "postgres",
+            exe: Some(&exe),
+            uid: None,
+            ports: &[],
+        };
+        assert!(guard.refusal(&protected).is_some());
+        assert!(guard.refusal(&facts("node", Some(999), &[])).is_some());
+    }
+
+    #[test]
+    fn test_other_users_refused_by_default() {
+        let rules = ProtectionRules::default();
+
+        let strict = ProtectionGuard::new(&rules, Some(1000), false).unwrap();
+        let refusal = strict.refusal(&facts("node", Some(1001), &[])).unwrap();
+        assert!(refusal.contains("--allow-other-users"));
+        assert!(strict.refusal(&facts("node", Some(1000), &[])).is_none());
+
+        let relaxed = ProtectionGuard::new(&rules, Some(1000), true).unwrap();
+        assert!(relaxed.refusal(&facts("node", Some(1001), &[])).is_none());
+    }
+
+    #[test]
+    fn test_unknown_owner_refused() {
+        let rules = ProtectionRules::default();
+
+        let strict = ProtectionGuard::new(&rules, Some(1000), false).unwrap();
+        let refusal = strict.refusal(&facts("node", None, &[])).unwrap();
+        assert!(refusal.contains("cannot be verified"));
+
+        let relaxed = ProtectionGuard::new(&rules, Some(1000), true).unwrap();
+        assert!(relaxed.refusal(&facts("node", None, &[])).is_none());
+
+        // A UID rule cannot be checked either, even for other users
+        let by_uid = ProtectionRules {
+            uids: vec![0],
+            ..Default::default()
+        };
+        let relaxed = ProtectionGuard::new(&by_uid, Some(1000), true).unwrap();
+        assert!(relaxed.refusal(&facts("node", None, &[])).is_some());
+    }
+
+    #[test]
+    fn test_managed_rules() {
+        let rules = ProtectionRules {
+            names: vec!["^pg$".to_string()],
+            ports: vec![5432],
+            ..Default::default()
+        };
+        let guard = ProtectionGuard::new(&rules, Some(1000), false).unwrap();
+
+        assert_eq!(
+            guard.managed_refusal("Container", "pg", &[5433]).as_deref(),
+            Some("Container name matches protected pattern '^pg$'")
+        );
+        assert_eq!(
+            guard
+                .managed_refusal("Unit", "db", &[5432, 8080])
+                .as_deref(),
+            Some("Port 5432 is protected by config")
+        );
+        assert!(guard.managed_refusal("Container", "web", &[8080]).is_none());
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let rules = ProtectionRules {
+            names: vec!["(unclosed".to_string()],
+            ..Default::default()
+        };
+        assert!(ProtectionGuard::new(&rules, None, false).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+use crate::probe::Transport;
+use anyhow::{Context, Result};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, ProtocolVersion, SignatureScheme};
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::{lookup_host, TcpStream};
+use tokio::time::timeout;
+use tokio_rustls::TlsConnector;
+
+/// How long each remote check may take unless `--timeout` says otherwise
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A `host:port` to check from this machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub host: String,
+    pub port: u16,
+}
+
+impl std::fmt::Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// `db.internal:5432`, `10.0.0.5:6379` or `[::1]:8080`
+pub fn parse_target(arg: &str) -> Option<Target> {
+    let (host, port) = match arg.strip_prefix('[') {
+        Some(rest) => {
+            let (host, port) = rest.split_once("]:")?;
+            host.parse::<std::net::Ipv6Addr>().ok()?;
+            (host, port)
+        }
+        None => {
+            let (host, port) = arg.rsplit_once(':')?;
+            // Unbracketed IPv6 addresses are ambiguous
+            if host.contains(':') {
+                return None;
+            }
+            (host, port)
+        }
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some(Target {
+        host: host.to_string(),
+        port: port.parse().ok()?,
+    })
+}
+
+/// Result of one check against a target
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Connected, or answered, after this long
+    Reachable(Duration),
+    /// The handshake completed with this protocol version
+    Tls(&'static str),
+    /// UDP got neither a reply nor a rejection: an open port that ignores
+    /// empty datagrams looks the same as a firewall that drops them
+    Silent,
+    Unreachable(String),
+}
+
+/// Connect over TCP to the first address of `target` that accepts
+pub async fn check_tcp(target: &Target, limit: Duration) -> Outcome {
+    let started = Instant::now();
+    match timeout(limit, connect(target)).await {
+        Ok(Ok(_)) => Outcome::Reachable(started.elapsed()),
+        Ok(Err(e)) => Outcome::Unreachable(format!("{e:#}")),
+        Err(_) => Outcome::Unreachable(format!("no answer within {limit:?}")),
+    }
+}
+
+/// Send an empty datagram to each resolved address until one replies or
+/// rejects it. Resolving counts against `limit` too.
+pub async fn check_udp(target: &Target, limit: Duration) -> Outcome {
+    let started = Instant::now();
+    let addresses: Vec<SocketAddr> =
+        match timeout(limit, lookup_host((target.host.as_str(), target.port))).await {
+            Ok(Ok(addresses)) => addresses.collect(),
+            Ok(Err(e)) => {
+                return Outcome::Unreachable(format!(
+                    "Failed to resolve {host}: {e}",
+                    host = target.host
+                ))
+            }
+            Err(_) => return Outcome::Unreachable(format!("no answer within {limit:?}")),
+        };
+    if addresses.is_empty() {
+        return Outcome::Unreachable(format!("No address for {host}", host = target.host));
+    }
+
+    let mut outcome = Outcome::Unreachable(format!("no answer within {limit:?}"));
+    for address in addresses {
+        let remaining = limit.saturating_sub(started.elapsed());
+        if remaining.is_zero() {
+            break;
+        }
+        // tokio does not wake a pending receive for the ICMP error of a connected
+        // UDP socket, so this one blocks on a read timeout instead
+        let exchange = tokio::task::spawn_blocking(move || udp_exchange(address, remaining))
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+        outcome = match exchange {
+            Ok(()) => return Outcome::Reachable(started.elapsed()),
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+                Outcome::Unreachable("port unreachable".to_string())
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                Outcome::Silent
+            }
+            Err(e) => Outcome::Unreachable(e.to_string()),
+        };
+    }
+    outcome
+}
+
+fn udp_exchange(address: SocketAddr, limit: Duration) -> std::io::Result<()> {
+    let bind: SocketAddr = match address {
+        SocketAddr::V4(_) => "0.0.0.0:0".parse().unwrap(),
+        SocketAddr::V6(_) => "[::]:0".parse().unwrap(),
+    };
+    let socket = std::net::UdpSocket::bind(bind)?;
+    socket.set_read_timeout(Some(limit))?;
+    socket.connect(address)?;
+    socket.send(&[])?;
+    socket.recv(&mut [0u8; 512])?;
+    Ok(())
+}
+
+/// Complete a TLS handshake over TCP. Certificates are not checked, so this
+/// tells a TLS endpoint from a plain one, not whether it can be trusted.
+pub async fn check_tls(target: &Target, limit: Duration) -> Outcome {
+    let handshake = async {
+        let server_name = ServerName::try_from(target.host.clone())
+            .with_context(|| format!("Invalid server name {host}", host = target.host))?;
+        let connector = TlsConnector::from(Arc::new(tls_config()?));
+        let stream = connect(target).await?;
+        let stream = connector
+            .connect(server_name, stream)
+            .await
+            .context("TLS handshake failed")?;
+        Ok::<_, anyhow::Error>(stream.get_ref().1.protocol_version())
+    };
+    match timeout(limit, handshake).await {
+        Ok(Ok(version)) => Outcome::Tls(version_name(version)),
+        Ok(Err(e)) => Outcome::Unreachable(format!("{e:#}")),
+        Err(_) => Outcome::Unreachable(format!("no TLS answer within {limit:?}")),
+    }
+}
+
+/// Run the requested checks against one target, in order
+pub async fn check(
+    target: &Target,
+    transports: &[Transport],
+    tls: bool,
+    limit: Duration,
+) -> Vec<(&'static str, Outcome)> {
+    let mut outcomes = Vec::new();
+    for transport in transports {
+        match transport {
+            Transport::Tcp if tls => outcomes.push(("TLS", check_tls(target, limit).await)),
+            Transport::Tcp => outcomes.push(("TCP", check_tcp(target, limit).await)),
+            Transport::Udp => outcomes.push(("UDP", check_udp(target, limit).await)),
+        }
+    }
+    outcomes
+}
+
+/// Try every resolved address, keeping the last error
+async fn connect(target: &Target) -> Result<TcpStream> {
+    let addresses = lookup_host((target.host.as_str(), target.port))
+        .await
+        .with_context(|| format!("Failed to resolve {host}", host = target.host))?;
+    let mut last_error = None;
+    for address in addresses {
+        match TcpStream::connect(address).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(e) => anyhow::Error::new(e),
+        None => anyhow::anyhow!("No address for {host}", host = target.host),
+    })
+}
+
+/// A client that offers TLS 1.3 and 1.2 and accepts any certificate
+fn tls_config() -> Result<ClientConfig> {
+    let provider = Arc::new(ring::default_provider());
+    Ok(ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider)))
+        .with_no_client_auth())
+}
+
+/// Accepts every certificate chain, but still checks the handshake
+/// signatures against the certificate the server sent
+#[derive(Debug)]
+struct AnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+fn version_name(version: Option<ProtocolVersion>) -> &'static str {
+    match version {
+        Some(ProtocolVersion::TLSv1_3) => "TLS 1.3",
+        Some(ProtocolVersion::TLSv1_2) => "TLS 1.2",
+        _ => "TLS",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(
+            parse_target("db.internal:5432"),
+            Some(Target {
+                host: "db.internal".to_string(),
+                port: 5432
+            })
+        );
+        let v6 = parse_target("[::1]:8080").unwrap();
+        assert_eq!(v6.host, "::1");
+        assert_eq!(v6.to_string(), "[::1]:8080");
+        assert_eq!(parse_target("3000"), None);
+        assert_eq!(parse_target("::1:8080"), None);
+        assert_eq!(parse_target(":8080"), None);
+        assert_eq!(parse_target("db:http"), None);
+    }
+
+    #[tokio::test]
+    async fn test_check_tcp_loopback() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let target = Target {
+            host: "127.0.0.1".to_string(),
+            port,
+        };
+        assert!(matches!(
+            check_tcp(&target, Duration::from_secs(2)).await,
+            Outcome::Reachable(_)
+        ));
+
+        drop(listener);
+        assert!(matches!(
+            check_tcp(&target, Duration::from_secs(2)).await,
+            Outcome::Unreachable(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_check_udp_loopback() {
+        let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = Target {
+            host: "localhost".to_string(),
+            port: socket.local_addr().unwrap().port(),
+        };
+        assert_eq!(
+            check_udp(&target, Duration::from_millis(200)).await,
+            Outcome::Silent
+        );
+
+        // Drop the datagram of the silent check before answering the next one
+        socket.recv(&mut [0u8; 16]).unwrap();
+        let echo = std::thread::spawn(move || {
+            let mut buffer = [0u8; 16];
+            let (_, peer) = socket.recv_from(&mut buffer).unwrap();
+            socket.send_to(b"pong", peer).unwrap();
+        });
+        assert!(matches!(
+            check_udp(&target, Duration::from_secs(2)).await,
+            Outcome::Reachable(_)
+        ));
+        echo.join().unwrap();
+
+        // Nothing is bound any more, so the port answers with a rejection
+        assert_eq!(
+            check_udp(&target, Duration::from_secs(2)).await,
+            Outcome::Unreachable("port unreachable".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_tls_plain_server() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target = Target {
+            host: "127.0.0.1".to_string(),
+            port: listener.local_addr().unwrap().port(),
+        };
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let _ = tokio::io::AsyncWriteExt::write_all(&mut stream, b"HTTP/1.1 400\r\n\r\n").await;
+        });
+        assert!(matches!(
+            check_tls(&target, Duration::from_secs(2)).await,
+            Outcome::Unreachable(_)
+        ));
+    }
+}
@@ -1,60 +1,470 @@
+use crate::cli::ScanBackend;
+use crate::containers::Container;
+use crate::netns::{self, Namespace, ProcSocket};
+use crate::systemd::Unit;
+use crate::unix_sockets;
 use anyhow::{Context, Result};
-use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use sysinfo::{Pid, System};
-
-#[cfg(target_os = "linux")]
-use std::collections::HashMap;
+use std::sync::OnceLock;
+use sysinfo::{Pid, ProcessRefreshKind, System, UpdateKind};
 
 #[derive(Debug, Clone)]
 pub struct PortInfo {
     pub port: u16,
-    pub pid: u32,
+    /// Unknown for sockets of other users without elevated privileges
+    pub pid: Option<u32>,
     pub process_name: String,
     pub command: String,
     pub start_time: u64,
     pub memory_usage: u64,
+    /// Well-known service name for the port, filled in by `ServiceRegistry`
+    pub service: Option<String>,
+    /// Working directory of the owning process, when readable
+    pub cwd: Option<PathBuf>,
+    /// Name of the project the process runs in, filled in by `projects::annotate`
+    pub project: Option<String>,
+    /// Root directory of that project
+    pub project_root: Option<PathBuf>,
+    /// Container publishing the port, filled in by `containers::annotate`
+    pub container: Option<Container>,
+    /// systemd unit owning the listener, filled in by `systemd::annotate`
+    pub systemd_unit: Option<Unit>,
+    /// Network namespace of the socket, set when scanning namespaces explicitly
+    pub netns: Option<Namespace>,
+    /// Established client connections, filled in by `graph::annotate_connections`
+    pub connections: usize,
+    /// Clients of the established connections (process or remote address)
+    pub peers: Vec<String>,
+    /// Accept queue of the listening socket: connections waiting to be accepted
+    pub recv_q: u64,
+    /// Accept queue limit (backlog), only known with the netlink backend;
+    /// `/proc/net` (and so procfs and netstat) and lsof report 0 for listeners
+    pub send_q: u64,
+}
+
+/// Shown in place of the process of a socket whose owner cannot be read
+pub const UNKNOWN_OWNER: &str = "unknown owner";
+
+/// How to see the owners of other users' sockets
+pub const ELEVATED_HINT: &str = "re-run with elevated privileges (e.g. sudo) to see socket owners";
+
+impl PortInfo {
+    /// Process name, or the unknown owner marker
+    pub fn owner_name(&self) -> &str {
+        match self.pid {
+            Some(_) => &self.process_name,
+            None => UNKNOWN_OWNER,
+        }
+    }
+
+    /// `name (PID: n)` for messages, or the unknown owner marker
+    pub fn describe_owner(&self) -> String {
+        match self.pid {
+            Some(pid) => format!("{name} (PID: {pid})", name = self.process_name),
+            None => UNKNOWN_OWNER.to_string(),
+        }
+    }
+}
+
+/// An established TCP socket from the system socket table
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connection {
+    pub local_addr: String,
+    pub local_port: u16,
+    pub remote_addr: String,
+    pub remote_port: u16,
+    /// Unknown for sockets of other users without elevated privileges
+    pub pid: Option<u32>,
+    pub process_name: Option<String>,
+    /// Bytes received but not yet read by the process
+    pub recv_q: u64,
+    /// Bytes sent but not yet acknowledged by the peer
+    pub send_q: u64,
+}
+
+/// A listening Unix domain socket and the process that owns it
+#[derive(Debug, Clone)]
+pub struct UnixSocketInfo {
+    pub path: String,
+    /// `stream`, `dgram` or `seqpacket`
+    pub kind: &'static str,
+    /// Unknown for sockets of other users without elevated privileges
+    pub pid: Option<u32>,
+    pub process_name: String,
+    pub command: String,
+}
+
+impl UnixSocketInfo {
+    /// Process name, or the unknown owner marker
+    pub fn owner_name(&self) -> &str {
+        match self.pid {
+            Some(_) => &self.process_name,
+            None => UNKNOWN_OWNER,
+        }
+    }
+}
+
+/// Backend chosen with `--backend`, used by every scanner of this invocation
+static BACKEND: OnceLock<ScanBackend> = OnceLock::new();
+
+/// Read listening sockets from `backend` instead of the platform default
+pub fn select_backend(backend: ScanBackend) {
+    let _ = BACKEND.set(backend);
 }
 
+/// Scans sockets and loads metadata only for the processes that own them.
+///
+/// Results are cached for the lifetime of the scanner, which is meant to be
+/// one command invocation; long-running commands call `refresh` between scans.
 pub struct PortScanner {
+    /// `None` picks the fastest backend that works on this platform
+    backend: Option<ScanBackend>,
     system: System,
+    /// PIDs whose metadata has been loaded since the last refresh
+    loaded: HashSet<u32>,
+    /// Listening ports from the last `scan_all_ports`
+    listeners: Option<Vec<PortInfo>>,
+    /// Socket inode owners, read once from every process's descriptors and
+    /// shared by the listener, connection and Unix socket scans
+    owners: Option<HashMap<u64, u32>>,
 }
 
 impl PortScanner {
     pub fn new() -> Self {
-        let system = System::new_all();
-        Self { system }
+        Self {
+            backend: BACKEND.get().copied(),
+            system: System::new(),
+            loaded: HashSet::new(),
+            listeners: None,
+            owners: None,
+        }
     }
 
+    /// Forget cached sockets and processes so the next scan reads them again
+    // Linux reads socket tables incrementally instead (see `SocketSnapshot`)
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
     pub fn refresh(&mut self) {
-        self.system.refresh_all();
+        self.system = System::new();
+        self.loaded.clear();
+        self.listeners = None;
+        self.owners = None;
+    }
+
+    /// The PID owning each socket inode, cached for the scanner's lifetime
+    fn socket_owners(&mut self) -> Result<HashMap<u64, u32>> {
+        if let Some(owners) = &self.owners {
+            return Ok(owners.clone());
+        }
+        let owners = netns::socket_owners(None)?;
+        self.owners = Some(owners.clone());
+        Ok(owners)
+    }
+
+    /// Load the name, command line, memory and working directory of socket
+    /// owners that have not been loaded yet
+    fn load_processes(&mut self, pids: impl IntoIterator<Item = u32>) {
+        let refresh_kind = ProcessRefreshKind::new()
+            .with_memory()
+            .with_cmd(UpdateKind::OnlyIfNotSet)
+            .with_cwd(UpdateKind::OnlyIfNotSet);
+        for pid in pids {
+            // One PID at a time: refreshing a PID list drops every other process
+            if self.loaded.insert(pid) {
+                self.system
+                    .refresh_process_specifics(Pid::from_u32(pid), refresh_kind);
+            }
+        }
     }
 
     /// Get all occupied ports with their process information
     pub fn scan_all_ports(&mut self) -> Result<Vec<PortInfo>> {
-        self.refresh();
+        if let Some(listeners) = &self.listeners {
+            return Ok(listeners.clone());
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        if let Some(backend @ (ScanBackend::Netlink | ScanBackend::Procfs)) = self.backend {
+            return Err(anyhow::anyhow!(
+                "The {backend:?} backend is only available on Linux"
+            ));
+        }
 
         #[cfg(target_os = "windows")]
-        return self.scan_ports_windows();
+        let listeners = self.scan_ports_windows()?;
 
         #[cfg(any(target_os = "linux", target_os = "macos"))]
-        return self.scan_ports_unix();
+        let listeners = self.scan_ports_unix()?;
+
+        self.listeners = Some(listeners.clone());
+        Ok(listeners)
     }
 
-    /// Check if a specific port is available
-    #[allow(dead_code)]
-    pub fn is_port_available(&self, port: u16) -> bool {
-        TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)).is_ok()
+    /// Get all established TCP connections with the names of the processes
+    /// on either end
+    pub fn scan_connections(&mut self) -> Result<Vec<Connection>> {
+        let mut connections = self.scan_connections_platform()?;
+        self.load_processes(connections.iter().filter_map(|connection| connection.pid));
+        for connection in &mut connections {
+            connection.process_name = connection
+                .pid
+                .and_then(|pid| self.system.process(Pid::from(pid as usize)))
+                .map(|process| process.name().to_string());
+        }
+        Ok(connections)
+    }
+
+    /// Established sockets from the selected backend, like `socket_table`,
+    /// owned through the same descriptor walk as the listeners
+    #[cfg(target_os = "linux")]
+    fn scan_connections_platform(&mut self) -> Result<Vec<Connection>> {
+        use crate::sock_diag;
+
+        let sockets = match self.backend {
+            Some(ScanBackend::Netstat) => return self.scan_connections_netstat(),
+            Some(ScanBackend::Netlink) => sock_diag::established_sockets()?,
+            Some(ScanBackend::Procfs) => netns::current_connections()?,
+            // Restricted sandboxes may refuse netlink sockets
+            None => sock_diag::established_sockets().or_else(|_| netns::current_connections())?,
+        };
+        let owners = self.socket_owners()?;
+        Ok(sockets
+            .into_iter()
+            .map(|socket| Connection {
+                local_addr: socket.local.ip().to_string(),
+                local_port: socket.local.port(),
+                remote_addr: socket.remote.ip().to_string(),
+                remote_port: socket.remote.port(),
+                pid: owners.get(&socket.inode).copied(),
+                process_name: None,
+                recv_q: socket.recv_q,
+                send_q: socket.send_q,
+            })
+            .collect())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn scan_connections_netstat(&self) -> Result<Vec<Connection>> {
+        let output = Command::new("netstat")
+            .args(["-tanp"])
+            .output()
+            .context("Failed to execute netstat command")?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("netstat command failed"));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .skip(2)
+            .filter_map(parse_netstat_connection)
+            .collect())
+    }
+
+    #[cfg(target_os = "macos")]
+    fn scan_connections_platform(&mut self) -> Result<Vec<Connection>> {
+        let output = Command::new("lsof")
+            .args(["-i", "TCP", "-P", "-n", "-s", "TCP:ESTABLISHED"])
+            .output()
+            .context("Failed to execute lsof command")?;
+
+        // lsof exits with 1 when nothing matches
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .skip(1)
+            .filter_map(parse_lsof_connection)
+            .collect())
+    }
+
+    #[cfg(target_os = "windows")]
+    fn scan_connections_platform(&mut self) -> Result<Vec<Connection>> {
+        let output = Command::new("netstat")
+            .args(["-ano", "-p", "TCP"])
+            .output()
+            .context("Failed to execute netstat command")?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("netstat command failed"));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if parts.len() < 5 || parts[0] != "TCP" || parts[3] != "ESTABLISHED" {
+                    return None;
+                }
+                let (local_addr, local_port) = split_address(parts[1])?;
+                let (remote_addr, remote_port) = split_address(parts[2])?;
+                Some(Connection {
+                    local_addr,
+                    local_port,
+                    remote_addr,
+                    remote_port,
+                    pid: parts[4].parse().ok(),
+                    process_name: None,
+                    recv_q: 0,
+                    send_q: 0,
+                })
+            })
+            .collect())
+    }
+
+    /// Get all listening Unix domain sockets with their owning process
+    pub fn scan_unix_sockets(&mut self) -> Result<Vec<UnixSocketInfo>> {
+        let sockets = unix_sockets::listening()?;
+        let owners = self.socket_owners()?;
+        self.load_processes(
+            sockets
+                .iter()
+                .filter_map(|socket| owners.get(&socket.inode).copied()),
+        );
+        let mut socket_infos = Vec::new();
+        for socket in sockets {
+            // The socket is still in use when its owner cannot be read
+            let Some(&pid) = owners.get(&socket.inode) else {
+                socket_infos.push(UnixSocketInfo {
+                    path: socket.path,
+                    kind: socket.kind,
+                    pid: None,
+                    process_name: String::new(),
+                    command: String::new(),
+                });
+                continue;
+            };
+            if let Some(process) = self.system.process(Pid::from(pid as usize)) {
+                socket_infos.push(UnixSocketInfo {
+                    path: socket.path,
+                    kind: socket.kind,
+                    pid: Some(pid),
+                    process_name: process.name().to_string(),
+                    command: format!("{} {}", process.name(), process.cmd().join(" ")),
+                });
+            }
+        }
+
+        socket_infos.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(socket_infos)
+    }
+
+    /// Get the listening ports of network namespaces from their `/proc`
+    /// socket tables, tagged with the namespace
+    pub fn scan_namespaces(&mut self, namespaces: &[Namespace]) -> Result<Vec<PortInfo>> {
+        let mut port_infos = Vec::new();
+        for namespace in namespaces {
+            port_infos.extend(self.namespace_ports(namespace)?);
+        }
+        Ok(port_infos)
+    }
+
+    fn namespace_ports(&mut self, namespace: &Namespace) -> Result<Vec<PortInfo>> {
+        let (sockets, owners) = netns::listening_sockets(namespace)?;
+        Ok(self
+            .owned_ports(sockets, &owners)
+            .into_iter()
+            .map(|port_info| PortInfo {
+                netns: Some(namespace.clone()),
+                ..port_info
+            })
+            .collect())
+    }
+
+    /// Listening sockets of the caller's namespace with their inodes, from
+    /// the selected backend. `netstat` has no inodes, so it reads `/proc/net`.
+    #[cfg(target_os = "linux")]
+    pub fn socket_table(&self) -> Result<Vec<ProcSocket>> {
+        use crate::sock_diag;
+
+        match self.backend {
+            Some(ScanBackend::Netlink) => sock_diag::listening_sockets(),
+            Some(ScanBackend::Procfs | ScanBackend::Netstat) => netns::current_sockets(),
+            // Restricted sandboxes may refuse netlink sockets
+            None => sock_diag::listening_sockets().or_else(|_| netns::current_sockets()),
+        }
+    }
+
+    /// Ports of the sockets with their owning processes loaded. Sockets no
+    /// visible process holds, typically those of other users when not
+    /// running as root, are kept with an unknown owner.
+    fn owned_ports(
+        &mut self,
+        sockets: Vec<ProcSocket>,
+        owners: &HashMap<u64, u32>,
+    ) -> Vec<PortInfo> {
+        self.load_processes(
+            sockets
+                .iter()
+                .filter_map(|socket| owners.get(&socket.inode).copied()),
+        );
+        sockets
+            .into_iter()
+            .filter_map(|socket| match owners.get(&socket.inode) {
+                Some(&pid) => self.owned_port(pid, socket.port, socket.recv_q, socket.send_q),
+                None => Some(unowned_port(socket.port, socket.recv_q, socket.send_q)),
+            })
+            .collect()
+    }
+
+    /// Describe a listener that appeared since the last look, reading its
+    /// process again in case the PID was reused
+    pub fn describe_listener(
+        &mut self,
+        pid: Option<u32>,
+        port: u16,
+        recv_q: u64,
+    ) -> Option<PortInfo> {
+        let Some(pid) = pid else {
+            return Some(unowned_port(port, recv_q, 0));
+        };
+        self.loaded.remove(&pid);
+        self.load_processes([pid]);
+        self.owned_port(pid, port, recv_q, 0)
+    }
+
+    /// A listening port of a loaded process
+    fn owned_port(&self, pid: u32, port: u16, recv_q: u64, send_q: u64) -> Option<PortInfo> {
+        let process = self.system.process(Pid::from(pid as usize))?;
+        Some(PortInfo {
+            port,
+            pid: Some(pid),
+            process_name: process.name().to_string(),
+            command: format!("{} {}", process.name(), process.cmd().join(" ")),
+            start_time: process.start_time(),
+            memory_usage: process.memory(),
+            service: None,
+            cwd: process.cwd().map(Path::to_path_buf),
+            project: None,
+            project_root: None,
+            container: None,
+            systemd_unit: None,
+            netns: None,
+            connections: 0,
+            peers: Vec::new(),
+            recv_q,
+            send_q,
+        })
     }
 
-    /// Get process information for a specific port
+    /// Get process information for a specific port, from the cached scan
+    #[allow(dead_code)]
     pub fn get_port_info(&mut self, port: u16) -> Result<Option<PortInfo>> {
         let all_ports = self.scan_all_ports()?;
         Ok(all_ports.into_iter().find(|info| info.port == port))
     }
 
     #[cfg(target_os = "linux")]
-    fn scan_ports_unix(&self) -> Result<Vec<PortInfo>> {
+    fn scan_ports_unix(&mut self) -> Result<Vec<PortInfo>> {
+        if self.backend == Some(ScanBackend::Netstat) {
+            return self.scan_ports_netstat();
+        }
+        let sockets = self.socket_table()?;
+        let owners = self.socket_owners()?;
+        Ok(self.owned_ports(sockets, &owners))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn scan_ports_netstat(&mut self) -> Result<Vec<PortInfo>> {
         let output = Command::new("netstat")
             .args(["-tulnp"])
             .output()
@@ -66,28 +476,48 @@ impl PortScanner {
 
         let output_str = String::from_utf8_lossy(&output.stdout);
         let mut port_infos = Vec::new();
-        let mut pid_to_port: HashMap<u32, Vec<u16>> = HashMap::new();
+        let mut pid_to_port: HashMap<u32, Vec<(u16, u64, u64)>> = HashMap::new();
 
         // Parse netstat output
         for line in output_str.lines().skip(2) {
-            if let Some(port_info) = self.parse_netstat_line(line) {
-                if let Some(pid) = port_info.1 {
-                    pid_to_port.entry(pid).or_default().push(port_info.0);
+            match self.parse_netstat_line(line) {
+                Some((port, Some(pid), recv_q, send_q)) => {
+                    pid_to_port
+                        .entry(pid)
+                        .or_default()
+                        .push((port, recv_q, send_q));
+                }
+                // netstat shows "-" for sockets of other users
+                Some((port, None, recv_q, send_q)) => {
+                    port_infos.push(unowned_port(port, recv_q, send_q));
                 }
+                None => {}
             }
         }
 
         // Match PIDs with process information
+        self.load_processes(pid_to_port.keys().copied());
         for (pid, ports) in pid_to_port {
             if let Some(process) = self.system.process(Pid::from(pid as usize)) {
-                for port in ports {
+                for (port, recv_q, send_q) in ports {
                     port_infos.push(PortInfo {
                         port,
-                        pid,
+                        pid: Some(pid),
                         process_name: process.name().to_string(),
                         command: format!("{} {}", process.name(), process.cmd().join(" ")),
                         start_time: process.start_time(),
                         memory_usage: process.memory(),
+                        service: None,
+                        cwd: process.cwd().map(Path::to_path_buf),
+                        project: None,
+                        project_root: None,
+                        container: None,
+                        systemd_unit: None,
+                        netns: None,
+                        connections: 0,
+                        peers: Vec::new(),
+                        recv_q,
+                        send_q,
                     });
                 }
             }
@@ -97,7 +527,7 @@ impl PortScanner {
     }
 
     #[cfg(target_os = "macos")]
-    fn scan_ports_unix(&self) -> Result<Vec<PortInfo>> {
+    fn scan_ports_unix(&mut self) -> Result<Vec<PortInfo>> {
         // Use lsof on macOS as it's more reliable for getting process info
         let output = Command::new("lsof")
             .args(["-i", "TCP", "-P", "-n", "-s", "TCP:LISTEN"])
@@ -112,18 +542,33 @@ impl PortScanner {
         let mut port_infos = Vec::new();
 
         // Parse lsof output (format: COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME)
-        for line in output_str.lines().skip(1) {
-            if let Some(port_info) = self.parse_lsof_line(line) {
-                if let Some(process) = self.system.process(Pid::from(port_info.1 as usize)) {
-                    port_infos.push(PortInfo {
-                        port: port_info.0,
-                        pid: port_info.1,
-                        process_name: process.name().to_string(),
-                        command: format!("{} {}", process.name(), process.cmd().join(" ")),
-                        start_time: process.start_time(),
-                        memory_usage: process.memory(),
-                    });
-                }
+        let listening: Vec<(u16, u32)> = output_str
+            .lines()
+            .skip(1)
+            .filter_map(|line| self.parse_lsof_line(line))
+            .collect();
+        self.load_processes(listening.iter().map(|(_, pid)| *pid));
+        for port_info in listening {
+            if let Some(process) = self.system.process(Pid::from(port_info.1 as usize)) {
+                port_infos.push(PortInfo {
+                    port: port_info.0,
+                    pid: Some(port_info.1),
+                    process_name: process.name().to_string(),
+                    command: format!("{} {}", process.name(), process.cmd().join(" ")),
+                    start_time: process.start_time(),
+                    memory_usage: process.memory(),
+                    service: None,
+                    cwd: process.cwd().map(Path::to_path_buf),
+                    project: None,
+                    project_root: None,
+                    container: None,
+                    systemd_unit: None,
+                    netns: None,
+                    connections: 0,
+                    peers: Vec::new(),
+                    recv_q: 0,
+                    send_q: 0,
+                });
             }
         }
 
@@ -131,7 +576,7 @@ impl PortScanner {
     }
 
     #[cfg(target_os = "windows")]
-    fn scan_ports_windows(&self) -> Result<Vec<PortInfo>> {
+    fn scan_ports_windows(&mut self) -> Result<Vec<PortInfo>> {
         let output = Command::new("netstat")
             .args(["-ano"])
             .output()
@@ -144,20 +589,34 @@ impl PortScanner {
         let output_str = String::from_utf8_lossy(&output.stdout);
         let mut port_infos = Vec::new();
 
-        for line in output_str.lines().skip(4) {
-            if line.trim().starts_with("TCP") {
-                if let Some(port_info) = self.parse_netstat_line_windows(line) {
-                    if let Some(process) = self.system.process(Pid::from(port_info.1 as usize)) {
-                        port_infos.push(PortInfo {
-                            port: port_info.0,
-                            pid: port_info.1,
-                            process_name: process.name().to_string(),
-                            command: format!("{} {}", process.name(), process.cmd().join(" ")),
-                            start_time: process.start_time(),
-                            memory_usage: process.memory(),
-                        });
-                    }
-                }
+        let listening: Vec<(u16, u32)> = output_str
+            .lines()
+            .skip(4)
+            .filter(|line| line.trim().starts_with("TCP"))
+            .filter_map(|line| self.parse_netstat_line_windows(line))
+            .collect();
+        self.load_processes(listening.iter().map(|(_, pid)| *pid));
+        for port_info in listening {
+            if let Some(process) = self.system.process(Pid::from(port_info.1 as usize)) {
+                port_infos.push(PortInfo {
+                    port: port_info.0,
+                    pid: Some(port_info.1),
+                    process_name: process.name().to_string(),
+                    command: format!("{} {}", process.name(), process.cmd().join(" ")),
+                    start_time: process.start_time(),
+                    memory_usage: process.memory(),
+                    service: None,
+                    cwd: process.cwd().map(Path::to_path_buf),
+                    project: None,
+                    project_root: None,
+                    container: None,
+                    systemd_unit: None,
+                    netns: None,
+                    connections: 0,
+                    peers: Vec::new(),
+                    recv_q: 0,
+                    send_q: 0,
+                });
             }
         }
 
@@ -189,7 +648,8 @@ impl PortScanner {
     }
 
     #[cfg(target_os = "linux")]
-    fn parse_netstat_line(&self, line: &str) -> Option<(u16, Option<u32>)> {
+    /// Port, owning PID and the Recv-Q/Send-Q columns of a listening socket
+    fn parse_netstat_line(&self, line: &str) -> Option<(u16, Option<u32>, u64, u64)> {
         let parts: Vec<&str> = line.split_whitespace().collect();
         if parts.len() < 4 {
             return None;
@@ -215,7 +675,10 @@ impl PortScanner {
             None
         };
 
-        Some((port, pid))
+        let recv_q = parts[1].parse().unwrap_or(0);
+        let send_q = parts[2].parse().unwrap_or(0);
+
+        Some((port, pid, recv_q, send_q))
     }
 
     #[cfg(target_os = "windows")]
@@ -240,6 +703,102 @@ impl PortScanner {
     }
 }
 
+/// A listening port whose owning process cannot be seen with the caller's
+/// privileges. The port is still occupied.
+fn unowned_port(port: u16, recv_q: u64, send_q: u64) -> PortInfo {
+    PortInfo {
+        port,
+        pid: None,
+        process_name: String::new(),
+        command: String::new(),
+        start_time: 0,
+        memory_usage: 0,
+        service: None,
+        cwd: None,
+        project: None,
+        project_root: None,
+        container: None,
+        systemd_unit: None,
+        netns: None,
+        connections: 0,
+        peers: Vec::new(),
+        recv_q,
+        send_q,
+    }
+}
+
+#[cfg(test)]
+impl PortInfo {
+    /// A listener of `pid` with nothing annotated, for tests to adjust with
+    /// struct update syntax
+    pub fn listener(port: u16, pid: u32, name: &str) -> Self {
+        PortInfo {
+            pid: Some(pid),
+            process_name: name.to_string(),
+            command: name.to_string(),
+            ..unowned_port(port, 0, 0)
+        }
+    }
+}
+
+/// Split `127.0.0.1:5432`, `::1:5432` or `[::1]:5432` into address and port
+fn split_address(address: &str) -> Option<(String, u16)> {
+    let (host, port) = address.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    Some((host.to_string(), port.parse().ok()?))
+}
+
+/// `tcp 0 0 127.0.0.1:40000 127.0.0.1:5432 ESTABLISHED 4242/node`
+#[cfg(target_os = "linux")]
+fn parse_netstat_connection(line: &str) -> Option<Connection> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 6 || !parts[0].starts_with("tcp") || parts[5] != "ESTABLISHED" {
+        return None;
+    }
+
+    let (local_addr, local_port) = split_address(parts[3])?;
+    let (remote_addr, remote_port) = split_address(parts[4])?;
+    let pid = parts
+        .get(6)
+        .and_then(|pid_info| pid_info.split('/').next())
+        .and_then(|pid| pid.parse().ok());
+
+    Some(Connection {
+        local_addr,
+        local_port,
+        remote_addr,
+        remote_port,
+        pid,
+        process_name: None,
+        recv_q: parts[1].parse().unwrap_or(0),
+        send_q: parts[2].parse().unwrap_or(0),
+    })
+}
+
+/// `node 4242 me 23u IPv4 0x1 0t0 TCP 127.0.0.1:40000->127.0.0.1:5432 (ESTABLISHED)`
+#[cfg(target_os = "macos")]
+fn parse_lsof_connection(line: &str) -> Option<Connection> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 10 {
+        return None;
+    }
+
+    let (local, remote) = parts[8].split_once("->")?;
+    let (local_addr, local_port) = split_address(local)?;
+    let (remote_addr, remote_port) = split_address(remote)?;
+
+    Some(Connection {
+        local_addr,
+        local_port,
+        remote_addr,
+        remote_port,
+        pid: parts[1].parse().ok(),
+        process_name: None,
+        recv_q: 0,
+        send_q: 0,
+    })
+}
+
 /// Common development ports that developers typically use
 pub const COMMON_DEV_PORTS: &[u16] = &[
     3000, 3001, 3002, 3003, // React, Next.js, Node.js
@@ -274,6 +833,7 @@ pub fn parse_port_range(range_str: &str) -> Result<(u16, u16)> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
 
     #[test]
     fn test_parse_port_range_valid() {
@@ -300,6 +860,92 @@ mod tests {
         assert!(!COMMON_DEV_PORTS.contains(&22)); // SSH port shouldn't be included
     }
 
+    #[test]
+    fn test_split_address() {
+        assert_eq!(
+            split_address("127.0.0.1:5432"),
+            Some(("127.0.0.1".to_string(), 5432))
+        );
+        assert_eq!(split_address("::1:8080"), Some(("::1".to_string(), 8080)));
+        assert_eq!(split_address("[::1]:8080"), Some(("::1".to_string(), 8080)));
+        assert_eq!(split_address("0.0.0.0:*"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_netstat_connection() {
+        let line = "tcp        0     12 127.0.0.1:40000         127.0.0.1:5432          ESTABLISHED 4242/node";
+        let connection = parse_netstat_connection(line).unwrap();
+        assert_eq!(connection.local_port, 40000);
+        assert_eq!(connection.remote_port, 5432);
+        assert_eq!(connection.pid, Some(4242));
+        assert_eq!(connection.send_q, 12);
+
+        let other_user =
+            "tcp6       0      0 ::1:5432                ::1:40002               ESTABLISHED -";
+        assert_eq!(parse_netstat_connection(other_user).unwrap().pid, None);
+
+        let listening =
+            "tcp        0      0 0.0.0.0:22              0.0.0.0:*               LISTEN      -";
+        assert!(parse_netstat_connection(listening).is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_netstat_line_queues() {
+        let scanner = PortScanner::new();
+        let line = "tcp        3    511 0.0.0.0:8080            0.0.0.0:*               LISTEN      4242/node";
+        assert_eq!(
+            scanner.parse_netstat_line(line),
+            Some((8080, Some(4242), 3, 511))
+        );
+    }
+
+    #[test]
+    fn test_owned_ports_keeps_unknown_owners() {
+        let mut scanner = PortScanner::new();
+        let socket = |port, inode| ProcSocket {
+            address: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port,
+            udp: false,
+            inode,
+            recv_q: 0,
+            send_q: 128,
+            uid: 0,
+        };
+        let owners = HashMap::from([(100, std::process::id())]);
+
+        let ports = scanner.owned_ports(vec![socket(3000, 100), socket(5432, 200)], &owners);
+        assert_eq!(ports.len(), 2);
+        assert_eq!(ports[0].pid, Some(std::process::id()));
+        assert_eq!(ports[1].port, 5432);
+        assert_eq!(ports[1].pid, None);
+        assert_eq!(ports[1].owner_name(), UNKNOWN_OWNER);
+        assert_eq!(ports[1].send_q, 128);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_scan_connections_without_netstat() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let _client = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let _server = listener.accept().unwrap();
+
+        for backend in [None, Some(ScanBackend::Netlink), Some(ScanBackend::Procfs)] {
+            let mut scanner = PortScanner::new();
+            scanner.backend = backend;
+            let connections = scanner.scan_connections().unwrap();
+            let client = connections
+                .iter()
+                .find(|connection| connection.remote_port == port)
+                .unwrap_or_else(|| panic!("client connection found with {backend:?}"));
+            assert_eq!(client.remote_addr, "127.0.0.1");
+            assert_eq!(client.pid, Some(std::process::id()));
+            assert!(client.process_name.is_some());
+        }
+    }
+
     #[test]
     fn test_port_scanner_creation() {
         let _scanner = PortScanner::new();
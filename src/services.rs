@@ -0,0 +1,153 @@
+use crate::config::Resolved;
+use crate::scanner::PortInfo;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+#[cfg(windows)]
+const SERVICES_FILE: &str = r"C:\Windows\System32\drivers\etc\services";
+#[cfg(not(windows))]
+const SERVICES_FILE: &str = "/etc/services";
+
+/// Development tools and servers that /etc/services does not know about
+/// (or names differently)
+pub const BUILTIN_SERVICES: &[(u16, &str)] = &[
+    (2181, "zookeeper"),
+    (2345, "delve"),
+    (3306, "mysql"),
+    (4200, "angular"),
+    (4321, "astro"),
+    (5005, "jdwp"),
+    (5173, "vite"),
+    (5174, "vite"),
+    (5432, "postgresql"),
+    (5672, "rabbitmq"),
+    (5678, "debugpy"),
+    (6006, "storybook"),
+    (6379, "redis"),
+    (8200, "vault"),
+    (8500, "consul"),
+    (8545, "ethereum-rpc"),
+    (8888, "jupyter"),
+    (9090, "prometheus"),
+    (9092, "kafka"),
+    (9200, "elasticsearch"),
+    (9222, "chrome-devtools"),
+    (9229, "node-inspector"),
+    (11211, "memcached"),
+    (15672, "rabbitmq-management"),
+    (16686, "jaeger"),
+    (19000, "expo"),
+    (24678, "vite-hmr"),
+    (27017, "mongodb"),
+    (35729, "livereload"),
+];
+
+/// Maps port numbers to well-known service names.
+///
+/// Lookup order: names from config, the built-in developer table, then
+/// the system services database.
+pub struct ServiceRegistry {
+    names: HashMap<u16, String>,
+}
+
+impl ServiceRegistry {
+    pub fn load(configured: &BTreeMap<u16, Resolved<String>>) -> Self {
+        let system = fs::read_to_string(SERVICES_FILE)
+            .map(|content| parse_services_file(&content))
+            .unwrap_or_default();
+        Self::from_sources(system, configured)
+    }
+
+    fn from_sources(
+        system: HashMap<u16, String>,
+        configured: &BTreeMap<u16, Resolved<String>>,
+    ) -> Self {
+        let mut names = system;
+        for &(port, name) in BUILTIN_SERVICES {
+            names.insert(port, name.to_string());
+        }
+        for (&port, name) in configured {
+            names.insert(port, name.value.clone());
+        }
+        Self { names }
+    }
+
+    pub fn lookup(&self, port: u16) -> Option<&str> {
+        self.names.get(&port).map(String::as_str)
+    }
+
+    /// Fill in the `service` field of every scanned port
+    pub fn annotate(&self, ports: &mut [PortInfo]) {
+        for port_info in ports {
+            port_info.service = self.lookup(port_info.port).map(str::to_string);
+        }
+    }
+}
+
+/// Parse the `name port/protocol [aliases] [# comment]` format of /etc/services.
+/// Only TCP entries are used; the first name listed for a port wins.
+pub fn parse_services_file(content: &str) -> HashMap<u16, String> {
+    let mut names = HashMap::new();
+
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut parts = line.split_whitespace();
+        let (Some(name), Some(port_proto)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+
+        let Some((port, protocol)) = port_proto.split_once('/') else {
+            continue;
+        };
+        if !protocol.eq_ignore_ascii_case("tcp") {
+            continue;
+        }
+
+        if let Ok(port) = port.parse::<u16>() {
+            names.entry(port).or_insert_with(|| name.to_string());
+        }
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ConfigSource;
+
+    #[test]
+    fn test_parse_services_file() {
+        let content = "\
+# Network services, Internet style
+ssh\t\t22/tcp\t\t\t\t# SSH Remote Login Protocol
+domain\t\t53/udp
+postgresql\t5432/tcp\tpostgres
+postgres-alt\t5432/tcp
+";
+        let names = parse_services_file(content);
+
+        assert_eq!(names.get(&22).map(String::as_str), Some("ssh"));
+        assert_eq!(names.get(&5432).map(String::as_str), Some("postgresql"));
+        assert!(!names.contains_key(&53));
+    }
+
+    #[test]
+    fn test_lookup_precedence() {
+        let system = HashMap::from([(22, "ssh".to_string()), (8888, "ddi-tcp-1".to_string())]);
+        let configured = BTreeMap::from([(
+            3000,
+            Resolved {
+                value: "frontend".to_string(),
+                source: ConfigSource::Default,
+            },
+        )]);
+
+        let registry = ServiceRegistry::from_sources(system, &configured);
+
+        assert_eq!(registry.lookup(22), Some("ssh"));
+        assert_eq!(registry.lookup(8888), Some("jupyter"));
+        assert_eq!(registry.lookup(3000), Some("frontend"));
+        assert_eq!(registry.lookup(40000), None);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::scanner::{PortInfo, PortScanner};
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::IpAddr;
+
+#[cfg(target_os = "linux")]
+use std::collections::{HashMap, HashSet};
+
+/// What makes a listening socket the same socket from one scan to the next.
+/// A port that is closed and bound again, by a restarted server or by
+/// another process, is a different socket even though the port is the same.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SocketId {
+    pub port: u16,
+    /// Local address, when the platform reports it
+    pub address: Option<IpAddr>,
+    /// `LISTEN` for TCP, `UNCONN` for UDP
+    pub state: &'static str,
+    /// Kernel socket inode, 0 when the platform does not report it
+    pub inode: u64,
+    /// `None` while the owner cannot be read
+    pub pid: Option<u32>,
+}
+
+/// Listening sockets that appeared and disappeared since the last update
+#[derive(Debug, Default)]
+pub struct SnapshotDiff {
+    pub opened: Vec<PortInfo>,
+    pub closed: Vec<PortInfo>,
+}
+
+/// A socket from the kernel table, described only if the platform scan
+/// already had to load its process
+struct Listener {
+    id: SocketId,
+    recv_q: u64,
+    info: Option<PortInfo>,
+}
+
+/// The listening sockets seen by the last update. Each socket's process is
+/// described once when the socket appears, so an unchanged host costs one
+/// read of the socket table per update.
+#[derive(Default)]
+pub struct SocketSnapshot {
+    sockets: BTreeMap<SocketId, PortInfo>,
+    /// Owner of every socket inode in the table; `None` when it cannot be read
+    #[cfg(target_os = "linux")]
+    owners: HashMap<u64, Option<u32>>,
+}
+
+impl SocketSnapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Listening ports as of the last update
+    pub fn ports(&self) -> impl Iterator<Item = &PortInfo> {
+        self.sockets.values()
+    }
+
+    /// Read the socket table again and describe only the sockets that are new
+    pub fn update(&mut self, scanner: &mut PortScanner) -> Result<SnapshotDiff> {
+        let listeners = self.listeners(scanner)?;
+        let current: BTreeSet<&SocketId> = listeners.iter().map(|listener| &listener.id).collect();
+        let (_, closed_ids) = diff(&self.sockets, &current);
+
+        let mut changes = SnapshotDiff::default();
+        for id in closed_ids {
+            changes.closed.extend(self.sockets.remove(&id));
+        }
+        for listener in listeners {
+            if self.sockets.contains_key(&listener.id) {
+                continue;
+            }
+            let info = listener.info.or_else(|| {
+                scanner.describe_listener(listener.id.pid, listener.id.port, listener.recv_q)
+            });
+            // The process may have exited since the table was read
+            if let Some(info) = info {
+                changes.opened.push(info.clone());
+                self.sockets.insert(listener.id, info);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Sockets from netlink or `/proc/net`; descriptors are only walked to find the
+    /// owners of inodes that were not in the table before
+    #[cfg(target_os = "linux")]
+    fn listeners(&mut self, scanner: &mut PortScanner) -> Result<Vec<Listener>> {
+        use crate::netns;
+
+        let sockets = scanner.socket_table()?;
+        if sockets
+            .iter()
+            .any(|socket| !self.owners.contains_key(&socket.inode))
+        {
+            let owners = netns::socket_owners(None)?;
+            for socket in &sockets {
+                self.owners
+                    .entry(socket.inode)
+                    .or_insert_with(|| owners.get(&socket.inode).copied());
+            }
+        }
+        let inodes: HashSet<u64> = sockets.iter().map(|socket| socket.inode).collect();
+        self.owners.retain(|inode, _| inodes.contains(inode));
+
+        Ok(sockets
+            .into_iter()
+            .map(|socket| Listener {
+                id: SocketId {
+                    port: socket.port,
+                    address: Some(socket.address),
+                    state: if socket.udp { "UNCONN" } else { "LISTEN" },
+                    inode: socket.inode,
+                    pid: self.owners.get(&socket.inode).copied().flatten(),
+                },
+                recv_q: socket.recv_q,
+                info: None,
+            })
+            .collect())
+    }
+
+    /// Without a socket table to read directly, every update is a full scan
+    #[cfg(not(target_os = "linux"))]
+    fn listeners(&mut self, scanner: &mut PortScanner) -> Result<Vec<Listener>> {
+        scanner.refresh();
+        Ok(scanner
+            .scan_all_ports()?
+            .into_iter()
+            .map(|info| Listener {
+                id: SocketId {
+                    port: info.port,
+                    address: None,
+                    state: "LISTEN",
+                    inode: 0,
+                    pid: info.pid,
+                },
+                recv_q: info.recv_q,
+                info: Some(info),
+            })
+            .collect())
+    }
+}
+
+/// Identities in `current` but not in `previous`, and the other way round
+fn diff<V>(
+    previous: &BTreeMap<SocketId, V>,
+    current: &BTreeSet<&SocketId>,
+) -> (Vec<SocketId>, Vec<SocketId>) {
+    let opened = current
+        .iter()
+        .filter(|id| !previous.contains_key(id))
+        .map(|id| (*id).clone())
+        .collect();
+    let closed = previous
+        .keys()
+        .filter(|id| !current.contains(id))
+        .cloned()
+        .collect();
+    (opened, closed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn socket(port: u16, inode: u64, pid: u32) -> SocketId {
+        SocketId {
+            port,
+            address: Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            state: "LISTEN",
+            inode,
+            pid: Some(pid),
+        }
+    }
+
+    #[test]
+    fn test_diff_by_socket_identity() {
+        let previous: BTreeMap<SocketId, ()> = [
+            (socket(3000, 100, 10), ()),
+            (socket(5432, 200, 20), ()),
+            (socket(8080, 300, 30), ()),
+        ]
+        .into_iter()
+        .collect();
+
+        // 3000 is unchanged, 5432 was rebound by a restarted server and 8080 closed
+        let restarted = socket(5432, 201, 21);
+        let unchanged = socket(3000, 100, 10);
+        let current: BTreeSet<&SocketId> = [&unchanged, &restarted].into_iter().collect();
+
+        let (opened, closed) = diff(&previous, &current);
+        assert_eq!(opened, vec![restarted]);
+        assert_eq!(closed, vec![socket(5432, 200, 20), socket(8080, 300, 30)]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_update_reports_new_and_closed_listeners() {
+        let mut scanner = PortScanner::new();
+        let mut snapshot = SocketSnapshot::new();
+        snapshot.update(&mut scanner).unwrap();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let changes = snapshot.update(&mut scanner).unwrap();
+        let opened = changes
+            .opened
+            .iter()
+            .find(|info| info.port == port)
+            .expect("new listener reported");
+        assert_eq!(opened.pid, Some(std::process::id()));
+        // Other tests bind ports concurrently, so only this one is checked
+        let again = snapshot.update(&mut scanner).unwrap();
+        assert!(again.opened.iter().all(|info| info.port != port));
+
+        drop(listener);
+        let changes = snapshot.update(&mut scanner).unwrap();
+        assert!(changes.closed.iter().any(|info| info.port == port));
+        assert!(snapshot.ports().all(|info| info.port != port));
+    }
+}
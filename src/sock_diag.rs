@@ -0,0 +1,359 @@
+use crate::netns::{ProcConnection, ProcSocket};
+use anyhow::{Context, Result};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// `SOCK_DIAG_BY_FAMILY` from `linux/sock_diag.h`
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+/// `TCP_ESTABLISHED`
+const TCP_ESTABLISHED: u32 = 1;
+/// `TCP_LISTEN`, the state of listening TCP sockets
+const TCP_LISTEN: u32 = 10;
+/// `TCP_CLOSE`, the state of unconnected UDP sockets
+const TCP_CLOSE: u32 = 7;
+
+const NLMSG_HEADER_LEN: usize = 16;
+/// `struct inet_diag_req_v2`
+const REQUEST_LEN: usize = 56;
+/// `struct inet_diag_msg`
+const DIAG_MESSAGE_LEN: usize = 72;
+
+const AF_INET: u8 = 2;
+const AF_INET6: u8 = 10;
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+/// A netlink reply as far as a dump is concerned
+#[derive(Debug, PartialEq)]
+enum Message<'a> {
+    /// Payload of one `inet_diag_msg`
+    Socket(&'a [u8]),
+    Done,
+    /// Negative errno reported by the kernel
+    Error(i32),
+}
+
+/// `nlmsghdr` followed by an `inet_diag_req_v2` that dumps every socket of a
+/// family and protocol in the given states
+fn dump_request(family: u8, protocol: u8, state: u32, sequence: u32) -> Vec<u8> {
+    let flags = (libc::NLM_F_REQUEST | libc::NLM_F_DUMP) as u16;
+    let mut request = Vec::with_capacity(NLMSG_HEADER_LEN + REQUEST_LEN);
+    request.extend(((NLMSG_HEADER_LEN + REQUEST_LEN) as u32).to_ne_bytes());
+    request.extend(SOCK_DIAG_BY_FAMILY.to_ne_bytes());
+    request.extend(flags.to_ne_bytes());
+    request.extend(sequence.to_ne_bytes());
+    request.extend(0u32.to_ne_bytes());
+    // sdiag_family, sdiag_protocol, idiag_ext, pad, idiag_states
+    request.extend([family, protocol, 0, 0]);
+    request.extend((1u32 << state).to_ne_bytes());
+    // Zeroed inet_diag_sockid: no filter on addresses or ports
+    request.resize(NLMSG_HEADER_LEN + REQUEST_LEN, 0);
+    request
+}
+
+/// Split a datagram from the kernel into its netlink messages
+fn parse_messages(buffer: &[u8]) -> Vec<Message<'_>> {
+    let mut messages = Vec::new();
+    let mut offset = 0;
+    while offset + NLMSG_HEADER_LEN <= buffer.len() {
+        let header = &buffer[offset..];
+        let len = u32::from_ne_bytes(header[0..4].try_into().unwrap()) as usize;
+        let kind = u16::from_ne_bytes(header[4..6].try_into().unwrap());
+        if len < NLMSG_HEADER_LEN || offset + len > buffer.len() {
+            break;
+        }
+        let payload = &header[NLMSG_HEADER_LEN..len];
+        messages.push(match kind as i32 {
+            libc::NLMSG_DONE => Message::Done,
+            libc::NLMSG_ERROR => {
+                let errno = payload
+                    .get(0..4)
+                    .map(|bytes| i32::from_ne_bytes(bytes.try_into().unwrap()))
+                    .unwrap_or(-libc::EIO);
+                // An error of 0 acknowledges the request
+                if errno == 0 {
+                    Message::Done
+                } else {
+                    Message::Error(errno)
+                }
+            }
+            _ => Message::Socket(payload),
+        });
+        // Messages are aligned to 4 bytes
+        offset += (len + 3) & !3;
+    }
+    messages
+}
+
+/// The socket described by an `inet_diag_msg`
+fn parse_diag_message(payload: &[u8], udp: bool) -> Option<ProcSocket> {
+    if payload.len() < DIAG_MESSAGE_LEN {
+        return None;
+    }
+    let word = |offset: usize| u32::from_ne_bytes(payload[offset..offset + 4].try_into().unwrap());
+
+    // inet_diag_sockid starts at offset 4 with the source port in network order
+    let port = u16::from_be_bytes([payload[4], payload[5]]);
+    let address = diag_address(payload[0], &payload[8..24])?;
+
+    Some(ProcSocket {
+        address,
+        port,
+        udp,
+        inode: u64::from(word(68)),
+        recv_q: u64::from(word(56)),
+        send_q: u64::from(word(60)),
+        uid: word(64),
+    })
+}
+
+/// The established socket described by an `inet_diag_msg`
+fn parse_diag_connection(payload: &[u8]) -> Option<ProcConnection> {
+    if payload.len() < DIAG_MESSAGE_LEN {
+        return None;
+    }
+    let word = |offset: usize| u32::from_ne_bytes(payload[offset..offset + 4].try_into().unwrap());
+
+    // Source and destination ports, then source and destination addresses
+    let local_port = u16::from_be_bytes([payload[4], payload[5]]);
+    let remote_port = u16::from_be_bytes([payload[6], payload[7]]);
+    Some(ProcConnection {
+        local: SocketAddr::new(diag_address(payload[0], &payload[8..24])?, local_port),
+        remote: SocketAddr::new(diag_address(payload[0], &payload[24..40])?, remote_port),
+        inode: u64::from(word(68)),
+        recv_q: u64::from(word(56)),
+        send_q: u64::from(word(60)),
+    })
+}
+
+/// An address field of `inet_diag_sockid`; IPv4 uses the first 4 of its 16 bytes
+fn diag_address(family: u8, bytes: &[u8]) -> Option<IpAddr> {
+    match family {
+        AF_INET => Some(IpAddr::V4(Ipv4Addr::new(
+            bytes[0], bytes[1], bytes[2], bytes[3],
+        ))),
+        AF_INET6 => {
+            let octets: [u8; 16] = bytes[..16].try_into().ok()?;
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+/// A `NETLINK_SOCK_DIAG` socket, closed on drop
+struct DiagSocket {
+    fd: std::os::fd::OwnedFd,
+}
+
+impl DiagSocket {
+    fn open() -> Result<Self> {
+        use std::os::fd::FromRawFd;
+
+        // SAFETY: plain socket(2) call; the descriptor is owned from here on
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_SOCK_DIAG,
+            )
+        };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error())
+                .context("Failed to open a sock_diag netlink socket");
+        }
+        Ok(Self {
+            // SAFETY: `fd` is a freshly opened descriptor nobody else owns
+            fd: unsafe { std::os::fd::OwnedFd::from_raw_fd(fd) },
+        })
+    }
+
+    /// Send a dump request and collect the sockets of the reply
+    fn dump<T>(&self, request: &[u8], parse: impl Fn(&[u8]) -> Option<T>) -> Result<Vec<T>> {
+        use std::os::fd::AsRawFd;
+
+        // SAFETY: an all-zero sockaddr_nl addresses the kernel
+        let mut kernel: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        kernel.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        // SAFETY: `request` and `kernel` outlive the call and the lengths match
+        let sent = unsafe {
+            libc::sendto(
+                self.fd.as_raw_fd(),
+                request.as_ptr().cast(),
+                request.len(),
+                0,
+                (&kernel as *const libc::sockaddr_nl).cast(),
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if sent < 0 {
+            return Err(std::io::Error::last_os_error())
+                .context("Failed to send sock_diag request");
+        }
+
+        let mut sockets = Vec::new();
+        let mut buffer = vec![0u8; 32 * 1024];
+        loop {
+            // SAFETY: the buffer is valid for writes of its full length
+            let received = unsafe {
+                libc::recv(
+                    self.fd.as_raw_fd(),
+                    buffer.as_mut_ptr().cast(),
+                    buffer.len(),
+                    0,
+                )
+            };
+            if received < 0 {
+                return Err(std::io::Error::last_os_error())
+                    .context("Failed to read sock_diag reply");
+            }
+            for message in parse_messages(&buffer[..received as usize]) {
+                match message {
+                    Message::Socket(payload) => sockets.extend(parse(payload)),
+                    Message::Done => return Ok(sockets),
+                    Message::Error(errno) => {
+                        return Err(std::io::Error::from_raw_os_error(-errno))
+                            .context("sock_diag request failed")
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Listening TCP and unconnected UDP sockets of the caller's network
+/// namespace, read with one `SOCK_DIAG_BY_FAMILY` dump per family and protocol
+pub fn listening_sockets() -> Result<Vec<ProcSocket>> {
+    let socket = DiagSocket::open()?;
+    let mut sockets = Vec::new();
+    let dumps = [
+        (AF_INET, IPPROTO_TCP, TCP_LISTEN),
+        (AF_INET6, IPPROTO_TCP, TCP_LISTEN),
+        (AF_INET, IPPROTO_UDP, TCP_CLOSE),
+        (AF_INET6, IPPROTO_UDP, TCP_CLOSE),
+    ];
+    for (sequence, (family, protocol, state)) in dumps.into_iter().enumerate() {
+        let request = dump_request(family, protocol, state, sequence as u32 + 1);
+        let udp = protocol == IPPROTO_UDP;
+        match socket.dump(&request, |payload| parse_diag_message(payload, udp)) {
+            Ok(found) => sockets.extend(found),
+            // Without IPv6 there is simply nothing to dump
+            Err(_) if family == AF_INET6 => {}
+            Err(e) if protocol == IPPROTO_UDP => {
+                return Err(
+                    e.context("UDP sockets cannot be read over netlink (is udp_diag loaded?)")
+                )
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(sockets)
+}
+
+/// Established TCP sockets of the caller's network namespace
+pub fn established_sockets() -> Result<Vec<ProcConnection>> {
+    let socket = DiagSocket::open()?;
+    let mut connections = Vec::new();
+    for (sequence, family) in [AF_INET, AF_INET6].into_iter().enumerate() {
+        let request = dump_request(family, IPPROTO_TCP, TCP_ESTABLISHED, sequence as u32 + 1);
+        match socket.dump(&request, parse_diag_connection) {
+            Ok(found) => connections.extend(found),
+            // Without IPv6 there is simply nothing to dump
+            Err(_) if family == AF_INET6 => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(connections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An `inet_diag_msg` for a listener on 127.0.0.1:8080
+    fn listener_message() -> Vec<u8> {
+        let mut payload = vec![0u8; DIAG_MESSAGE_LEN];
+        payload[0] = AF_INET;
+        payload[1] = TCP_LISTEN as u8;
+        payload[4..6].copy_from_slice(&8080u16.to_be_bytes());
+        payload[8..12].copy_from_slice(&[127, 0, 0, 1]);
+        payload[56..60].copy_from_slice(&3u32.to_ne_bytes());
+        payload[60..64].copy_from_slice(&511u32.to_ne_bytes());
+        payload[64..68].copy_from_slice(&1000u32.to_ne_bytes());
+        payload[68..72].copy_from_slice(&41234u32.to_ne_bytes());
+
+        let mut message = Vec::new();
+        message.extend(((NLMSG_HEADER_LEN + DIAG_MESSAGE_LEN) as u32).to_ne_bytes());
+        message.extend(SOCK_DIAG_BY_FAMILY.to_ne_bytes());
+        message.extend([0u8; 10]);
+        message.extend(payload);
+        message
+    }
+
+    #[test]
+    fn test_parse_diag_reply() {
+        let mut buffer = listener_message();
+        // NLMSG_DONE with its 4-byte status
+        buffer.extend(20u32.to_ne_bytes());
+        buffer.extend((libc::NLMSG_DONE as u16).to_ne_bytes());
+        buffer.extend([0u8; 14]);
+
+        let messages = parse_messages(&buffer);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1], Message::Done);
+        let Message::Socket(payload) = messages[0] else {
+            panic!("expected a socket message");
+        };
+        assert_eq!(
+            parse_diag_message(payload, false),
+            Some(ProcSocket {
+                address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                port: 8080,
+                udp: false,
+                inode: 41234,
+                recv_q: 3,
+                send_q: 511,
+                uid: 1000
+            })
+        );
+
+        assert_eq!(dump_request(AF_INET, IPPROTO_TCP, TCP_LISTEN, 1).len(), 72);
+    }
+
+    #[test]
+    fn test_parse_diag_connection() {
+        // 127.0.0.1:41668 -> 127.0.0.1:8080
+        let mut payload = vec![0u8; DIAG_MESSAGE_LEN];
+        payload[0] = AF_INET;
+        payload[1] = TCP_ESTABLISHED as u8;
+        payload[4..6].copy_from_slice(&41668u16.to_be_bytes());
+        payload[6..8].copy_from_slice(&8080u16.to_be_bytes());
+        payload[8..12].copy_from_slice(&[127, 0, 0, 1]);
+        payload[24..28].copy_from_slice(&[127, 0, 0, 1]);
+        payload[60..64].copy_from_slice(&12u32.to_ne_bytes());
+        payload[68..72].copy_from_slice(&41299u32.to_ne_bytes());
+
+        assert_eq!(
+            parse_diag_connection(&payload),
+            Some(ProcConnection {
+                local: "127.0.0.1:41668".parse().unwrap(),
+                remote: "127.0.0.1:8080".parse().unwrap(),
+                inode: 41299,
+                recv_q: 0,
+                send_q: 12,
+            })
+        );
+    }
+
+    #[test]
+    fn test_own_listener_over_netlink() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let sockets = listening_sockets().unwrap();
+        let socket = sockets
+            .iter()
+            .find(|socket| socket.port == port && !socket.udp)
+            .expect("listener found over netlink");
+        assert_eq!(socket.address, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_ne!(socket.inode, 0);
+    }
+}
@@ -0,0 +1,365 @@
+use crate::containers;
+use crate::scanner::PortInfo;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::process::Command;
+
+/// Units whose processes are terminals; whatever runs in the shell inside
+/// belongs to the user, not to the unit
+const TERMINAL_UNITS: &[&str] = &[
+    "terminal",
+    "konsole",
+    "kitty",
+    "alacritty",
+    "wezterm",
+    "tilix",
+    "xterm",
+    "tmux",
+];
+
+/// Whether a unit belongs to the system manager or the user's own manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnitScope {
+    System,
+    User,
+}
+
+impl fmt::Display for UnitScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitScope::System => write!(f, "system"),
+            UnitScope::User => write!(f, "user"),
+        }
+    }
+}
+
+/// The systemd unit that owns a listener
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Unit {
+    pub name: String,
+    pub scope: UnitScope,
+    /// Service started by a socket unit; set when systemd itself holds the port
+    pub activates: Option<String>,
+    /// Main process of the service (of the activated service for socket units)
+    pub main_pid: Option<u32>,
+}
+
+impl Unit {
+    /// Unit name without its type suffix, e.g. `sshd` for `sshd.service`
+    pub fn stem(&self) -> &str {
+        self.name
+            .rsplit_once('.')
+            .map_or(self.name.as_str(), |(stem, _)| stem)
+    }
+
+    /// Units to stop or restart together: a socket unit keeps the port open
+    /// (and starts the service again) unless both are handled
+    pub fn control_targets(&self) -> Vec<&str> {
+        let mut targets = vec![self.name.as_str()];
+        targets.extend(self.activates.as_deref());
+        targets
+    }
+
+    /// Run `systemctl [--user] <action>` on the unit
+    pub fn systemctl(&self, action: &str) -> Result<()> {
+        let mut command = Command::new("systemctl");
+        if self.scope == UnitScope::User {
+            command.arg("--user");
+        }
+        let output = command
+            .arg(action)
+            .args(self.control_targets())
+            .output()
+            .context("Failed to execute systemctl")?;
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "systemctl {action} failed: {message}",
+                message = String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.activates {
+            Some(service) => write!(f, "{} -> {service} ({})", self.name, self.scope),
+            None => write!(f, "{} ({})", self.name, self.scope),
+        }
+    }
+}
+
+/// The service unit a process runs in, from its `/proc/<pid>/cgroup`.
+///
+/// Login sessions, terminals and containers run in transient `.scope` units,
+/// user managers in `user@UID.service`, and applications started from a
+/// desktop in `app-*` units; none of them is a service that can be stopped or
+/// restarted on its own, so they are ignored, as are terminal emulators.
+/// Callers still have to check that the process is the unit's main process
+/// (see `unit_for_pid`).
+pub fn unit_from_cgroup(cgroup: &str) -> Option<Unit> {
+    // The unified hierarchy (`0::`) or the v1 systemd controller
+    let path = cgroup.lines().find_map(|line| {
+        let (_, rest) = line.split_once(':')?;
+        let (controllers, path) = rest.split_once(':')?;
+        (controllers.is_empty() || controllers == "name=systemd").then_some(path)
+    })?;
+
+    let name = path
+        .split('/')
+        .rev()
+        .find(|component| component.ends_with(".service") && !component.starts_with("user@"))?;
+    let lowercase = name.to_lowercase();
+    if name.starts_with("app-")
+        || TERMINAL_UNITS
+            .iter()
+            .any(|terminal| lowercase.contains(terminal))
+    {
+        return None;
+    }
+    let scope = if path.contains("/user@") {
+        UnitScope::User
+    } else {
+        UnitScope::System
+    };
+
+    Some(Unit {
+        name: name.to_string(),
+        scope,
+        activates: None,
+        main_pid: None,
+    })
+}
+
+/// A listening socket unit: (port, socket unit, activated service)
+type SocketUnit = (u16, String, Option<String>);
+
+/// Listening socket units from `systemctl list-sockets --no-legend`
+pub fn parse_list_sockets(output: &str) -> Vec<SocketUnit> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let listen = fields.next()?;
+            let unit = fields.next()?;
+            let activates = fields
+                .next()
+                .map(|services| services.trim_end_matches(',').to_string());
+            let port = listen.rsplit_once(':')?.1.parse().ok()?;
+            Some((port, unit.to_string(), activates))
+        })
+        .collect()
+}
+
+/// `MainPID` of a running unit from `systemctl show`
+fn main_pid(name: &str, scope: UnitScope) -> Option<u32> {
+    let mut command = Command::new("systemctl");
+    if scope == UnitScope::User {
+        command.arg("--user");
+    }
+    let output = command
+        .args(["show", "--property", "MainPID", "--value", name])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&pid| pid != 0)
+}
+
+fn list_sockets(scope: UnitScope) -> Vec<SocketUnit> {
+    let mut command = Command::new("systemctl");
+    if scope == UnitScope::User {
+        command.arg("--user");
+    }
+    command
+        .args(["list-sockets", "--all", "--full", "--no-legend"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| parse_list_sockets(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+/// Fill in the systemd unit of every listener. Ports held by systemd itself
+/// are matched to their socket unit; container proxies are left to
+/// `containers::annotate` since their unit is the container engine.
+pub fn annotate(ports: &mut [PortInfo]) {
+    let mut socket_units: Vec<(UnitScope, Vec<SocketUnit>)> = Vec::new();
+    let mut units_by_pid: HashMap<u32, Option<Unit>> = HashMap::new();
+
+    for port_info in ports {
+        if containers::is_proxy(&port_info.process_name) {
+            continue;
+        }
+
+        if port_info.process_name == "systemd" {
+            // PID 1 is the system manager; any other systemd is a user manager
+            let scope = if port_info.pid == Some(1) {
+                UnitScope::System
+            } else {
+                UnitScope::User
+            };
+            let listed = match socket_units.iter().position(|(listed, _)| *listed == scope) {
+                Some(index) => &socket_units[index].1,
+                None => {
+                    socket_units.push((scope, list_sockets(scope)));
+                    &socket_units[socket_units.len() - 1].1
+                }
+            };
+            port_info.systemd_unit = listed
+                .iter()
+                .find(|(port, _, _)| *port == port_info.port)
+                .map(|(_, name, activates)| Unit {
+                    name: name.clone(),
+                    scope,
+                    activates: activates.clone(),
+                    main_pid: activates
+                        .as_deref()
+                        .and_then(|service| main_pid(service, scope)),
+                });
+            continue;
+        }
+
+        // One `systemctl show` per process, however many ports it has
+        port_info.systemd_unit = port_info.pid.and_then(|pid| {
+            units_by_pid
+                .entry(pid)
+                .or_insert_with(|| unit_for_pid(pid))
+                .clone()
+        });
+    }
+}
+
+/// The service unit of a running process, when the process is the unit's
+/// main process or one of its descendants (such as a worker). Anything else
+/// merely shares the cgroup and stopping the unit is not what was asked for.
+#[cfg(target_os = "linux")]
+pub fn unit_for_pid(pid: u32) -> Option<Unit> {
+    let cgroup = std::fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()?;
+    let unit = unit_from_cgroup(&cgroup)?;
+    let main = main_pid(&unit.name, unit.scope)?;
+    is_same_or_descendant(pid, main).then_some(Unit {
+        main_pid: Some(main),
+        ..unit
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn is_same_or_descendant(mut pid: u32, ancestor: u32) -> bool {
+    // Bounded in case the chain changes while it is walked
+    for _ in 0..64 {
+        if pid == ancestor {
+            return true;
+        }
+        let Some(parent) = std::fs::read_to_string(format!("/proc/{pid}/stat"))
+            .ok()
+            .as_deref()
+            .and_then(parent_from_stat)
+        else {
+            return false;
+        };
+        if parent <= 1 {
+            return false;
+        }
+        pid = parent;
+    }
+    false
+}
+
+/// Parent PID from `/proc/<pid>/stat`; the command name in parentheses may
+/// itself contain spaces and parentheses
+pub fn parent_from_stat(stat: &str) -> Option<u32> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn unit_for_pid(_pid: u32) -> Option<Unit> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_from_cgroup() {
+        let system = unit_from_cgroup("0::/system.slice/nginx.service\n").unwrap();
+        assert_eq!(system.name, "nginx.service");
+        assert_eq!(system.scope, UnitScope::System);
+
+        let user = unit_from_cgroup(
+            "0::/user.slice/user-1000.slice/user@1000.service/app.slice/api.service\n",
+        )
+        .unwrap();
+        assert_eq!(user.to_string(), "api.service (user)");
+
+        // cgroup v1 only names the unit in the systemd controller
+        let v1 = "4:memory:/system.slice/redis.service\n1:name=systemd:/system.slice/redis-server.service\n";
+        assert_eq!(unit_from_cgroup(v1).unwrap().name, "redis-server.service");
+
+        // Terminals, sessions, desktop apps and containers are not services
+        for cgroup in [
+            "0::/user.slice/user-1000.slice/user@1000.service/app.slice/vte-spawn-1.scope\n",
+            "0::/user.slice/user-1000.slice/user@1000.service/app.slice/gnome-terminal-server.service\n",
+            "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-org.kde.konsole@0a1b.service\n",
+            "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-firefox-1234.service\n",
+            "0::/user.slice/user-1000.slice/session-3.scope\n",
+            "0::/system.slice/docker-4f1c2a9d.scope\n",
+            "0::/\n",
+        ] {
+            assert_eq!(unit_from_cgroup(cgroup), None, "{cgroup}");
+        }
+    }
+
+    #[test]
+    fn test_parent_from_stat() {
+        assert_eq!(
+            parent_from_stat("4242 (node) S 4200 4242 4200 0 -1 4194560"),
+            Some(4200)
+        );
+        assert_eq!(
+            parent_from_stat("77 (tmux: server (1)) S 1 77 77 0 -1"),
+            Some(1)
+        );
+        assert_eq!(parent_from_stat("garbage"), None);
+    }
+
+    #[test]
+    fn test_parse_list_sockets() {
+        let output = "\
+[::]:22                      sshd.socket           sshd.service
+0.0.0.0:631                  cups.socket           cups.service
+/run/dbus/system_bus_socket  dbus.socket           dbus.service
+127.0.0.1:9000               php-fpm.socket
+";
+        let sockets = parse_list_sockets(output);
+
+        assert_eq!(sockets.len(), 3);
+        assert_eq!(
+            sockets[0],
+            (
+                22,
+                "sshd.socket".to_string(),
+                Some("sshd.service".to_string())
+            )
+        );
+        assert_eq!(sockets[2], (9000, "php-fpm.socket".to_string(), None));
+
+        let unit = Unit {
+            name: "sshd.socket".to_string(),
+            scope: UnitScope::System,
+            activates: Some("sshd.service".to_string()),
+            main_pid: None,
+        };
+        assert_eq!(unit.control_targets(), vec!["sshd.socket", "sshd.service"]);
+        assert_eq!(unit.stem(), "sshd");
+    }
+}
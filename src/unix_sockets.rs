@@ -0,0 +1,96 @@
+use anyhow::Result;
+
+/// `__SO_ACCEPTCON`: the socket is listening for connections
+const ACCEPT_CONNECTIONS: u32 = 0x10000;
+
+/// A listening Unix domain socket from `/proc/net/unix`
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnixSocket {
+    /// Filesystem path, or `@name` for abstract sockets
+    pub path: String,
+    pub kind: &'static str,
+    pub inode: u64,
+}
+
+/// Parse one row of `/proc/net/unix`, keeping listening sockets with a name:
+/// `Num RefCount Protocol Flags Type St Inode Path`
+pub fn parse_proc_net_unix_line(line: &str) -> Option<UnixSocket> {
+    let mut fields = line.split_whitespace();
+    let _num = fields.next()?;
+    let _ref_count = fields.next()?;
+    let _protocol = fields.next()?;
+    let flags = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let kind = match fields.next()? {
+        "0001" => "stream",
+        "0002" => "dgram",
+        "0005" => "seqpacket",
+        _ => return None,
+    };
+    let _state = fields.next()?;
+    let inode = fields.next()?.parse().ok()?;
+    // Paths may contain spaces, so take the rest of the line
+    let path = fields.collect::<Vec<_>>().join(" ");
+
+    if flags & ACCEPT_CONNECTIONS == 0 || path.is_empty() {
+        return None;
+    }
+    Some(UnixSocket { path, kind, inode })
+}
+
+/// Listening Unix domain sockets of the current network namespace
+#[cfg(target_os = "linux")]
+pub fn listening() -> Result<Vec<UnixSocket>> {
+    use anyhow::Context;
+
+    let content =
+        std::fs::read_to_string("/proc/net/unix").context("Failed to read /proc/net/unix")?;
+    Ok(content
+        .lines()
+        .skip(1)
+        .filter_map(parse_proc_net_unix_line)
+        .collect())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn listening() -> Result<Vec<UnixSocket>> {
+    Err(anyhow::anyhow!(
+        "Unix socket listing is only supported on Linux"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_net_unix_line() {
+        let postgres = "0000000000000000: 00000002 00000000 00010000 0001 01 23456 /run/postgresql/.s.PGSQL.5432";
+        assert_eq!(
+            parse_proc_net_unix_line(postgres),
+            Some(UnixSocket {
+                path: "/run/postgresql/.s.PGSQL.5432".to_string(),
+                kind: "stream",
+                inode: 23456
+            })
+        );
+
+        let spaced = "0000000000000000: 00000002 00000000 00010000 0005 01 777 /tmp/my app/ls.sock";
+        let socket = parse_proc_net_unix_line(spaced).unwrap();
+        assert_eq!(socket.path, "/tmp/my app/ls.sock");
+        assert_eq!(socket.kind, "seqpacket");
+
+        let abstract_socket =
+            "0000000000000000: 00000002 00000000 00010000 0001 01 888 @/tmp/.X11-unix/X0";
+        assert_eq!(
+            parse_proc_net_unix_line(abstract_socket).unwrap().path,
+            "@/tmp/.X11-unix/X0"
+        );
+
+        // Connected and unnamed sockets are not listeners
+        let connected =
+            "0000000000000000: 00000003 00000000 00000000 0001 03 999 /run/dbus/system_bus_socket";
+        assert_eq!(parse_proc_net_unix_line(connected), None);
+        let unnamed = "0000000000000000: 00000002 00000000 00010000 0001 01 1000";
+        assert_eq!(parse_proc_net_unix_line(unnamed), None);
+    }
+}
@@ -87,3 +87,731 @@ fn test_init_command() {
     // Check that config file was created
     assert!(temp_dir.path().join(".portman.yaml").exists());
 }
+
+#[test]
+fn test_init_registers_project() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = TempDir::new().unwrap();
+
+    Command::cargo_bin("portman")
+        .unwrap()
+        .current_dir(temp_dir.path())
+        .env("XDG_DATA_HOME", data_dir.path())
+        .args(["init", "--force"])
+        .assert()
+        .success();
+
+    // Registered for `conflicts --registered`
+    let registry = data_dir.path().join("portman").join("projects.json");
+    let registered = std::fs::read_to_string(registry).unwrap();
+    let project = temp_dir.path().canonicalize().unwrap();
+    assert!(registered.contains(project.to_string_lossy().as_ref()));
+}
+
+#[test]
+fn test_kill_dry_run_json() {
+    let mut cmd = Command::cargo_bin("portman").unwrap();
+    cmd.args(["kill", "60002", "--dry-run", "--format", "json"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"dry_run\": true"))
+        .stdout(predicate::str::contains("Port not in use"));
+}
+
+#[test]
+fn test_free_dry_run_json_is_always_a_plan() {
+    let output = Command::cargo_bin("portman")
+        .unwrap()
+        .args(["--dry-run", "free", "--set", "db", "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let plan: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(plan["dry_run"], true);
+
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["--dry-run", "free", "--set", "nope", "--format", "json"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("Unknown port set 'nope'"));
+}
+
+#[test]
+fn test_kill_dry_run_csv_lists_skipped_ports() {
+    let mut cmd = Command::cargo_bin("portman").unwrap();
+    cmd.args(["kill", "60004", "--dry-run", "--format", "csv"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "PID,Process,Ports,TreeRoot,Signals,Skipped",
+        ))
+        .stdout(predicate::str::contains(
+            ",\"\",\"60004\",,,\"Port not in use\"",
+        ));
+}
+
+#[test]
+fn test_config_show_resolved_sources() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    let user_dir = temp_dir.path().join("xdg").join("portman");
+    std::fs::create_dir_all(&user_dir).unwrap();
+    std::fs::write(user_dir.join("config.toml"), "kill_timeout_ms = 2000\n").unwrap();
+    std::fs::write(
+        temp_dir.path().join(".portman.yaml"),
+        "ports: [3000]\nkill_timeout_ms: 250\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("portman").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path().join("xdg"))
+        .args(["config", "show", "--resolved", "--format", "json"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"value\": \"250\""))
+        .stdout(predicate::str::contains("project ("));
+}
+
+#[test]
+fn test_broken_config_only_blocks_commands_that_use_it() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(temp_dir.path().join(".portman.yaml"), "ports: [oops\n").unwrap();
+    let portman = || {
+        let mut cmd = Command::cargo_bin("portman").unwrap();
+        cmd.current_dir(temp_dir.path())
+            .env("XDG_CONFIG_HOME", temp_dir.path().join("xdg"))
+            .env("XDG_DATA_HOME", temp_dir.path().join("data"));
+        cmd
+    };
+
+    portman()
+        .args(["check", "61301"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to load config"));
+    portman()
+        .args(["config", "show"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ports: [oops"));
+    portman().args(["init", "--force"]).assert().success();
+    portman().args(["check", "61301"]).assert().success();
+}
+
+#[test]
+fn test_list_service_filter_csv() {
+    let mut cmd = Command::cargo_bin("portman").unwrap();
+    cmd.args(["list", "--service", "postgres", "--format", "csv"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("MemoryUsage,Service"));
+}
+
+#[test]
+fn test_conflicts_between_projects() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    for (name, port) in [("billing", 61080), ("search", 61080), ("web", 61090)] {
+        let dir = temp_dir.path().join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(".portman.yaml"),
+            format!("project: {name}\nports: [{port}]\n"),
+        )
+        .unwrap();
+    }
+
+    let mut cmd = Command::cargo_bin("portman").unwrap();
+    cmd.args(["conflicts", "--format", "json"])
+        .arg(temp_dir.path());
+
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("\"port\": 61080"))
+        .stdout(predicate::str::contains("\"suggested_port\": 61081"))
+        .stdout(predicate::str::contains("61090").not());
+}
+
+#[test]
+fn test_conflicts_csv_quotes_fields() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["billing", "search"] {
+        let dir = temp_dir.path().join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(".portman.yaml"),
+            format!("project: '{name}, \"v2\"'\nports: [61180]\n"),
+        )
+        .unwrap();
+    }
+
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["conflicts", "--format", "csv"])
+        .arg(temp_dir.path())
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "61180,\"billing, \"\"v2\"\"\",\"\",\"",
+        ));
+}
+
+#[test]
+fn test_graph_csv_quotes_fields() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let _client = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+    let _server = listener.accept().unwrap();
+
+    // Text fields are quoted, numbers are not
+    let pid = std::process::id();
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["graph", "--format", "csv"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Port,Service,ServerPID,ServerProcess,Client,Connections",
+        ))
+        .stdout(predicate::str::contains(format!("{port},\"\",{pid},\"")))
+        .stdout(predicate::str::contains(format!("({pid})\",1")));
+}
+
+#[test]
+fn test_reserve_blocks_other_projects() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let owner = temp_dir.path().join("owner");
+    let other = temp_dir.path().join("other");
+    std::fs::create_dir_all(&owner).unwrap();
+    std::fs::create_dir_all(&other).unwrap();
+
+    let portman = |dir: &std::path::Path| {
+        let mut cmd = Command::cargo_bin("portman").unwrap();
+        cmd.current_dir(dir).env("XDG_DATA_HOME", &data_dir);
+        cmd
+    };
+
+    portman(&owner)
+        .args(["reserve", "61234", "--ttl", "1h"])
+        .assert()
+        .success();
+
+    portman(&other)
+        .args(["check", "61234"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("reserved by owner"));
+
+    portman(&owner).args(["check", "61234"]).assert().success();
+
+    portman(&other)
+        .args(["release", "61234"])
+        .assert()
+        .failure();
+    portman(&owner)
+        .args(["release", "61234"])
+        .assert()
+        .success();
+    portman(&other).args(["check", "61234"]).assert().success();
+}
+
+#[test]
+fn test_hold_answers_http_and_releases() {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    let mut holder = std::process::Command::new(assert_cmd::cargo::cargo_bin("portman"))
+        .args(["hold", "61321", "--http-status", "503", "--duration", "10s"])
+        .env("XDG_DATA_HOME", temp_dir.path())
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let started = Instant::now();
+    let mut stream = loop {
+        match TcpStream::connect("127.0.0.1:61321") {
+            Ok(stream) => break stream,
+            Err(_) if started.elapsed() < Duration::from_secs(5) => {
+                std::thread::sleep(Duration::from_millis(50))
+            }
+            Err(e) => panic!("hold did not bind: {e}"),
+        }
+    };
+    stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 503 Service Unavailable"));
+
+    let agent_file = temp_dir
+        .path()
+        .join("portman")
+        .join("agents")
+        .join(format!("{}.json", holder.id()));
+    assert!(agent_file.exists());
+
+    holder.kill().unwrap();
+    holder.wait().unwrap();
+
+    // Even without a clean shutdown the kernel frees the port with the process
+    let started = Instant::now();
+    while let Err(e) = std::net::TcpListener::bind("127.0.0.1:61321") {
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "port not released after kill: {e}"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn test_hold_rejects_invalid_http_status() {
+    for status in ["99", "600"] {
+        Command::cargo_bin("portman")
+            .unwrap()
+            .args(["hold", "61323", "--http-status", status])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("100..=599"));
+    }
+}
+
+#[test]
+fn test_kill_protects_other_ports_of_the_same_process() {
+    use std::net::TcpStream;
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(
+        temp_dir.path().join(".portman.yaml"),
+        "protected:\n  ports: [61341]\n",
+    )
+    .unwrap();
+    let mut holder = std::process::Command::new(assert_cmd::cargo::cargo_bin("portman"))
+        .args(["hold", "61341", "61342", "--duration", "10s"])
+        .env("XDG_DATA_HOME", temp_dir.path().join("data"))
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let started = Instant::now();
+    while TcpStream::connect("127.0.0.1:61342").is_err() {
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "hold did not bind"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let mut cmd = Command::cargo_bin("portman").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path().join("xdg"))
+        .args(["kill", "61342", "--dry-run", "--format", "json"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Port 61341 is protected by config",
+    ));
+
+    holder.kill().unwrap();
+    holder.wait().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_inspect_pid_json_redacts_environment() {
+    use tempfile::TempDir;
+
+    let temp_dir = TempDir::new().unwrap();
+    std::fs::write(
+        temp_dir.path().join(".portman.yaml"),
+        "project: inspected\nports: [3000]\n",
+    )
+    .unwrap();
+    let mut child = std::process::Command::new("sleep")
+        .arg("10")
+        .current_dir(temp_dir.path())
+        .env("PORTMAN_TEST_SECRET", "hunter2")
+        .spawn()
+        .unwrap();
+
+    let output = Command::cargo_bin("portman")
+        .unwrap()
+        .args([
+            "inspect",
+            &child.id().to_string(),
+            "--pid",
+            "--format",
+            "json",
+        ])
+        .output()
+        .unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["pid"], child.id());
+    assert_eq!(report["name"], "sleep");
+    assert_eq!(report["project"]["name"], "inspected");
+    assert_eq!(report["environment"]["PORTMAN_TEST_SECRET"], "<redacted>");
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("hunter2"));
+}
+
+#[test]
+fn test_inspect_free_port_requires_pid_flag() {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+        .to_string();
+
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["inspect", &port])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "Nothing is listening on port {port}; use --pid"
+        )));
+}
+
+#[test]
+fn test_list_project_filter() {
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    let data_dir = TempDir::new().unwrap();
+    let repo = TempDir::new().unwrap();
+    std::fs::create_dir(repo.path().join(".git")).unwrap();
+    let mut holder = std::process::Command::new(assert_cmd::cargo::cargo_bin("portman"))
+        .args(["hold", "61322", "--duration", "10s"])
+        .current_dir(repo.path())
+        .env("XDG_DATA_HOME", data_dir.path())
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let started = Instant::now();
+    while std::net::TcpStream::connect("127.0.0.1:61322").is_err() {
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "hold did not bind"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let list = |dir: &std::path::Path| {
+        let output = Command::cargo_bin("portman")
+            .unwrap()
+            .args(["list", "--project-dir", ".", "--format", "json"])
+            .current_dir(dir)
+            .env("XDG_DATA_HOME", data_dir.path())
+            .output()
+            .unwrap();
+        serde_json::from_slice::<Vec<serde_json::Value>>(&output.stdout).unwrap()
+    };
+    let in_repo = list(repo.path());
+    let elsewhere = list(data_dir.path());
+    holder.kill().unwrap();
+    holder.wait().unwrap();
+
+    let held = in_repo
+        .iter()
+        .find(|port| port["port"] == 61322)
+        .expect("held port listed for its project");
+    let repo_name = repo.path().file_name().unwrap().to_string_lossy();
+    assert_eq!(held["project"], repo_name.as_ref());
+    assert!(!elsewhere.iter().any(|port| port["port"] == 61322));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_list_netns_by_pid() {
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    let data_dir = TempDir::new().unwrap();
+    let mut holder = std::process::Command::new(assert_cmd::cargo::cargo_bin("portman"))
+        .args(["hold", "61323", "--duration", "10s"])
+        .env("XDG_DATA_HOME", data_dir.path())
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let started = Instant::now();
+    while std::net::TcpStream::connect("127.0.0.1:61323").is_err() {
+        assert!(
+            started.elapsed() < Duration::from_secs(5),
+            "hold did not bind"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let output = Command::cargo_bin("portman")
+        .unwrap()
+        .args([
+            "list",
+            "--netns",
+            &holder.id().to_string(),
+            "--format",
+            "json",
+        ])
+        .env("XDG_DATA_HOME", data_dir.path())
+        .output()
+        .unwrap();
+    holder.kill().unwrap();
+    holder.wait().unwrap();
+
+    let ports: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let held = ports
+        .iter()
+        .find(|port| port["port"] == 61323)
+        .expect("held port listed in its namespace");
+    assert_eq!(held["pid"], holder.id());
+    assert!(held["netns"]["id"].as_u64().is_some());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_list_unix_sockets_and_kill_dry_run() {
+    use tempfile::TempDir;
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("portman-test.sock");
+    let _listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    let path = path.to_string_lossy().into_owned();
+
+    let output = Command::cargo_bin("portman")
+        .unwrap()
+        .args(["list", "--unix", "--format", "json"])
+        .output()
+        .unwrap();
+    let sockets: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let socket = sockets
+        .iter()
+        .find(|socket| socket["path"] == path.as_str())
+        .expect("test socket listed");
+    assert_eq!(socket["pid"], std::process::id());
+    assert_eq!(socket["type"], "stream");
+
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["--dry-run", "kill", "--socket", &path])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "PID {}",
+            std::process::id()
+        )));
+}
+
+#[test]
+fn test_list_unix_rejects_port_filters() {
+    for filter in [
+        &["--range", "3000-4000"][..],
+        &["--service", "redis"],
+        &["--project", "."],
+    ] {
+        Command::cargo_bin("portman")
+            .unwrap()
+            .args(["list", "--unix"])
+            .args(filter)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_kill_missing_socket_reports_it() {
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["kill", "--force", "--socket", "/nonexistent/portman.sock"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "No process found on socket /nonexistent/portman.sock",
+        ))
+        .stderr(predicate::str::contains(
+            "Socket /nonexistent/portman.sock: No process found",
+        ));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_list_backends_agree_on_own_listener() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    for backend in ["netlink", "procfs", "netstat"] {
+        let output = Command::cargo_bin("portman")
+            .unwrap()
+            .args(["--backend", backend, "list", "--format", "json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{backend} backend failed");
+        let ports: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+        let own = ports
+            .iter()
+            .find(|entry| entry["port"] == port)
+            .unwrap_or_else(|| panic!("{backend} backend misses the test listener"));
+        assert_eq!(own["pid"], std::process::id(), "{backend}");
+    }
+}
+
+#[test]
+fn test_check_probe_reports_each_address() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port().to_string();
+
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args([
+            "check",
+            "--probe",
+            "--bind",
+            "127.0.0.1",
+            "--protocol",
+            "both",
+            &port,
+        ])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(format!(
+            "TCP 127.0.0.1:{port} is in use"
+        )))
+        .stdout(predicate::str::contains(format!(
+            "UDP 127.0.0.1:{port} is free"
+        )));
+}
+
+#[test]
+fn test_check_suggests_free_port_env() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let output = Command::cargo_bin("portman")
+        .unwrap()
+        .args([
+            "check",
+            "--suggest",
+            "2",
+            "--suggest-env",
+            &port.to_string(),
+        ])
+        .output()
+        .unwrap();
+    // A free alternative was found, so the port can be used
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Free ports nearby"), "{stderr}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let suggested: u16 = stdout
+        .trim_end()
+        .strip_prefix("PORT=")
+        .expect("only the PORT line on stdout")
+        .parse()
+        .unwrap();
+    assert!(suggested > port);
+}
+
+#[test]
+fn test_check_remote_targets_on_loopback() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["check", &format!("127.0.0.1:{port}")])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "127.0.0.1:{port} is reachable over TCP"
+        )));
+
+    // A plain TCP server does not answer a TLS handshake
+    let server = std::thread::spawn(move || drop(listener.accept()));
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["check", "--host", "127.0.0.1", "--tls", "--timeout", "1s"])
+        .arg(port.to_string())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "127.0.0.1:{port} is not reachable over TLS"
+        )));
+    server.join().unwrap();
+}
+
+#[test]
+fn test_check_rejects_mixed_local_and_remote() {
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["check", "60000", "127.0.0.1:60001"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Local ports and remote targets cannot be checked together",
+        ));
+}
+
+#[test]
+fn test_check_rejects_remote_flags_for_local_ports() {
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["check", "60000", "--tls"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--tls only applies to remote targets",
+        ));
+
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["check", "60000", "--timeout", "1s"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--timeout only applies to remote targets",
+        ));
+
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["check", "127.0.0.1:60000", "--tls", "--protocol", "udp"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "cannot be used with --protocol udp",
+        ));
+
+    // The timeout is validated before anything is checked
+    Command::cargo_bin("portman")
+        .unwrap()
+        .args(["check", "127.0.0.1:60000", "--timeout", "soon"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("reachable").not())
+        .stderr(predicate::str::contains("Invalid duration"));
+}